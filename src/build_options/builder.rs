@@ -0,0 +1,173 @@
+//!
+//! The ZKsync LLVM build options builder.
+//!
+
+use crate::build_options::BuildOptions;
+use crate::build_type::BuildType;
+use crate::ccache_variant::CcacheVariant;
+use crate::host::Host;
+use crate::llvm_project::LLVMProject;
+use crate::platforms::Platform;
+use crate::sanitizer::Sanitizer;
+use crate::target_env::TargetEnv;
+use crate::target_triple::TargetTriple;
+
+///
+/// The LLVM framework build options builder.
+///
+#[derive(Debug, Default, Clone)]
+pub struct BuildOptionsBuilder {
+    /// The options being built.
+    options: BuildOptions,
+}
+
+impl From<BuildOptions> for BuildOptionsBuilder {
+    fn from(options: BuildOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl BuildOptionsBuilder {
+    ///
+    /// Sets the LLVM build type.
+    ///
+    pub fn build_type(mut self, build_type: BuildType) -> Self {
+        self.options.build_type = build_type;
+        self
+    }
+
+    ///
+    /// Sets the target environment.
+    ///
+    pub fn target_env(mut self, target_env: TargetEnv) -> Self {
+        self.options.target_env = target_env;
+        self
+    }
+
+    ///
+    /// Adds LLVM targets to build in addition to the default ones.
+    ///
+    pub fn targets(mut self, targets: impl IntoIterator<Item = Platform>) -> Self {
+        self.options.targets.extend(targets);
+        self
+    }
+
+    ///
+    /// Adds LLVM projects to build in addition to the default ones.
+    ///
+    pub fn llvm_projects(mut self, llvm_projects: impl IntoIterator<Item = LLVMProject>) -> Self {
+        self.options.llvm_projects.extend(llvm_projects);
+        self
+    }
+
+    ///
+    /// Enables or disables run-time type information (RTTI).
+    ///
+    pub fn enable_rtti(mut self, enabled: bool) -> Self {
+        self.options.enable_rtti = enabled;
+        self
+    }
+
+    ///
+    /// Sets the default target triple.
+    ///
+    pub fn default_target(mut self, default_target: Option<TargetTriple>) -> Self {
+        self.options.default_target = default_target;
+        self
+    }
+
+    ///
+    /// Enables or disables the LLVM tests.
+    ///
+    pub fn enable_tests(mut self, enabled: bool) -> Self {
+        self.options.enable_tests = enabled;
+        self
+    }
+
+    ///
+    /// Enables or disables the source-based code coverage.
+    ///
+    pub fn enable_coverage(mut self, enabled: bool) -> Self {
+        self.options.enable_coverage = enabled;
+        self
+    }
+
+    ///
+    /// Appends extra arguments to pass to CMake.
+    ///
+    pub fn extra_args(mut self, extra_args: impl IntoIterator<Item = String>) -> Self {
+        self.options.extra_args.extend(extra_args);
+        self
+    }
+
+    ///
+    /// Sets the compiler cache variant.
+    ///
+    pub fn ccache_variant(mut self, ccache_variant: Option<CcacheVariant>) -> Self {
+        self.options.ccache_variant = ccache_variant;
+        self
+    }
+
+    ///
+    /// Enables or disables assertions.
+    ///
+    pub fn enable_assertions(mut self, enabled: bool) -> Self {
+        self.options.enable_assertions = enabled;
+        self
+    }
+
+    ///
+    /// Sets the sanitizer.
+    ///
+    pub fn sanitizer(mut self, sanitizer: Option<Sanitizer>) -> Self {
+        self.options.sanitizer = sanitizer;
+        self
+    }
+
+    ///
+    /// Enables or disables running the LLVM unit tests under valgrind.
+    ///
+    pub fn enable_valgrind(mut self, enabled: bool) -> Self {
+        self.options.enable_valgrind = enabled;
+        self
+    }
+
+    ///
+    /// Appends valgrind options.
+    ///
+    pub fn valgrind_options(mut self, valgrind_options: impl IntoIterator<Item = String>) -> Self {
+        self.options.valgrind_options.extend(valgrind_options);
+        self
+    }
+
+    ///
+    /// Sets the number of parallel jobs of the musl build.
+    ///
+    pub fn musl_jobs(mut self, musl_jobs: Option<usize>) -> Self {
+        self.options.musl_jobs = musl_jobs;
+        self
+    }
+
+    ///
+    /// Sets the Xcode version of the macOS builder.
+    ///
+    pub fn xcode_version(mut self, xcode_version: Option<u32>) -> Self {
+        self.options.xcode_version = xcode_version;
+        self
+    }
+
+    ///
+    /// Sets the host the builder runs on, cross-compiling if it differs from the built one.
+    ///
+    pub fn build_host(mut self, build_host: Option<Host>) -> Self {
+        self.options.build_host = build_host;
+        self
+    }
+
+    ///
+    /// Returns the built options.
+    ///
+    pub fn build(self) -> BuildOptions {
+        self.options
+    }
+}
@@ -0,0 +1,106 @@
+//!
+//! The ZKsync LLVM build options.
+//!
+
+pub mod builder;
+
+pub use self::builder::BuildOptionsBuilder;
+
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::build_type::BuildType;
+use crate::ccache_variant::CcacheVariant;
+use crate::host::Host;
+use crate::llvm_project::LLVMProject;
+use crate::platforms::Platform;
+use crate::sanitizer::Sanitizer;
+use crate::target_env::TargetEnv;
+use crate::target_triple::TargetTriple;
+
+///
+/// The LLVM framework build options.
+///
+/// The defaults match the defaults of the `build` subcommand of the CLI.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildOptions {
+    /// The LLVM build type.
+    pub build_type: BuildType,
+    /// The target environment (GNU or MUSL).
+    pub target_env: TargetEnv,
+    /// The LLVM targets to build in addition to the mandatory ones.
+    pub targets: BTreeSet<Platform>,
+    /// The LLVM projects to build in addition to the mandatory ones.
+    pub llvm_projects: BTreeSet<LLVMProject>,
+    /// Whether to build with run-time type information (RTTI) enabled.
+    pub enable_rtti: bool,
+    /// The default target triple.
+    pub default_target: Option<TargetTriple>,
+    /// Whether to build the LLVM tests.
+    pub enable_tests: bool,
+    /// Whether to build for source-based code coverage.
+    pub enable_coverage: bool,
+    /// Extra arguments to pass to CMake.
+    pub extra_args: Vec<String>,
+    /// The compiler cache variant.
+    pub ccache_variant: Option<CcacheVariant>,
+    /// Whether to build with assertions enabled.
+    pub enable_assertions: bool,
+    /// The sanitizer to build with.
+    pub sanitizer: Option<Sanitizer>,
+    /// Whether to run the LLVM unit tests under valgrind.
+    pub enable_valgrind: bool,
+    /// Additional valgrind options.
+    pub valgrind_options: Vec<String>,
+    /// The number of parallel jobs of the musl build, the number of CPUs if unset.
+    pub musl_jobs: Option<usize>,
+    /// The Xcode version of the macOS builder, the minimal supported one if unset.
+    #[serde(skip)]
+    pub xcode_version: Option<u32>,
+    /// The host the builder runs on if the LLVM framework is cross-compiled for another one.
+    #[serde(skip)]
+    pub build_host: Option<Host>,
+}
+
+impl BuildOptions {
+    /// The LLVM targets built regardless of the options.
+    pub const TARGETS_MANDATORY: [Platform; 2] = [Platform::EraVM, Platform::EVM];
+
+    /// The LLVM projects built regardless of the options.
+    pub const LLVM_PROJECTS_MANDATORY: [LLVMProject; 1] = [LLVMProject::LLD];
+
+    ///
+    /// Returns a builder initialized with the default options.
+    ///
+    pub fn builder() -> BuildOptionsBuilder {
+        BuildOptionsBuilder::default()
+    }
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            build_type: BuildType::Release,
+            target_env: TargetEnv::GNU,
+            targets: BTreeSet::from(Self::TARGETS_MANDATORY),
+            llvm_projects: BTreeSet::from(Self::LLVM_PROJECTS_MANDATORY),
+            enable_rtti: false,
+            default_target: None,
+            enable_tests: false,
+            enable_coverage: false,
+            extra_args: vec![],
+            ccache_variant: None,
+            enable_assertions: false,
+            sanitizer: None,
+            enable_valgrind: false,
+            valgrind_options: vec![],
+            musl_jobs: None,
+            xcode_version: None,
+            build_host: None,
+        }
+    }
+}
@@ -2,10 +2,13 @@
 //! The ZKsync LLVM build type.
 //!
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// The ZKsync LLVM build type.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BuildType {
     /// The debug build.
     Debug,
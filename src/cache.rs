@@ -0,0 +1,191 @@
+//!
+//! The local source cache.
+//!
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use sha2::Digest;
+
+use crate::plan::CommandStep;
+use crate::runner::CommandRunner;
+
+///
+/// The content-addressed cache of the downloaded tarballs and cloned repositories.
+///
+/// The tarballs are stored by their SHA-256 as `sha256/<digest>`, and the repositories are
+/// stored as bare clones by the hash of their URL and tag as `git/<hash>.git`. The entries
+/// are written to temporary paths and renamed, so concurrent builds never see partial ones.
+/// The stage 1 host toolchains shared by the MUSL builds are stored as `host/<key>/`, if the
+/// cache directory is set explicitly.
+///
+/// The cache can be populated on a connected machine with `zksync-llvm fetch` and copied
+/// to the air-gapped ones, where the builder is run with `--offline`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cache {
+    /// The cache root directory.
+    pub root: PathBuf,
+    /// Whether the network access is disabled, so everything must be in the cache.
+    pub offline: bool,
+    /// Whether the stage 1 host toolchains are shared through the cache.
+    pub shares_host_toolchains: bool,
+}
+
+impl Cache {
+    /// The cache directory name in the user cache directory.
+    pub const DIRECTORY_NAME: &'static str = "zksync-llvm";
+
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(root: &Path, offline: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            offline,
+            shares_host_toolchains: true,
+        }
+    }
+
+    ///
+    /// Returns the cache in the directory, or in the default one if it is not set and the
+    /// cache is required, e.g. in the offline mode or to populate the cache.
+    ///
+    /// The stage 1 host toolchains are only shared through the directory set explicitly,
+    /// while the default one only stores the sources.
+    ///
+    pub fn resolve(root: Option<PathBuf>, offline: bool, is_required: bool) -> Option<Self> {
+        match root {
+            Some(root) => Some(Self::new(root.as_path(), offline)),
+            None if !offline && !is_required => None,
+            None => Self::default_root().map(|root| Self {
+                shares_host_toolchains: false,
+                ..Self::new(root.as_path(), offline)
+            }),
+        }
+    }
+
+    ///
+    /// Returns the default cache root, e.g. `~/.cache/zksync-llvm/`.
+    ///
+    pub fn default_root() -> Option<PathBuf> {
+        let cache_home = match std::env::var_os("XDG_CACHE_HOME") {
+            Some(cache_home) => PathBuf::from(cache_home),
+            None => {
+                PathBuf::from(std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?)
+                    .join(".cache")
+            }
+        };
+        Some(cache_home.join(Self::DIRECTORY_NAME))
+    }
+
+    ///
+    /// Returns the path of the file with the SHA-256.
+    ///
+    pub fn file(&self, sha256: &str) -> PathBuf {
+        self.root.join("sha256").join(sha256.to_ascii_lowercase())
+    }
+
+    ///
+    /// Returns the path of the bare clone of the repository tag.
+    ///
+    pub fn git(&self, url: &str, tag: &str) -> PathBuf {
+        let hash = sha2::Sha256::digest(format!("{url}#{tag}").as_bytes());
+        self.root.join("git").join(format!("{hash:x}.git"))
+    }
+
+    ///
+    /// Returns the directory of the shared host toolchains.
+    ///
+    pub fn host_toolchains(&self) -> PathBuf {
+        self.root.join("host")
+    }
+
+    ///
+    /// Returns the directory of the shared host toolchain with the key.
+    ///
+    pub fn host_toolchain(&self, key: &str) -> PathBuf {
+        self.host_toolchains().join(key)
+    }
+
+    ///
+    /// Returns a unique temporary path next to the cache entry.
+    ///
+    pub fn temporary(path: &Path) -> PathBuf {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(".tmp-{}", std::process::id()));
+        path.with_file_name(file_name)
+    }
+
+    ///
+    /// Stores the file with the SHA-256, unless it is already stored.
+    ///
+    pub fn store_file(&self, source: &Path, sha256: &str) -> anyhow::Result<()> {
+        let path = self.file(sha256);
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temporary = Self::temporary(path.as_path());
+        std::fs::copy(source, temporary.as_path())
+            .map_err(|error| anyhow::anyhow!("Cache entry {path:?} writing: {error}"))?;
+        std::fs::rename(temporary.as_path(), path.as_path())
+            .map_err(|error| anyhow::anyhow!("Cache entry {path:?} writing: {error}"))?;
+        Ok(())
+    }
+
+    ///
+    /// Returns the bare clone of the repository tag, cloning it from the first available
+    /// mirror unless it is already cached.
+    ///
+    /// The entry is keyed by the upstream URL, so it is found regardless of the mirrors.
+    ///
+    pub fn repository(
+        &self,
+        url: &str,
+        tag: &str,
+        runner: &mut dyn CommandRunner,
+    ) -> anyhow::Result<PathBuf> {
+        let path = self.git(url, tag);
+        if path.exists() {
+            return Ok(path);
+        }
+        self.check_missing(&[format!("the repository `{url}` at `{tag}`")])?;
+
+        let temporary = Self::temporary(path.as_path());
+        let urls = crate::mirrors::candidates(runner, url);
+        crate::mirrors::try_each(urls.as_slice(), "Repository caching", |source| {
+            runner.run(
+                &CommandStep::new("git", "Repository caching")
+                    .args(["clone", "--bare", "--depth", "1", "--branch", tag, source])
+                    .arg(temporary.to_string_lossy()),
+            )
+        })?;
+        if !runner.applies_side_effects() {
+            return Ok(path);
+        }
+        if path.exists() {
+            std::fs::remove_dir_all(temporary.as_path())?;
+        } else {
+            std::fs::rename(temporary.as_path(), path.as_path())
+                .map_err(|error| anyhow::anyhow!("Cache entry {path:?} writing: {error}"))?;
+        }
+        Ok(path)
+    }
+
+    ///
+    /// Fails in the offline mode if any sources are missing, listing all of them.
+    ///
+    pub fn check_missing(&self, missing: &[String]) -> anyhow::Result<()> {
+        if !self.offline || missing.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!(
+            "The following sources are missing from the cache {:?}, and the network access is disabled in the offline mode:\n    {}\nRun `zksync-llvm fetch` on a connected machine and copy the cache directory",
+            self.root,
+            missing.join("\n    ")
+        )
+    }
+}
@@ -2,10 +2,14 @@
 //! Compiler cache variants.
 //!
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// The list compiler cache variants to be used as constants.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CcacheVariant {
     /// Standard ccache.
     Ccache,
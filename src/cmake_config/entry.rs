@@ -0,0 +1,55 @@
+//!
+//! The CMake cache entry.
+//!
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cmake_config::entry_type::CMakeEntryType;
+use crate::cmake_config::origin::CMakeOrigin;
+
+///
+/// The CMake cache entry passed with the `-D` argument.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CMakeEntry {
+    /// The cache variable name.
+    pub key: String,
+    /// The cache variable type.
+    pub r#type: CMakeEntryType,
+    /// The cache variable value.
+    pub value: String,
+    /// The configuration layer that set the value.
+    pub origin: CMakeOrigin,
+}
+
+impl CMakeEntry {
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(
+        key: impl Into<String>,
+        r#type: CMakeEntryType,
+        value: impl Into<String>,
+        origin: CMakeOrigin,
+    ) -> Self {
+        Self {
+            key: key.into(),
+            r#type,
+            value: value.into(),
+            origin,
+        }
+    }
+}
+
+impl std::fmt::Display for CMakeEntry {
+    ///
+    /// Renders the entry as a CMake command-line argument.
+    ///
+    /// The type is not rendered, as CMake would make relative `PATH` and `FILEPATH` values
+    /// absolute, which breaks values like `clang` that are expected to be found in `PATH`.
+    ///
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "-D{}='{}'", self.key, self.value)
+    }
+}
@@ -0,0 +1,33 @@
+//!
+//! The CMake cache entry type.
+//!
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The CMake cache entry type.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CMakeEntryType {
+    /// The boolean `On`/`Off` value.
+    Bool,
+    /// The arbitrary string value.
+    String,
+    /// The directory path.
+    Path,
+    /// The file path.
+    FilePath,
+}
+
+impl std::fmt::Display for CMakeEntryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Bool => write!(f, "BOOL"),
+            Self::String => write!(f, "STRING"),
+            Self::Path => write!(f, "PATH"),
+            Self::FilePath => write!(f, "FILEPATH"),
+        }
+    }
+}
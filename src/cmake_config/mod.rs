@@ -0,0 +1,199 @@
+//!
+//! The CMake configuration.
+//!
+
+pub mod entry;
+pub mod entry_type;
+pub mod origin;
+
+pub use self::entry::CMakeEntry;
+pub use self::entry_type::CMakeEntryType;
+pub use self::origin::CMakeOrigin;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::plan::command::CommandStep;
+
+///
+/// The CMake configuration of a single build directory.
+///
+/// The entries are kept in the order they were first set. Setting an entry that already
+/// exists overrides its value and origin in place, so platforms can apply their overrides
+/// on top of the shared base without duplicating `-D` arguments.
+///
+/// The raw arguments are rendered after the entries set before them, and before the ones
+/// set afterwards, which take precedence over the raw arguments in CMake. An entry set
+/// before the raw arguments and overridden afterwards is moved after them.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CMakeConfig {
+    /// The source directory.
+    pub source_directory: PathBuf,
+    /// The build directory.
+    pub build_directory: PathBuf,
+    /// The CMake generator.
+    pub generator: String,
+    /// The cache entries.
+    pub entries: Vec<CMakeEntry>,
+    /// The raw arguments rendered between the cache entries.
+    pub extra_args: Vec<String>,
+    /// The number of the leading cache entries rendered before the raw arguments.
+    pub extra_args_position: usize,
+}
+
+impl CMakeConfig {
+    /// The default CMake generator.
+    pub const GENERATOR_DEFAULT: &'static str = "Ninja";
+
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(source_directory: &Path, build_directory: &Path) -> Self {
+        Self {
+            source_directory: source_directory.to_path_buf(),
+            build_directory: build_directory.to_path_buf(),
+            generator: Self::GENERATOR_DEFAULT.to_owned(),
+            entries: vec![],
+            extra_args: vec![],
+            extra_args_position: 0,
+        }
+    }
+
+    ///
+    /// Sets the cache entry, overriding the existing one with the same key.
+    ///
+    /// The overridden entry rendered before the raw arguments is moved after them, so the
+    /// override takes precedence over the raw arguments.
+    ///
+    pub fn set(&mut self, entry: CMakeEntry) -> &mut Self {
+        match self
+            .entries
+            .iter()
+            .position(|existing| existing.key == entry.key)
+        {
+            Some(index) if index < self.extra_args_position => {
+                self.entries.remove(index);
+                self.extra_args_position -= 1;
+                self.entries.push(entry);
+            }
+            Some(index) => self.entries[index] = entry,
+            None => self.entries.push(entry),
+        }
+        self
+    }
+
+    ///
+    /// Sets the `On`/`Off` cache entry.
+    ///
+    pub fn set_bool(&mut self, key: &str, value: bool, origin: CMakeOrigin) -> &mut Self {
+        self.set(CMakeEntry::new(
+            key,
+            CMakeEntryType::Bool,
+            if value { "On" } else { "Off" },
+            origin,
+        ))
+    }
+
+    ///
+    /// Sets the string cache entry.
+    ///
+    pub fn set_string(
+        &mut self,
+        key: &str,
+        value: impl Into<String>,
+        origin: CMakeOrigin,
+    ) -> &mut Self {
+        self.set(CMakeEntry::new(key, CMakeEntryType::String, value, origin))
+    }
+
+    ///
+    /// Sets the directory path cache entry.
+    ///
+    pub fn set_path(&mut self, key: &str, value: &Path, origin: CMakeOrigin) -> &mut Self {
+        self.set(CMakeEntry::new(
+            key,
+            CMakeEntryType::Path,
+            value.to_string_lossy(),
+            origin,
+        ))
+    }
+
+    ///
+    /// Sets the file path cache entry.
+    ///
+    pub fn set_file_path(
+        &mut self,
+        key: &str,
+        value: impl Into<String>,
+        origin: CMakeOrigin,
+    ) -> &mut Self {
+        self.set(CMakeEntry::new(
+            key,
+            CMakeEntryType::FilePath,
+            value,
+            origin,
+        ))
+    }
+
+    ///
+    /// Removes the cache entry if it exists.
+    ///
+    pub fn unset(&mut self, key: &str) -> &mut Self {
+        if let Some(index) = self.entries.iter().position(|entry| entry.key == key) {
+            self.entries.remove(index);
+            if index < self.extra_args_position {
+                self.extra_args_position -= 1;
+            }
+        }
+        self
+    }
+
+    ///
+    /// Appends raw arguments, e.g. the user-provided extra arguments.
+    ///
+    /// The raw arguments are rendered after the entries set so far, so the entries set
+    /// afterwards cannot be overridden by them.
+    ///
+    pub fn extra_args(&mut self, args: impl IntoIterator<Item = String>) -> &mut Self {
+        self.extra_args.extend(args);
+        self.extra_args_position = self.entries.len();
+        self
+    }
+
+    ///
+    /// Returns the cache entry by its key.
+    ///
+    pub fn get(&self, key: &str) -> Option<&CMakeEntry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    ///
+    /// Renders the configuration into the CMake command-line arguments.
+    ///
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-S".to_owned(),
+            self.source_directory.to_string_lossy().to_string(),
+            "-B".to_owned(),
+            self.build_directory.to_string_lossy().to_string(),
+            "-G".to_owned(),
+            self.generator.clone(),
+        ];
+        let (leading, trailing) = self.entries.split_at(self.extra_args_position);
+        args.extend(leading.iter().map(|entry| entry.to_string()));
+        args.extend(self.extra_args.iter().cloned());
+        args.extend(trailing.iter().map(|entry| entry.to_string()));
+        args
+    }
+
+    ///
+    /// Creates the CMake build plan command from the configuration.
+    ///
+    pub fn to_step(&self, description: &str) -> CommandStep {
+        CommandStep::new("cmake", description).args(self.to_args())
+    }
+}
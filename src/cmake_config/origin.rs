@@ -0,0 +1,30 @@
+//!
+//! The CMake cache entry origin.
+//!
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The origin of a CMake cache entry, that is, the layer of the configuration that set it.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CMakeOrigin {
+    /// The base shared by all platforms.
+    Shared,
+    /// The platform-specific override.
+    Platform,
+    /// The value derived from the build options.
+    Options,
+}
+
+impl std::fmt::Display for CMakeOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Shared => write!(f, "shared"),
+            Self::Platform => write!(f, "platform"),
+            Self::Options => write!(f, "options"),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//!
+//! The ZKsync LLVM builder configuration file.
+//!
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::build_options::BuildOptions;
+use crate::build_options::BuildOptionsBuilder;
+use crate::host::Host;
+use crate::mirrors::Mirror;
+use crate::mirrors::Mirrors;
+use crate::mirrors::Rewrite;
+use crate::retry::RetryPolicy;
+
+///
+/// The builder configuration file data.
+///
+/// Kept next to `LLVM.lock` and defines the named build profiles, so CI jobs and developers
+/// do not have to repeat long command lines:
+///
+/// ```toml
+/// [profile.ci-asan]
+/// build-type = "RelWithDebInfo"
+/// enable-assertions = true
+/// sanitizer = "Address"
+/// extra-args = ["-DLLVM_PARALLEL_LINK_JOBS=2"]
+///
+/// [profile.release-aarch64-musl]
+/// host-triple = "aarch64-unknown-linux-musl"
+///
+/// [directories]
+/// source = "/mnt/sources/llvm"
+/// target = "/scratch/target-llvm"
+///
+/// [[mirror]]
+/// upstream = "https://github.com/llvm/llvm-project"
+/// urls = ["https://git.example.com/llvm-project"]
+///
+/// [[rewrite]]
+/// prefix = "https://github.com/"
+/// replacement = "https://git.example.com/github/"
+/// ```
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The build profiles by name.
+    #[serde(default)]
+    pub profile: BTreeMap<String, Profile>,
+    /// The workspace directories.
+    #[serde(default)]
+    pub directories: Directories,
+    /// The download retry policy.
+    #[serde(default)]
+    pub download: RetryPolicy,
+    /// The mirrors of the upstream sources.
+    #[serde(default)]
+    pub mirror: Vec<Mirror>,
+    /// The upstream URL rewrite rules.
+    #[serde(default)]
+    pub rewrite: Vec<Rewrite>,
+}
+
+///
+/// The build profile covering all the building arguments of the CLI.
+///
+/// The build options are flattened into the profile table. The profile is deserialized
+/// manually, as the flattened options would not reject the unknown keys otherwise.
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    /// The build options.
+    #[serde(flatten)]
+    pub options: BuildOptions,
+    /// The host to build for, in the `<arch>-<os>[-<env>]` form.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_host"
+    )]
+    pub host: Option<Host>,
+    /// The host triple to cross-compile for on the running system.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_host_triple"
+    )]
+    pub host_triple: Option<Host>,
+    /// The existing LLVM checkout to build from, relative to the workspace root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llvm_source: Option<PathBuf>,
+}
+
+impl<'de> Deserialize<'de> for Profile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut table = toml::Table::deserialize(deserializer)?;
+        let mut take_string = |key: &str| -> Result<Option<String>, D::Error> {
+            table
+                .remove(key)
+                .map(|value| match value {
+                    toml::Value::String(value) => Ok(value),
+                    value => Err(serde::de::Error::custom(format!(
+                        "`{key}` must be a string, found {}",
+                        value.type_str()
+                    ))),
+                })
+                .transpose()
+        };
+        let host = take_string("host")?
+            .map(|host| host.parse::<Host>().map_err(serde::de::Error::custom))
+            .transpose()?;
+        let host_triple = take_string("host-triple")?
+            .map(|triple| Host::from_triple(triple.as_str()).map_err(serde::de::Error::custom))
+            .transpose()?;
+        if host.is_some() && host_triple.is_some() {
+            return Err(serde::de::Error::custom(
+                "`host` cannot be used with `host-triple`",
+            ));
+        }
+        let llvm_source = take_string("llvm-source")?.map(PathBuf::from);
+        let options = BuildOptions::deserialize(toml::Value::Table(table))
+            .map_err(serde::de::Error::custom)?;
+        Ok(Self {
+            options,
+            host,
+            host_triple,
+            llvm_source,
+        })
+    }
+}
+
+///
+/// The workspace directories overriding the defaults.
+///
+/// Relative paths are resolved against the workspace root.
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Directories {
+    /// The LLVM source directory.
+    pub source: Option<PathBuf>,
+    /// The LLVM host source directory for stage 1 of multistage MUSL builds.
+    pub host_source: Option<PathBuf>,
+    /// The LLVM target directory with all the build trees and artifacts.
+    pub target: Option<PathBuf>,
+}
+
+impl Config {
+    /// The configuration file name.
+    pub const FILE_NAME: &'static str = "zksync-llvm.toml";
+
+    ///
+    /// Returns the mirrors and rewrite rules of the upstream sources.
+    ///
+    pub fn mirrors(&self) -> Mirrors {
+        Mirrors::new(self.mirror.clone(), self.rewrite.clone())
+    }
+
+    ///
+    /// Returns the profile.
+    ///
+    /// The profile targets and projects extend the mandatory ones, the same way as the
+    /// command-line arguments do.
+    ///
+    pub fn profile(&self, name: &str) -> anyhow::Result<Profile> {
+        let mut profile = self.profile.get(name).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Profile `{name}` is not defined in `{}`. Available profiles: {}",
+                Self::FILE_NAME,
+                self.profile
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            )
+        })?;
+        profile.options = BuildOptionsBuilder::from(profile.options)
+            .targets(BuildOptions::TARGETS_MANDATORY)
+            .llvm_projects(BuildOptions::LLVM_PROJECTS_MANDATORY)
+            .build();
+        Ok(profile)
+    }
+
+    ///
+    /// Reads the configuration file from the workspace root, if it exists.
+    ///
+    pub fn load(root: &Path) -> anyhow::Result<Option<Self>> {
+        let path = root.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::try_from(&path).map(Some)
+    }
+}
+
+impl TryFrom<&PathBuf> for Config {
+    type Error = anyhow::Error;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        let config_str = std::fs::read_to_string(path)
+            .with_context(|| format!("Error opening {path:?} file"))?;
+        toml::from_str(config_str.as_str()).with_context(|| format!("Error parsing {path:?} file"))
+    }
+}
+
+///
+/// Serializes the host in the `<arch>-<os>[-<env>]` form.
+///
+fn serialize_host<S>(host: &Option<Host>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    host.map(|host| host.to_string()).serialize(serializer)
+}
+
+///
+/// Serializes the host as the target triple.
+///
+fn serialize_host_triple<S>(host: &Option<Host>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    host.map(|host| host.triple()).serialize(serializer)
+}
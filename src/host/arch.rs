@@ -0,0 +1,55 @@
+//!
+//! The host architecture.
+//!
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The host architectures supported by the builder.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arch {
+    /// The amd64 architecture.
+    #[serde(rename = "x86_64")]
+    X86_64,
+    /// The arm64 architecture.
+    AArch64,
+}
+
+impl Arch {
+    ///
+    /// Returns the architecture the builder is running on.
+    ///
+    pub fn detect() -> anyhow::Result<Self> {
+        if cfg!(target_arch = "x86_64") {
+            Ok(Self::X86_64)
+        } else if cfg!(target_arch = "aarch64") {
+            Ok(Self::AArch64)
+        } else {
+            anyhow::bail!("Unsupported target architecture")
+        }
+    }
+}
+
+impl std::str::FromStr for Arch {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "x86_64" => Ok(Self::X86_64),
+            "aarch64" => Ok(Self::AArch64),
+            value => Err(format!("Unsupported host architecture: `{value}`")),
+        }
+    }
+}
+
+impl std::fmt::Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::X86_64 => write!(f, "x86_64"),
+            Self::AArch64 => write!(f, "aarch64"),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//!
+//! The host the LLVM framework is built for.
+//!
+
+pub mod arch;
+pub mod os;
+
+pub use self::arch::Arch;
+pub use self::os::OS;
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::target_env::TargetEnv;
+
+///
+/// The host the LLVM framework is built for.
+///
+/// Detected from the running builder by default, but can be overridden to plan builds
+/// for other hosts, e.g. to inspect the macOS commands on a Linux machine.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Host {
+    /// The host architecture.
+    pub arch: Arch,
+    /// The host operating system.
+    pub os: OS,
+    /// The host environment.
+    pub env: TargetEnv,
+}
+
+impl Host {
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(arch: Arch, os: OS, env: TargetEnv) -> Self {
+        Self { arch, os, env }
+    }
+
+    ///
+    /// Returns the host the builder is running on, with the specified environment.
+    ///
+    pub fn detect(env: TargetEnv) -> anyhow::Result<Self> {
+        Ok(Self::new(Arch::detect()?, OS::detect()?, env))
+    }
+
+    ///
+    /// Parses the LLVM target triple, e.g. `aarch64-unknown-linux-gnu` or `aarch64-apple-darwin`.
+    ///
+    pub fn from_triple(value: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = value.split('-').collect();
+        let (arch, os, env) = match parts.as_slice() {
+            [arch, "apple", "darwin"] => (arch, OS::MacOS, TargetEnv::GNU),
+            [arch, "pc" | "w64", "windows", "gnu"] | [arch, "w64", "mingw32"] => {
+                (arch, OS::Windows, TargetEnv::GNU)
+            }
+            [arch, "unknown", "linux", env] => (arch, OS::Linux, TargetEnv::from_str(env)?),
+            _ => {
+                return Err(format!(
+                    "Invalid host triple `{value}`: expected e.g. `aarch64-unknown-linux-gnu`"
+                ))
+            }
+        };
+        Ok(Self::new(Arch::from_str(arch)?, os, env))
+    }
+
+    ///
+    /// Returns the LLVM target triple, e.g. `aarch64-unknown-linux-musl`.
+    ///
+    /// The Windows triple is the one of the mingw-w64 toolchains, e.g. `x86_64-w64-mingw32`.
+    ///
+    pub fn triple(&self) -> String {
+        match self.os {
+            OS::Linux => format!("{}-unknown-linux-{}", self.arch, self.env),
+            OS::MacOS => format!("{}-apple-darwin", self.arch),
+            OS::Windows => format!("{}-w64-mingw32", self.arch),
+        }
+    }
+
+    ///
+    /// Whether the LLVM framework for the host must be cross-compiled on the builder host.
+    ///
+    pub fn is_cross(&self, builder: &Host) -> bool {
+        self.arch != builder.arch || self.os != builder.os
+    }
+}
+
+impl std::str::FromStr for Host {
+    type Err = String;
+
+    ///
+    /// Parses the `<arch>-<os>[-<env>]` string, e.g. `x86_64-linux-musl` or `aarch64-macos`.
+    ///
+    /// The environment defaults to `gnu` if omitted.
+    ///
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = value.split('-').collect();
+        let (arch, os, env) = match parts.as_slice() {
+            [arch, os] => (arch, os, TargetEnv::GNU),
+            [arch, os, env] => (arch, os, TargetEnv::from_str(env)?),
+            _ => {
+                return Err(format!(
+                    "Invalid host `{value}`: expected `<arch>-<os>[-<env>]`"
+                ))
+            }
+        };
+        Ok(Self::new(Arch::from_str(arch)?, OS::from_str(os)?, env))
+    }
+}
+
+impl std::fmt::Display for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.os {
+            OS::MacOS => write!(f, "{}-{}", self.arch, self.os),
+            _ => write!(f, "{}-{}-{}", self.arch, self.os, self.env),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//!
+//! The host operating system.
+//!
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The host operating systems supported by the builder.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OS {
+    /// The Linux operating system.
+    Linux,
+    /// The macOS operating system.
+    MacOS,
+    /// The Windows operating system.
+    Windows,
+}
+
+impl OS {
+    ///
+    /// Returns the operating system the builder is running on.
+    ///
+    pub fn detect() -> anyhow::Result<Self> {
+        if cfg!(target_os = "linux") {
+            Ok(Self::Linux)
+        } else if cfg!(target_os = "macos") {
+            Ok(Self::MacOS)
+        } else if cfg!(target_os = "windows") {
+            Ok(Self::Windows)
+        } else {
+            anyhow::bail!("Unsupported target OS")
+        }
+    }
+}
+
+impl std::str::FromStr for OS {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "linux" => Ok(Self::Linux),
+            "macos" => Ok(Self::MacOS),
+            "windows" => Ok(Self::Windows),
+            value => Err(format!("Unsupported host OS: `{value}`")),
+        }
+    }
+}
+
+impl std::fmt::Display for OS {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Linux => write!(f, "linux"),
+            Self::MacOS => write!(f, "macos"),
+            Self::Windows => write!(f, "windows"),
+        }
+    }
+}
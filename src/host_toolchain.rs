@@ -0,0 +1,521 @@
+//!
+//! The shared stage 1 host toolchain cache.
+//!
+
+use std::fs::File;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use fs4::fs_std::FileExt;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+
+use crate::cache::Cache;
+use crate::host::Host;
+use crate::lock::MuslSource;
+use crate::plan::Stage;
+
+///
+/// The manifest of the host toolchain cache entry.
+///
+/// The MUSL, CRT, and host toolchain stages of the MUSL builds only depend on the host LLVM
+/// commit, the musl release, and the build tools, so they are installed once into the cache
+/// directory keyed by the hash of these inputs, e.g. `~/.cache/zksync-llvm/host/<key>/`,
+/// and reused by every workspace. The manifest is written after all the stages complete,
+/// so the entries without it are incomplete.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HostToolchain {
+    /// The cache key, the hash of all the other fields but the timestamp.
+    pub key: String,
+    /// The host, e.g. `x86_64-linux-musl`.
+    pub host: String,
+    /// The LLVM host source commit.
+    pub commit: String,
+    /// The musl release name, e.g. `musl-1.2.3`.
+    pub musl: String,
+    /// The musl release tarball SHA-256.
+    pub musl_sha256: String,
+    /// The Linux kernel headers installed into the musl sysroot, e.g. `system`.
+    #[serde(default)]
+    pub kernel_headers: String,
+    /// The first line of `--version` of every required tool.
+    pub tools: Vec<String>,
+    /// The builder version.
+    pub builder: String,
+    /// The completion timestamp.
+    #[serde(default)]
+    pub created: String,
+}
+
+impl HostToolchain {
+    /// The manifest file name in the entry directory.
+    pub const FILE_NAME: &'static str = "manifest.json";
+
+    /// The file updated every time the entry is used.
+    pub const LAST_USED_FILE_NAME: &'static str = "last-used";
+
+    /// The names of the stages installed into the entry.
+    pub const STAGES: [&'static str; 3] = [Stage::MUSL, Stage::CRT, Stage::HOST];
+
+    /// The target directories of the stages installed into the entry.
+    pub const DIRECTORIES: [&'static str; 3] = ["target-musl", "target-crt", "target-host"];
+
+    ///
+    /// Creates the manifest, computing the cache key from the inputs.
+    ///
+    pub fn new(
+        host: String,
+        commit: String,
+        musl: &MuslSource,
+        tools: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        let mut toolchain = Self {
+            key: String::new(),
+            host,
+            commit,
+            musl: musl.name(),
+            musl_sha256: musl.sha256()?,
+            kernel_headers: musl.kernel_headers_description(),
+            tools,
+            builder: env!("CARGO_PKG_VERSION").to_owned(),
+            created: String::new(),
+        };
+        toolchain.key = toolchain.compute_key()?;
+        Ok(toolchain)
+    }
+
+    ///
+    /// Computes the cache key from the inputs.
+    ///
+    pub fn compute_key(&self) -> anyhow::Result<String> {
+        let inputs = Self {
+            key: String::new(),
+            created: String::new(),
+            ..self.to_owned()
+        };
+        let hash = sha2::Sha256::digest(serde_json::to_vec(&inputs)?);
+        Ok(format!("{hash:x}")[..32].to_owned())
+    }
+
+    ///
+    /// Whether the toolchain has been built for the host with the musl release.
+    ///
+    pub fn matches(&self, host: Host, musl: &MuslSource) -> anyhow::Result<bool> {
+        Ok(self.host == host.to_string()
+            && self.musl == musl.name()
+            && self.musl_sha256 == musl.sha256()?
+            && self.kernel_headers == musl.kernel_headers_description())
+    }
+
+    ///
+    /// Reads the manifest of the entry, returning `None` if the entry is incomplete.
+    ///
+    pub fn load(directory: &Path) -> anyhow::Result<Option<Self>> {
+        Self::read(directory.join(Self::FILE_NAME).as_path())
+    }
+
+    ///
+    /// Writes the manifest, marking the entry complete.
+    ///
+    pub fn save(&mut self, directory: &Path) -> anyhow::Result<()> {
+        self.created = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        self.write(directory.join(Self::FILE_NAME).as_path())
+    }
+
+    ///
+    /// Reads the manifest file, returning `None` if it does not exist.
+    ///
+    pub fn read(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| anyhow::anyhow!("Manifest {path:?} reading: {error}"))?;
+        serde_json::from_str(contents.as_str())
+            .map(Some)
+            .map_err(|error| anyhow::anyhow!("Manifest {path:?} parsing: {error}"))
+    }
+
+    ///
+    /// Writes the manifest file through a temporary one, so it is never seen partially written.
+    ///
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temporary = Cache::temporary(path);
+        std::fs::write(temporary.as_path(), serde_json::to_string_pretty(self)?)
+            .map_err(|error| anyhow::anyhow!("Manifest {path:?} writing: {error}"))?;
+        std::fs::rename(temporary.as_path(), path)
+            .map_err(|error| anyhow::anyhow!("Manifest {path:?} writing: {error}"))
+    }
+
+    ///
+    /// Records the use of the entry, so it is not pruned.
+    ///
+    pub fn touch(directory: &Path) -> anyhow::Result<()> {
+        let path = directory.join(Self::LAST_USED_FILE_NAME);
+        std::fs::write(
+            path.as_path(),
+            humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        )
+        .map_err(|error| anyhow::anyhow!("File {path:?} writing: {error}"))
+    }
+}
+
+///
+/// The host toolchain cache entry.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostToolchainEntry {
+    /// The entry directory.
+    pub directory: PathBuf,
+    /// The manifest, or `None` if the entry is incomplete.
+    pub toolchain: Option<HostToolchain>,
+    /// The time the entry was last used or modified.
+    pub last_used: Option<SystemTime>,
+}
+
+impl HostToolchainEntry {
+    ///
+    /// Returns the cache entries sorted by key.
+    ///
+    pub fn list(cache: &Cache) -> anyhow::Result<Vec<Self>> {
+        let root = cache.host_toolchains();
+        if !root.exists() {
+            return Ok(vec![]);
+        }
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(root.as_path())
+            .map_err(|error| anyhow::anyhow!("Directory {root:?} reading: {error}"))?
+        {
+            let directory = entry?.path();
+            if !directory.is_dir() {
+                continue;
+            }
+            let last_used = [
+                directory.join(HostToolchain::LAST_USED_FILE_NAME),
+                directory.clone(),
+            ]
+            .iter()
+            .find_map(|path| {
+                path.metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            });
+            entries.push(Self {
+                toolchain: HostToolchain::load(directory.as_path())?,
+                directory,
+                last_used,
+            });
+        }
+        entries.sort_by(|a, b| a.directory.cmp(&b.directory));
+        Ok(entries)
+    }
+
+    ///
+    /// Removes the entries not used for the duration, and the incomplete ones that are not
+    /// being populated, returning the removed entries.
+    ///
+    /// The entries being populated or removed by other processes are skipped.
+    ///
+    pub fn prune(cache: &Cache, unused_for: Duration) -> anyhow::Result<Vec<Self>> {
+        let now = SystemTime::now();
+        let mut removed = Vec::new();
+        for entry in Self::list(cache)?.into_iter() {
+            let is_stale = entry.last_used.is_none_or(|last_used| {
+                now.duration_since(last_used).unwrap_or_default() >= unused_for
+            });
+            if entry.toolchain.is_some() && !is_stale {
+                continue;
+            }
+            let Some(lock) = Self::try_lock(entry.directory.as_path())? else {
+                continue;
+            };
+            Self::remove_locked(entry.directory.as_path(), lock)?;
+            removed.push(entry);
+        }
+        Ok(removed)
+    }
+
+    ///
+    /// Removes the entry with the key, unless it is being populated or used by another process.
+    ///
+    pub fn remove(cache: &Cache, key: &str) -> anyhow::Result<Self> {
+        let entry = Self::list(cache)?
+            .into_iter()
+            .find(|entry| entry.directory.file_name() == Some(std::ffi::OsStr::new(key)))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Host toolchain `{key}` is not in the cache {:?}",
+                    cache.host_toolchains()
+                )
+            })?;
+        let lock = Self::try_lock(entry.directory.as_path())?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Host toolchain `{key}` is being populated by another build. Try again later"
+            )
+        })?;
+        Self::remove_locked(entry.directory.as_path(), lock)?;
+        Ok(entry)
+    }
+
+    ///
+    /// Removes the locked entry with its lock file.
+    ///
+    /// The builds waiting for the removed lock file lock the new one instead.
+    ///
+    fn remove_locked(directory: &Path, lock: File) -> anyhow::Result<()> {
+        std::fs::remove_dir_all(directory)
+            .map_err(|error| anyhow::anyhow!("Cache entry {directory:?} removing: {error}"))?;
+        let lock_path = Self::lock_path(directory);
+        std::fs::remove_file(lock_path.as_path())
+            .map_err(|error| anyhow::anyhow!("Lock file {lock_path:?} removing: {error}"))?;
+        drop(lock);
+        Ok(())
+    }
+
+    ///
+    /// Packages the MUSL, CRT, and host toolchain targets in the directory into a `.tar.zst`
+    /// archive with the manifest first.
+    ///
+    /// The directory is either the complete cache entry or the LLVM target directory of the
+    /// workspace.
+    ///
+    pub fn export(
+        toolchain: &HostToolchain,
+        directory: &Path,
+        archive: &Path,
+    ) -> anyhow::Result<()> {
+        for name in HostToolchain::DIRECTORIES.into_iter() {
+            if !directory.join(name).is_dir() {
+                anyhow::bail!(
+                    "The host toolchain in {directory:?} does not contain the `{name}` directory. Build it first"
+                );
+            }
+        }
+
+        let temporary = Cache::temporary(archive);
+        let result = (|| -> anyhow::Result<()> {
+            let file = File::create(temporary.as_path())?;
+            let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0)?.auto_finish());
+            builder.follow_symlinks(false);
+            let manifest = serde_json::to_vec_pretty(toolchain)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_size(manifest.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+            );
+            header.set_cksum();
+            builder.append_data(&mut header, HostToolchain::FILE_NAME, manifest.as_slice())?;
+            for name in HostToolchain::DIRECTORIES.into_iter() {
+                builder.append_dir_all(name, directory.join(name))?;
+            }
+            builder.into_inner()?;
+            Ok(())
+        })();
+        if let Err(error) = result {
+            let _ = std::fs::remove_file(temporary.as_path());
+            anyhow::bail!("Archive {archive:?} writing: {error}");
+        }
+        std::fs::rename(temporary.as_path(), archive)
+            .map_err(|error| anyhow::anyhow!("Archive {archive:?} writing: {error}"))?;
+        Ok(())
+    }
+
+    ///
+    /// Unpacks the `.tar.zst` archive into the cache entry of its manifest key, unless the
+    /// entry is already complete.
+    ///
+    /// The archive is unpacked to a temporary directory and renamed under the entry lock.
+    /// As the toolchain may have been built at another path, the host `clang` and `clang++`
+    /// configuration files are written to point the default sysroot at the imported musl.
+    ///
+    pub fn import(cache: &Cache, archive: &Path) -> anyhow::Result<HostToolchain> {
+        let temporary = Cache::temporary(cache.root.join("import").as_path());
+        let toolchain = Self::unpack_temporary(archive, temporary.as_path())?;
+
+        let directory = cache.host_toolchain(toolchain.key.as_str());
+        let _lock = Self::lock(directory.as_path())?;
+        if HostToolchain::load(directory.as_path())?.is_some() {
+            std::fs::remove_dir_all(temporary.as_path())?;
+        } else {
+            if directory.exists() {
+                std::fs::remove_dir_all(directory.as_path())?;
+            }
+            std::fs::rename(temporary.as_path(), directory.as_path())
+                .map_err(|error| anyhow::anyhow!("Cache entry {directory:?} writing: {error}"))?;
+        }
+
+        Self::write_sysroot_configs(directory.as_path())?;
+        HostToolchain::touch(directory.as_path())?;
+        Ok(toolchain)
+    }
+
+    ///
+    /// Unpacks the `.tar.zst` archive into the LLVM target directory of the workspace,
+    /// replacing the MUSL, CRT, and host toolchain targets.
+    ///
+    /// The archive is unpacked to a temporary directory first, so a corrupted archive leaves
+    /// the workspace intact. The host `clang` and `clang++` configuration files are written
+    /// the same way as in the cache entries.
+    ///
+    pub fn import_workspace(target: &Path, archive: &Path) -> anyhow::Result<HostToolchain> {
+        let temporary = Cache::temporary(target.join("host-toolchain-import").as_path());
+        let toolchain = Self::unpack_temporary(archive, temporary.as_path())?;
+
+        for name in HostToolchain::DIRECTORIES.into_iter() {
+            let path = target.join(name);
+            if path.exists() {
+                std::fs::remove_dir_all(path.as_path())
+                    .map_err(|error| anyhow::anyhow!("Directory {path:?} removing: {error}"))?;
+            }
+            std::fs::rename(temporary.join(name), path.as_path())
+                .map_err(|error| anyhow::anyhow!("Directory {path:?} writing: {error}"))?;
+        }
+        std::fs::remove_dir_all(temporary.as_path())?;
+
+        Self::write_sysroot_configs(target)?;
+        Ok(toolchain)
+    }
+
+    ///
+    /// Unpacks the archive into the temporary directory, removing it if the archive is
+    /// rejected.
+    ///
+    fn unpack_temporary(archive: &Path, temporary: &Path) -> anyhow::Result<HostToolchain> {
+        if temporary.exists() {
+            std::fs::remove_dir_all(temporary)?;
+        }
+        std::fs::create_dir_all(temporary)?;
+        let result = Self::unpack(archive, temporary);
+        if result.is_err() {
+            std::fs::remove_dir_all(temporary)?;
+        }
+        result
+    }
+
+    ///
+    /// Points the default sysroot of the host `clang` and `clang++` at the musl target in
+    /// the directory.
+    ///
+    fn write_sysroot_configs(directory: &Path) -> anyhow::Result<()> {
+        let sysroot = format!(
+            "--sysroot={}\n",
+            directory.join("target-musl").to_string_lossy()
+        );
+        for driver in ["clang", "clang++"] {
+            let path = directory
+                .join("target-host")
+                .join("bin")
+                .join(format!("{driver}.cfg"));
+            std::fs::write(path.as_path(), sysroot.as_str())
+                .map_err(|error| anyhow::anyhow!("File {path:?} writing: {error}"))?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Unpacks the archive into the directory, checking its manifest and contents.
+    ///
+    fn unpack(archive: &Path, directory: &Path) -> anyhow::Result<HostToolchain> {
+        let file = File::open(archive)
+            .map_err(|error| anyhow::anyhow!("Archive {archive:?} opening: {error}"))?;
+        tar::Archive::new(zstd::Decoder::new(file)?)
+            .unpack(directory)
+            .map_err(|error| anyhow::anyhow!("Archive {archive:?} unpacking: {error}"))?;
+
+        let toolchain = HostToolchain::load(directory)?.ok_or_else(|| {
+            anyhow::anyhow!("Archive {archive:?} is not a host toolchain: the manifest is missing")
+        })?;
+        if toolchain.compute_key()? != toolchain.key {
+            anyhow::bail!("Archive {archive:?} manifest is corrupted: the key does not match");
+        }
+        for name in HostToolchain::DIRECTORIES.into_iter() {
+            if !directory.join(name).is_dir() {
+                anyhow::bail!("Archive {archive:?} does not contain the `{name}` directory");
+            }
+        }
+        Ok(toolchain)
+    }
+
+    ///
+    /// Locks the entry exclusively, waiting for the other processes populating it.
+    ///
+    /// The lock is released when the returned file is dropped. If the lock file has been
+    /// removed with the pruned entry while waiting, the new one is locked instead.
+    ///
+    pub fn lock(directory: &Path) -> anyhow::Result<File> {
+        loop {
+            let file = Self::lock_file(directory)?;
+            if !FileExt::try_lock_exclusive(&file)? {
+                eprintln!(
+                    "Waiting for another build populating the host toolchain {directory:?}..."
+                );
+                FileExt::lock_exclusive(&file)?;
+            }
+            if Self::is_lock_current(directory, &file)? {
+                return Ok(file);
+            }
+        }
+    }
+
+    ///
+    /// Locks the entry exclusively, unless it is locked or removed by another process.
+    ///
+    fn try_lock(directory: &Path) -> anyhow::Result<Option<File>> {
+        let file = Self::lock_file(directory)?;
+        if !FileExt::try_lock_exclusive(&file)? || !Self::is_lock_current(directory, &file)? {
+            return Ok(None);
+        }
+        Ok(Some(file))
+    }
+
+    ///
+    /// Whether the locked file is still the lock file of the entry, and not the one removed
+    /// with the pruned entry.
+    ///
+    fn is_lock_current(directory: &Path, file: &File) -> anyhow::Result<bool> {
+        let path = Self::lock_path(directory);
+        let current = match same_file::Handle::from_path(path.as_path()) {
+            Ok(current) => current,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => anyhow::bail!("Lock file {path:?} reading: {error}"),
+        };
+        Ok(current == same_file::Handle::from_file(file.try_clone()?)?)
+    }
+
+    ///
+    /// Returns the path of the lock file next to the entry directory.
+    ///
+    fn lock_path(directory: &Path) -> PathBuf {
+        let mut file_name = directory.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".lock");
+        directory.with_file_name(file_name)
+    }
+
+    ///
+    /// Opens the lock file next to the entry directory.
+    ///
+    fn lock_file(directory: &Path) -> anyhow::Result<File> {
+        let path = Self::lock_path(directory);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.as_path())
+            .map_err(|error| anyhow::anyhow!("Lock file {path:?} opening: {error}"))
+    }
+}
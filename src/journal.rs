@@ -0,0 +1,119 @@
+//!
+//! The build stage journal.
+//!
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The build stage journal.
+///
+/// Records the completed stages with the fingerprints of their inputs, so an interrupted
+/// build resumes from the first stage that has not been completed or whose inputs changed.
+/// The stages of the imported host toolchain are recorded with its key instead.
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    /// The completed stages by name.
+    pub stages: BTreeMap<String, JournalEntry>,
+}
+
+///
+/// The completed stage record.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// The fingerprint of the stage inputs.
+    pub fingerprint: String,
+    /// The completion timestamp.
+    pub completed: String,
+    /// The key of the imported host toolchain, if the stage has been imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported: Option<String>,
+}
+
+impl Journal {
+    /// The journal file name in the target directory.
+    pub const FILE_NAME: &'static str = "journal.json";
+
+    ///
+    /// Loads the journal, returning an empty one if it does not exist.
+    ///
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| anyhow::anyhow!("Journal {path:?} reading: {error}"))?;
+        serde_json::from_str(contents.as_str())
+            .map_err(|error| anyhow::anyhow!("Journal {path:?} parsing: {error}"))
+    }
+
+    ///
+    /// Saves the journal.
+    ///
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|error| anyhow::anyhow!("Journal {path:?} writing: {error}"))
+    }
+
+    ///
+    /// Whether the stage has been completed with the same inputs.
+    ///
+    pub fn is_completed(&self, stage: &str, fingerprint: &str) -> bool {
+        self.stages
+            .get(stage)
+            .is_some_and(|entry| entry.fingerprint == fingerprint)
+    }
+
+    ///
+    /// Records the stage completion.
+    ///
+    pub fn complete(&mut self, stage: &str, fingerprint: String) {
+        self.stages.insert(
+            stage.to_owned(),
+            JournalEntry {
+                fingerprint,
+                completed: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                imported: None,
+            },
+        );
+    }
+
+    ///
+    /// Whether the stage has been imported with the host toolchain.
+    ///
+    pub fn is_imported(&self, stage: &str) -> bool {
+        self.stages
+            .get(stage)
+            .is_some_and(|entry| entry.imported.is_some())
+    }
+
+    ///
+    /// Records the stage imported with the host toolchain with the key.
+    ///
+    pub fn import(&mut self, stage: &str, key: &str) {
+        self.stages.insert(
+            stage.to_owned(),
+            JournalEntry {
+                fingerprint: String::new(),
+                completed: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                imported: Some(key.to_owned()),
+            },
+        );
+    }
+
+    ///
+    /// Removes the stage record before running it, so an interrupted stage is not trusted.
+    ///
+    pub fn invalidate(&mut self, stage: &str) {
+        self.stages.remove(stage);
+    }
+}
@@ -2,49 +2,111 @@
 //! The ZKsync LLVM builder library.
 //!
 
+pub mod build_options;
 pub mod build_type;
+pub mod cache;
 pub mod ccache_variant;
+pub mod cmake_config;
+pub mod config;
+pub mod host;
+pub mod host_toolchain;
+pub mod journal;
 pub mod llvm_path;
 pub mod llvm_project;
 pub mod lock;
+pub mod mirrors;
+pub mod plan;
 pub mod platforms;
+pub mod report;
+pub mod retry;
+pub mod runner;
 pub mod sanitizer;
 pub mod target_env;
 pub mod target_triple;
 pub mod utils;
 
+pub use self::build_options::BuildOptions;
 pub use self::build_type::BuildType;
+pub use self::cmake_config::CMakeConfig;
+pub use self::config::Config;
+pub use self::host::Host;
 pub use self::llvm_path::LLVMPath;
 pub use self::lock::Lock;
+pub use self::plan::Plan;
 pub use self::platforms::Platform;
+pub use self::report::BuildReport;
 pub use self::target_triple::TargetTriple;
 
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::Path;
+use std::time::Duration;
+
+use self::cache::Cache;
+use self::host::Arch;
+use self::host::OS;
+use self::host_toolchain::HostToolchain;
+use self::host_toolchain::HostToolchainEntry;
+use self::journal::Journal;
+use self::lock::HostSource;
+use self::lock::MuslSource;
+use self::plan::CommandStep;
+use self::plan::Stage;
+use self::plan::StageMode;
+use self::plan::StageSelection;
+use self::plan::Step;
+use self::runner::CommandRunner;
+use self::target_env::TargetEnv;
 
 ///
 /// Executes the LLVM host repository cloning for stage 1 MUSL builds.
 ///
-pub fn clone_host() -> anyhow::Result<()> {
-    let destination_path = PathBuf::from(LLVMPath::DIRECTORY_LLVM_HOST_SOURCE);
+/// The repository is cloned through the cache, if any, or from the first available mirror.
+/// If the lock file pins the tag commit, the cloned repository is checked against it.
+///
+pub fn clone_host(
+    host: &HostSource,
+    paths: &LLVMPath,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let destination_path = paths.llvm_host_source();
     if destination_path.exists() {
         eprintln!("The host repository is already cloned at {destination_path:?}. Skipping...",);
-        return Ok(());
+    } else {
+        let urls = match runner.cache().cloned() {
+            Some(cache) => {
+                let repository = cache.repository(host.url.as_str(), host.tag.as_str(), runner)?;
+                vec![format!("file://{}", repository.to_string_lossy())]
+            }
+            None => mirrors::candidates(runner, host.url.as_str()),
+        };
+        mirrors::try_each(urls.as_slice(), "LLVM host repository cloning", |url| {
+            runner.run(
+                &CommandStep::new("git", "LLVM host repository cloning").args([
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--branch",
+                    host.tag.as_str(),
+                    url,
+                    destination_path.to_string_lossy().as_ref(),
+                ]),
+            )
+        })?;
     }
 
-    utils::command(
-        Command::new("git").args([
-            "clone",
-            "--depth",
-            "1",
-            "--branch",
-            utils::LLVM_HOST_SOURCE_TAG,
-            utils::LLVM_HOST_SOURCE_URL,
-            destination_path.to_string_lossy().as_ref(),
-        ]),
-        "LLVM host repository cloning",
-    )?;
+    if let (Some(expected), true) = (host.commit.as_deref(), runner.applies_side_effects()) {
+        let actual = runner.output(
+            &CommandStep::new("git", "LLVM host repository commit reading")
+                .args(["rev-parse", "HEAD"])
+                .current_dir(destination_path.as_path()),
+        )?;
+        if actual.trim() != expected {
+            anyhow::bail!(
+                "The host repository at {destination_path:?} is at commit {}, but the lock file expects `{}` at {expected}",
+                actual.trim(),
+                host.tag
+            );
+        }
+    }
 
     Ok(())
 }
@@ -52,19 +114,72 @@ pub fn clone_host() -> anyhow::Result<()> {
 ///
 /// Executes the LLVM repository cloning.
 ///
-pub fn clone(lock: Lock, deep: bool, target_env: target_env::TargetEnv) -> anyhow::Result<()> {
-    utils::exists("git")?;
+/// The LLVM host repository is also cloned if the host is a Linux MUSL one, as its stage 1
+/// toolchain is built from it. The repository already cloned at the pinned commit is kept,
+/// e.g. the one copied to an offline machine.
+///
+pub fn clone(
+    lock: Lock,
+    deep: bool,
+    host: Host,
+    paths: &LLVMPath,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    lock.ensure_remote()?;
+    if runner.applies_side_effects() {
+        utils::exists("git")?;
+    }
 
-    // Clone the host repository if the target is musl.
-    if cfg!(target_os = "linux") && target_env == target_env::TargetEnv::MUSL {
-        clone_host()?;
+    let host_source = lock.host.clone().unwrap_or_default();
+    let is_host_required = host.os == OS::Linux && host.env == TargetEnv::MUSL;
+    let destination_path = paths.llvm_source();
+    let is_cloned = destination_path.exists();
+    if is_cloned {
+        let head = runner
+            .output(
+                &CommandStep::new("git", "LLVM repository commit reading")
+                    .args(["rev-parse", "HEAD"])
+                    .current_dir(destination_path.as_path()),
+            )
+            .ok();
+        match (lock.r#ref.as_deref(), head.as_deref().map(str::trim)) {
+            (Some(expected), Some(actual)) if expected == actual => eprintln!(
+                "The repository is already cloned at {destination_path:?} at the pinned commit. Skipping..."
+            ),
+            _ => anyhow::bail!(
+                "The repository is already cloned at {destination_path:?}. Use `checkout` instead",
+            ),
+        }
     }
 
-    let destination_path = PathBuf::from(LLVMPath::DIRECTORY_LLVM_SOURCE);
-    if destination_path.exists() {
-        anyhow::bail!(
-            "The repository is already cloned at {destination_path:?}. Use `checkout` instead",
-        );
+    if let Some(cache) = runner.cache() {
+        let mut missing = Vec::new();
+        if is_host_required
+            && !paths.llvm_host_source().exists()
+            && !cache
+                .git(host_source.url.as_str(), host_source.tag.as_str())
+                .exists()
+        {
+            missing.push(format!(
+                "the host LLVM repository `{}` at `{}`",
+                host_source.url, host_source.tag
+            ));
+        }
+        if !is_cloned {
+            missing.push(format!(
+                "the LLVM repository `{}`, which is not cached; copy a checkout and build it with `--llvm-source`",
+                lock.url
+            ));
+        }
+        cache.check_missing(missing.as_slice())?;
+    }
+
+    // Clone the host repository if the target is musl.
+    if is_host_required {
+        clone_host(&host_source, paths, runner)?;
+    }
+    if is_cloned {
+        return Ok(());
     }
 
     let mut clone_args = vec!["clone", "--branch", lock.branch.as_str()];
@@ -73,20 +188,30 @@ pub fn clone(lock: Lock, deep: bool, target_env: target_env::TargetEnv) -> anyho
         clone_args.push("1");
     }
 
-    utils::command(
-        Command::new("git")
-            .args(clone_args)
-            .arg(lock.url.as_str())
-            .arg(destination_path.to_string_lossy().as_ref()),
-        "LLVM repository cloning",
-    )?;
+    let urls = mirrors::candidates(runner, lock.url.as_str());
+    mirrors::try_each(urls.as_slice(), "LLVM repository cloning", |url| {
+        runner.run(
+            &CommandStep::new("git", "LLVM repository cloning")
+                .args(clone_args.as_slice())
+                .arg(url)
+                .arg(destination_path.to_string_lossy()),
+        )
+    })?;
 
     if let Some(r#ref) = lock.r#ref {
-        utils::command(
-            Command::new("git")
+        let remotes = remotes(lock.url.as_str(), &*runner);
+        fetch_ref(
+            destination_path.as_path(),
+            r#ref.as_str(),
+            lock.branch.as_str(),
+            !deep,
+            remotes.as_slice(),
+            runner,
+        )?;
+        runner.run(
+            &CommandStep::new("git", "LLVM repository commit checking out")
                 .args(["checkout", r#ref.as_str()])
-                .current_dir(destination_path.to_string_lossy().as_ref()),
-            "LLVM repository commit checking out",
+                .current_dir(destination_path.as_path()),
         )?;
     }
 
@@ -96,204 +221,694 @@ pub fn clone(lock: Lock, deep: bool, target_env: target_env::TargetEnv) -> anyho
 ///
 /// Executes the checkout of the specified branch.
 ///
-pub fn checkout(lock: Lock, force: bool) -> anyhow::Result<()> {
-    let destination_path = PathBuf::from(LLVMPath::DIRECTORY_LLVM_SOURCE);
+pub fn checkout(
+    lock: Lock,
+    force: bool,
+    paths: &LLVMPath,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    lock.ensure_remote()?;
+    let destination_path = paths.llvm_source();
 
-    utils::command(
-        Command::new("git")
-            .current_dir(destination_path.as_path())
-            .args(["fetch", "--all", "--tags"]),
-        "LLVM repository data fetching",
-    )?;
+    if runner.cache().is_some_and(|cache| cache.offline) {
+        eprintln!("The LLVM repository fetching is skipped in the offline mode");
+    } else {
+        let shallow = runner
+            .output(
+                &CommandStep::new("git", "LLVM repository shallowness checking")
+                    .current_dir(destination_path.as_path())
+                    .args(["rev-parse", "--is-shallow-repository"]),
+            )?
+            .trim()
+            == "true";
+        let remotes = remotes(lock.url.as_str(), &*runner);
+        match lock.r#ref.as_deref() {
+            Some(r#ref) => fetch_ref(
+                destination_path.as_path(),
+                r#ref,
+                lock.branch.as_str(),
+                shallow,
+                remotes.as_slice(),
+                runner,
+            )?,
+            None => {
+                let mut fetch_command = CommandStep::new("git", "LLVM repository data fetching")
+                    .current_dir(destination_path.as_path())
+                    .arg("fetch");
+                if shallow {
+                    fetch_command = fetch_command.args(["--depth", "1"]);
+                }
+                mirrors::try_each(
+                    remotes.as_slice(),
+                    "LLVM repository data fetching",
+                    |remote| {
+                        runner.run(&fetch_command.clone().args([remote, lock.branch.as_str()]))
+                    },
+                )?;
+            }
+        }
+    }
 
     if force {
-        utils::command(
-            Command::new("git")
+        runner.run(
+            &CommandStep::new("git", "LLVM repository cleaning")
                 .current_dir(destination_path.as_path())
                 .args(["clean", "-d", "-x", "--force"]),
-            "LLVM repository cleaning",
         )?;
     }
 
-    utils::command(
-        Command::new("git")
+    runner.run(
+        &CommandStep::new("git", "LLVM repository data pulling")
             .current_dir(destination_path.as_path())
             .args(["checkout", "--force", lock.branch.as_str()]),
-        "LLVM repository data pulling",
     )?;
 
     if let Some(r#ref) = lock.r#ref {
-        let mut checkout_command = Command::new("git");
-        checkout_command.current_dir(destination_path.as_path());
-        checkout_command.arg("checkout");
+        let mut checkout_command = CommandStep::new("git", "LLVM repository checking out")
+            .current_dir(destination_path.as_path())
+            .arg("checkout");
         if force {
-            checkout_command.arg("--force");
+            checkout_command = checkout_command.arg("--force");
         }
-        checkout_command.arg(r#ref);
-        utils::command(&mut checkout_command, "LLVM repository checking out")?;
+        runner.run(&checkout_command.arg(r#ref))?;
     }
 
     Ok(())
 }
 
 ///
-/// Executes the building of the LLVM framework for the platform determined by the cfg macro.
-/// Since cfg is evaluated at compile time, overriding the platform with a command-line
-/// argument is not possible. So for cross-platform testing, comment out all but the
-/// line to be tested, and perhaps also checks in the platform-specific build method.
+/// Populates the cache with the musl tarball and the host LLVM repository, so the builds
+/// can be run in the offline mode.
 ///
-#[allow(clippy::too_many_arguments)]
-pub fn build(
-    build_type: BuildType,
-    target_env: target_env::TargetEnv,
-    targets: HashSet<Platform>,
-    llvm_projects: HashSet<llvm_project::LLVMProject>,
-    enable_rtti: bool,
-    default_target: Option<TargetTriple>,
-    enable_tests: bool,
-    enable_coverage: bool,
-    extra_args: Vec<String>,
-    ccache_variant: Option<ccache_variant::CcacheVariant>,
-    enable_assertions: bool,
-    sanitizer: Option<sanitizer::Sanitizer>,
-    enable_valgrind: bool,
-    valgrind_options: Vec<String>,
+pub fn fetch(
+    host: &HostSource,
+    musl: &MuslSource,
+    runner: &mut dyn CommandRunner,
 ) -> anyhow::Result<()> {
-    std::fs::create_dir_all(LLVMPath::DIRECTORY_LLVM_TARGET)?;
-
-    if cfg!(target_arch = "x86_64") {
-        if cfg!(target_os = "linux") {
-            if target_env == target_env::TargetEnv::MUSL {
-                platforms::x86_64_linux_musl::build(
-                    build_type,
-                    targets,
-                    llvm_projects,
-                    enable_rtti,
-                    default_target,
-                    enable_tests,
-                    enable_coverage,
-                    extra_args,
-                    ccache_variant,
-                    enable_assertions,
-                    sanitizer,
-                    enable_valgrind,
-                    valgrind_options,
-                )?;
-            } else if target_env == target_env::TargetEnv::GNU {
-                platforms::x86_64_linux_gnu::build(
-                    build_type,
-                    targets,
-                    llvm_projects,
-                    enable_rtti,
-                    default_target,
-                    enable_tests,
-                    enable_coverage,
-                    extra_args,
-                    ccache_variant,
-                    enable_assertions,
-                    sanitizer,
-                    enable_valgrind,
-                    valgrind_options,
-                )?;
-            } else {
-                anyhow::bail!("Unsupported target environment for x86_64 and Linux");
-            }
-        } else if cfg!(target_os = "macos") {
-            platforms::x86_64_macos::build(
-                build_type,
-                targets,
-                llvm_projects,
-                enable_rtti,
-                default_target,
-                enable_tests,
-                enable_coverage,
-                extra_args,
-                ccache_variant,
-                enable_assertions,
-                sanitizer,
-            )?;
-        } else if cfg!(target_os = "windows") {
-            platforms::x86_64_windows_gnu::build(
-                build_type,
-                targets,
-                llvm_projects,
-                enable_rtti,
-                default_target,
-                enable_tests,
-                enable_coverage,
-                extra_args,
-                ccache_variant,
-                enable_assertions,
-                sanitizer,
+    let cache = runner
+        .cache()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("The cache directory is not set"))?;
+    if cache.offline {
+        anyhow::bail!("The sources cannot be fetched in the offline mode");
+    }
+
+    let sha256 = musl.sha256()?;
+    fetch_file(
+        musl.tarball_url().as_str(),
+        musl.file_name().as_str(),
+        sha256.as_str(),
+        &cache,
+        runner,
+    )?;
+    println!("{}: {:?}", musl.name(), cache.file(sha256.as_str()));
+
+    if let Some(kernel_headers) = musl.kernel_headers.as_ref() {
+        if let Some(version) = kernel_headers.version.as_deref() {
+            let sha256 = kernel_headers.sha256(version)?;
+            let name = lock::KernelHeaders::name(version);
+            fetch_file(
+                kernel_headers.tarball_url(version).as_str(),
+                format!("{name}.tar.gz").as_str(),
+                sha256.as_str(),
+                &cache,
+                runner,
             )?;
-        } else {
-            anyhow::bail!("Unsupported target OS for x86_64");
+            println!("{name}: {:?}", cache.file(sha256.as_str()));
         }
-    } else if cfg!(target_arch = "aarch64") {
-        if cfg!(target_os = "linux") {
-            if target_env == target_env::TargetEnv::MUSL {
-                platforms::aarch64_linux_musl::build(
-                    build_type,
-                    targets,
-                    llvm_projects,
-                    enable_rtti,
-                    default_target,
-                    enable_tests,
-                    enable_coverage,
-                    extra_args,
-                    ccache_variant,
-                    enable_assertions,
-                    sanitizer,
-                    enable_valgrind,
-                    valgrind_options,
-                )?;
-            } else if target_env == target_env::TargetEnv::GNU {
-                platforms::aarch64_linux_gnu::build(
-                    build_type,
-                    targets,
-                    llvm_projects,
-                    enable_rtti,
-                    default_target,
-                    enable_tests,
-                    enable_coverage,
-                    extra_args,
-                    ccache_variant,
-                    enable_assertions,
-                    sanitizer,
-                    enable_valgrind,
-                    valgrind_options,
-                )?;
-            } else {
-                anyhow::bail!("Unsupported target environment for aarch64 and Linux");
+    }
+
+    let repository = cache.repository(host.url.as_str(), host.tag.as_str(), runner)?;
+    println!("{} {}: {repository:?}", host.url, host.tag);
+    Ok(())
+}
+
+///
+/// Downloads the file into the cache unless it is already there.
+///
+fn fetch_file(
+    url: &str,
+    file_name: &str,
+    sha256: &str,
+    cache: &Cache,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    if cache.file(sha256).exists() {
+        return Ok(());
+    }
+    let download_directory = Cache::temporary(cache.root.join("download").as_path());
+    std::fs::create_dir_all(download_directory.as_path())?;
+    let result = utils::download(
+        url,
+        download_directory.as_path(),
+        file_name,
+        sha256,
+        &runner.retry_policy(),
+        Some(cache),
+        runner.mirrors(),
+    );
+    std::fs::remove_dir_all(download_directory.as_path())?;
+    result
+}
+
+///
+/// Identifies the stage 1 host toolchain of the workspace.
+///
+/// The toolchain imported into the workspace is used if it has been built for the same host
+/// and musl release. Otherwise, it is identified by the host source commit and the build tool
+/// versions, unless the host source is not a clean Git repository.
+///
+pub fn host_toolchain(
+    host: Host,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<Option<HostToolchain>> {
+    let imported_path = paths.imported_host_toolchain();
+    if let Some(imported) = HostToolchain::read(imported_path.as_path())? {
+        if imported.matches(host, musl)? {
+            return Ok(Some(imported));
+        }
+        eprintln!(
+            "The imported host toolchain `{}` is built for `{}` with {}, so it is not used",
+            imported.key, imported.host, imported.musl
+        );
+    }
+
+    let host_source = paths.llvm_host_source();
+    let commit = runner.output(
+        &CommandStep::new("git", "LLVM host repository commit reading")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(host_source.as_path()),
+    );
+    let status = runner.output(
+        &CommandStep::new("git", "LLVM host repository state reading")
+            .args(["status", "--porcelain"])
+            .current_dir(host_source.as_path()),
+    );
+    let commit = match (commit, status) {
+        (Ok(commit), Ok(status)) if status.trim().is_empty() => commit.trim().to_owned(),
+        _ => return Ok(None),
+    };
+    let tool_versions = plan(&BuildOptions::default(), host, paths, musl)?.tool_versions(runner)?;
+    HostToolchain::new(host.to_string(), commit, musl, tool_versions).map(Some)
+}
+
+///
+/// Packages the stage 1 host toolchain of the workspace into the archive.
+///
+/// The toolchain is taken from the shared cache if it is complete there. Otherwise, it is
+/// taken from the workspace, where its stages must have been completed or imported.
+///
+pub fn export_host_toolchain(
+    host: Host,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+    archive: &Path,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let mut toolchain = host_toolchain(host, paths, musl, runner)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "The host toolchain cannot be identified, as the host source {:?} is not a clean Git repository",
+            paths.llvm_host_source()
+        )
+    })?;
+    let cached = runner
+        .cache()
+        .filter(|cache| cache.shares_host_toolchains)
+        .map(|cache| cache.host_toolchain(toolchain.key.as_str()));
+    let directory = match cached {
+        Some(directory) if HostToolchain::load(directory.as_path())?.is_some() => directory,
+        _ => {
+            let journal = Journal::load(paths.journal().as_path())?;
+            let Some(completed) = HostToolchain::STAGES
+                .into_iter()
+                .map(|stage| journal.stages.get(stage))
+                .collect::<Option<Vec<_>>>()
+            else {
+                anyhow::bail!(
+                    "The host toolchain stages are not complete in the workspace {:?}. Build them first",
+                    paths.llvm_target()
+                );
+            };
+            if toolchain.created.is_empty() {
+                toolchain.created = completed
+                    .last()
+                    .map(|entry| entry.completed.clone())
+                    .unwrap_or_default();
             }
-        } else if cfg!(target_os = "macos") {
-            platforms::aarch64_macos::build(
-                build_type,
-                targets,
-                llvm_projects,
-                enable_rtti,
-                default_target,
-                enable_tests,
-                enable_coverage,
-                extra_args,
-                ccache_variant,
-                enable_assertions,
-                sanitizer,
-            )?;
-        } else {
-            anyhow::bail!("Unsupported target OS for aarch64");
+            paths.llvm_target()
         }
-    } else {
-        anyhow::bail!("Unsupported target architecture");
+    };
+    HostToolchainEntry::export(&toolchain, directory.as_path(), archive)?;
+    println!(
+        "Exported the host toolchain `{}` for `{}` with {} and LLVM {} to {archive:?}",
+        toolchain.key, toolchain.host, toolchain.musl, toolchain.commit
+    );
+    Ok(())
+}
+
+///
+/// Unpacks the stage 1 host toolchain archive and marks its stages complete in the workspace,
+/// so the builds start from the final stage.
+///
+/// The toolchain is unpacked into the shared cache if it is enabled, or into the workspace
+/// otherwise, where its stages are recorded as imported in the journal.
+///
+pub fn import_host_toolchain(
+    paths: &LLVMPath,
+    archive: &Path,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let (toolchain, directory) = match runner.cache().filter(|cache| cache.shares_host_toolchains) {
+        Some(cache) => {
+            let toolchain = HostToolchainEntry::import(cache, archive)?;
+            let directory = cache.host_toolchain(toolchain.key.as_str());
+            (toolchain, directory)
+        }
+        None => {
+            let directory = paths.llvm_target();
+            let toolchain = HostToolchainEntry::import_workspace(directory.as_path(), archive)?;
+            let mut journal = Journal::load(paths.journal().as_path())?;
+            for stage in HostToolchain::STAGES.into_iter() {
+                journal.import(stage, toolchain.key.as_str());
+            }
+            journal.save(paths.journal().as_path())?;
+            (toolchain, directory)
+        }
+    };
+    toolchain.write(paths.imported_host_toolchain().as_path())?;
+    println!(
+        "Imported the host toolchain `{}` for `{}` with {} and LLVM {} to {directory:?}",
+        toolchain.key, toolchain.host, toolchain.musl, toolchain.commit,
+    );
+    Ok(())
+}
+
+///
+/// Forgets the host toolchain imported into the workspace if it has been built for another
+/// host or musl release, so its stages are built again.
+///
+fn check_imported_host_toolchain(
+    host: Host,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+) -> anyhow::Result<()> {
+    let imported_path = paths.imported_host_toolchain();
+    let Some(imported) = HostToolchain::read(imported_path.as_path())? else {
+        return Ok(());
+    };
+    if imported.matches(host, musl)? {
+        return Ok(());
+    }
+    let mut journal = Journal::load(paths.journal().as_path())?;
+    for stage in HostToolchain::STAGES.into_iter() {
+        if journal.is_imported(stage) {
+            journal.invalidate(stage);
+        }
+    }
+    journal.save(paths.journal().as_path())?;
+    std::fs::remove_file(imported_path.as_path())?;
+    Ok(())
+}
+
+///
+/// Prints the shared host toolchains in the cache.
+///
+pub fn list_host_toolchains(cache: &Cache) -> anyhow::Result<()> {
+    let entries = HostToolchainEntry::list(cache)?;
+    if entries.is_empty() {
+        println!("No host toolchains in {:?}", cache.host_toolchains());
+    }
+    for entry in entries.into_iter() {
+        let key = entry
+            .directory
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let last_used = entry
+            .last_used
+            .map(|last_used| humantime::format_rfc3339_seconds(last_used).to_string())
+            .unwrap_or_else(|| "unknown".to_owned());
+        match entry.toolchain {
+            Some(toolchain) => println!(
+                "{key}  {}  {}  LLVM {}  last used {last_used}",
+                toolchain.host, toolchain.musl, toolchain.commit
+            ),
+            None => println!("{key}  incomplete  last modified {last_used}"),
+        }
+    }
+    Ok(())
+}
+
+///
+/// Removes the shared host toolchains not used for the duration, and the incomplete ones.
+///
+pub fn prune_host_toolchains(cache: &Cache, unused_for: Duration) -> anyhow::Result<()> {
+    let removed = HostToolchainEntry::prune(cache, unused_for)?;
+    for entry in removed.iter() {
+        println!("Removed {:?}", entry.directory);
+    }
+    println!("Removed {} host toolchain(s)", removed.len());
+    Ok(())
+}
+
+///
+/// Removes the shared host toolchain with the key.
+///
+pub fn remove_host_toolchain(cache: &Cache, key: &str) -> anyhow::Result<()> {
+    let removed = HostToolchainEntry::remove(cache, key)?;
+    println!("Removed {:?}", removed.directory);
+    Ok(())
+}
+
+///
+/// Pins the remote head of the lock branch, or of the specified branch, into the lock file.
+///
+/// In the check mode, fails if the pinned commit is stale instead of updating it.
+///
+pub fn update_lock(
+    lock_path: &Path,
+    branch: Option<String>,
+    check: bool,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let lock = Lock::try_from(&lock_path.to_path_buf())?;
+    lock.ensure_remote()?;
+    let branch = branch.unwrap_or_else(|| lock.branch.clone());
+    let head = lock.remote_head(branch.as_str(), runner)?;
+
+    if branch == lock.branch && lock.r#ref.as_deref() == Some(head.as_str()) {
+        println!("{lock_path:?} is up to date with `{branch}` at {head}");
+        return Ok(());
     }
 
+    let old = match lock.r#ref.as_deref() {
+        Some(old) => format!("`{}` at {old}", lock.branch),
+        None => format!("`{}` without a commit", lock.branch),
+    };
+    if check {
+        anyhow::bail!("{lock_path:?} is stale: pinned {old}, but `{branch}` is at {head}");
+    }
+    Lock::pin(lock_path, branch.as_str(), head.as_str())?;
+    match lock.r#ref.as_deref() {
+        Some(old_ref) if branch == lock.branch => {
+            println!("Updated {lock_path:?}: `{branch}` {old_ref}..{head}")
+        }
+        _ => println!("Updated {lock_path:?}: {old} -> `{branch}` at {head}"),
+    }
     Ok(())
 }
 
+///
+/// Returns the remotes to fetch the LLVM repository from.
+///
+/// The `origin` remote is used unless the repository URL has mirrors or is rewritten, so
+/// the fetches go to the same servers as the clone.
+///
+fn remotes(url: &str, runner: &dyn CommandRunner) -> Vec<String> {
+    let urls = mirrors::candidates(runner, url);
+    if urls.as_slice() == [url] {
+        return vec!["origin".to_owned()];
+    }
+    urls
+}
+
+///
+/// Fetches exactly the pinned commit from the first remote that has it, keeping the shallow
+/// repository shallow.
+///
+/// If the servers do not allow fetching commits by hash, the history of the branch is
+/// fetched instead, deepened in steps in the shallow repository until the commit is found.
+/// Falls back to fetching the whole history of all branches and tags as the last resort.
+///
+fn fetch_ref(
+    destination_path: &Path,
+    reference: &str,
+    branch: &str,
+    shallow: bool,
+    remotes: &[String],
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let mut fetch_command = CommandStep::new("git", "LLVM repository commit fetching")
+        .current_dir(destination_path)
+        .arg("fetch");
+    if shallow {
+        fetch_command = fetch_command.args(["--depth", "1"]);
+    }
+    for remote in remotes.iter() {
+        if runner
+            .run(&fetch_command.clone().args([remote.as_str(), reference]))
+            .is_ok()
+        {
+            return Ok(());
+        }
+    }
+
+    eprintln!("Fetching the commit `{reference}` failed. Fetching the history of `{branch}`...");
+    let refspec = format!("+refs/heads/{branch}:refs/remotes/origin/{branch}");
+    let depths: Vec<Option<u32>> = if shallow {
+        utils::FETCH_DEPTHS.into_iter().map(Some).collect()
+    } else {
+        vec![None]
+    };
+    for depth in depths.into_iter() {
+        let mut fetch_command = CommandStep::new("git", "LLVM repository branch fetching")
+            .current_dir(destination_path)
+            .arg("fetch");
+        if let Some(depth) = depth {
+            fetch_command = fetch_command.arg("--depth").arg(depth.to_string());
+        }
+        let fetched = mirrors::try_each(remotes, "LLVM repository branch fetching", |remote| {
+            runner.run(&fetch_command.clone().args([remote, refspec.as_str()]))
+        });
+        let is_found = runner
+            .output(
+                &CommandStep::new("git", "LLVM repository commit checking")
+                    .current_dir(destination_path)
+                    .args(["cat-file", "-e"])
+                    .arg(format!("{reference}^{{commit}}")),
+            )
+            .is_ok();
+        if fetched.is_ok() && is_found {
+            return Ok(());
+        }
+    }
+
+    eprintln!(
+        "The commit `{reference}` is not found in the history of `{branch}`. Fetching the whole history..."
+    );
+    let mut fetch_command = CommandStep::new("git", "LLVM repository history fetching")
+        .current_dir(destination_path)
+        .arg("fetch");
+    if shallow {
+        fetch_command = fetch_command.arg("--unshallow");
+    }
+    mirrors::try_each(remotes, "LLVM repository history fetching", |remote| {
+        runner.run(&fetch_command.clone().args([
+            "--tags",
+            remote,
+            "+refs/heads/*:refs/remotes/origin/*",
+        ]))
+    })
+}
+
+///
+/// Returns the building plan of the LLVM framework for the specified host.
+///
+/// The host is usually detected from the running builder, but can be overridden to plan
+/// the builds for other platforms, e.g. to generate and test the macOS plans on Linux.
+/// The musl source is only used by the MUSL hosts.
+///
+/// If the builder host is specified in the options and differs from the host, the plan
+/// cross-compiles the LLVM framework on the builder.
+///
+pub fn plan(
+    options: &BuildOptions,
+    host: Host,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+) -> anyhow::Result<Plan> {
+    if let Some(builder) = options.build_host.filter(|builder| host.is_cross(builder)) {
+        return match (builder.arch, builder.os, host.arch, host.os, host.env) {
+            (Arch::X86_64, OS::Linux, Arch::AArch64, OS::Linux, TargetEnv::GNU) => {
+                platforms::aarch64_linux_gnu::cross_plan(options, paths)
+            }
+            (Arch::X86_64, OS::Linux, Arch::AArch64, OS::Linux, TargetEnv::MUSL) => {
+                platforms::aarch64_linux_musl::cross_plan(options, paths, musl)
+            }
+            (Arch::X86_64, OS::Linux, Arch::X86_64, OS::Windows, TargetEnv::GNU) => {
+                platforms::x86_64_windows_gnu::cross_plan(options, paths)
+            }
+            _ => anyhow::bail!("Cross-compiling for `{host}` on `{builder}` is not supported"),
+        };
+    }
+
+    match (host.arch, host.os, host.env) {
+        (Arch::X86_64, OS::Linux, TargetEnv::GNU) => {
+            platforms::x86_64_linux_gnu::plan(options, paths)
+        }
+        (Arch::X86_64, OS::Linux, TargetEnv::MUSL) => {
+            platforms::x86_64_linux_musl::plan(options, paths, musl)
+        }
+        (Arch::X86_64, OS::MacOS, _) => platforms::x86_64_macos::plan(options, paths),
+        (Arch::X86_64, OS::Windows, TargetEnv::GNU) => {
+            platforms::x86_64_windows_gnu::plan(options, paths)
+        }
+        (Arch::AArch64, OS::Linux, TargetEnv::GNU) => {
+            platforms::aarch64_linux_gnu::plan(options, paths)
+        }
+        (Arch::AArch64, OS::Linux, TargetEnv::MUSL) => {
+            platforms::aarch64_linux_musl::plan(options, paths, musl)
+        }
+        (Arch::AArch64, OS::MacOS, _) => platforms::aarch64_macos::plan(options, paths),
+        (arch, os, env) => {
+            anyhow::bail!("Unsupported host: `{arch}` architecture, `{os}` OS, `{env}` environment")
+        }
+    }
+}
+
+///
+/// Executes the building of the LLVM framework for the specified host.
+///
+/// The stages completed with the same inputs are skipped unless forced by the selection.
+/// After a successful build, the LLVM source state is recorded in the build report.
+/// The stage 1 host toolchain of cross-compilation is built in the workspace, as it is
+/// not shared through the cache.
+///
+pub fn build(
+    options: BuildOptions,
+    host: Host,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+    selection: &StageSelection,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let plan = plan(&options, host, paths, musl)?;
+    if let Some(cache) = runner.cache() {
+        let missing: Vec<String> = plan
+            .stages
+            .iter()
+            .flat_map(|stage| stage.steps.iter())
+            .filter_map(|step| match step {
+                Step::Download {
+                    url,
+                    destination,
+                    file_name,
+                    sha256,
+                } if !destination.join(file_name).exists() && !cache.file(sha256).exists() => {
+                    Some(format!("`{file_name}` from `{url}` with SHA-256 {sha256}"))
+                }
+                _ => None,
+            })
+            .collect();
+        cache.check_missing(missing.as_slice())?;
+    }
+    if !runner.applies_side_effects() {
+        return plan.execute(runner, selection, paths.journal().as_path());
+    }
+
+    let report = BuildReport::new(paths.llvm_source().as_path(), host.to_string(), runner);
+    eprintln!("Building LLVM from {report}");
+    let is_cross = options
+        .build_host
+        .is_some_and(|builder| host.is_cross(&builder));
+    let is_host_cached = !is_cross && plan.stages.iter().any(|stage| stage.name == Stage::HOST);
+    match runner.cache().cloned() {
+        Some(cache) if is_host_cached && cache.shares_host_toolchains => {
+            build_with_host_toolchain(&options, host, paths, musl, selection, &cache, runner)?
+        }
+        _ => {
+            check_imported_host_toolchain(host, paths, musl)?;
+            plan.execute(runner, selection, paths.journal().as_path())?
+        }
+    }
+    report.save(paths.build_report().as_path())
+}
+
+///
+/// Executes the building with the stage 1 host toolchain taken from the shared cache.
+///
+/// The host toolchain is built into the cache under the entry lock if it is missing, so
+/// concurrent builds wait for each other instead of building it twice. The host stages of
+/// the complete entry cannot be forced by the selection, as the other builds may be using it.
+/// If the toolchain cannot be identified, it is built in the workspace.
+///
+fn build_with_host_toolchain(
+    options: &BuildOptions,
+    host: Host,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+    selection: &StageSelection,
+    cache: &Cache,
+    runner: &mut dyn CommandRunner,
+) -> anyhow::Result<()> {
+    let Some(mut toolchain) = host_toolchain(host, paths, musl, runner)? else {
+        eprintln!(
+            "The host source {:?} is not a clean Git repository. Building the host toolchain in the workspace...",
+            paths.llvm_host_source()
+        );
+        return plan(options, host, paths, musl)?.execute(
+            runner,
+            selection,
+            paths.journal().as_path(),
+        );
+    };
+    let directory = cache.host_toolchain(toolchain.key.as_str());
+    let paths = paths.to_owned().host_toolchain(directory.as_path());
+    let mut plan = plan(options, host, &paths, musl)?;
+
+    let modes = selection.modes(plan.stages.as_slice())?;
+    let split = plan
+        .stages
+        .iter()
+        .position(|stage| !HostToolchain::STAGES.contains(&stage.name.as_str()))
+        .unwrap_or(plan.stages.len());
+    let target_plan = plan.split_off(split);
+    let (host_modes, target_modes) = modes.split_at(split);
+
+    let is_forced = host_modes.contains(&StageMode::Forced);
+    let mut is_complete = HostToolchain::load(directory.as_path())?.is_some();
+    if !is_complete {
+        let _lock = HostToolchainEntry::lock(directory.as_path())?;
+        is_complete = HostToolchain::load(directory.as_path())?.is_some();
+        if !is_complete {
+            eprintln!("Building the host toolchain into the cache {directory:?}");
+            let host_modes: Vec<StageMode> = if is_forced {
+                host_modes.to_vec()
+            } else {
+                vec![StageMode::Auto; host_modes.len()]
+            };
+            std::fs::create_dir_all(directory.as_path())?;
+            plan.execute_modes(
+                runner,
+                host_modes.as_slice(),
+                directory.join(Journal::FILE_NAME).as_path(),
+            )?;
+            if !host_modes.contains(&StageMode::Skipped) {
+                toolchain.save(directory.as_path())?;
+            }
+        }
+    }
+    if is_complete {
+        if is_forced {
+            anyhow::bail!(
+                "The host toolchain stages cannot be forced, as they are shared with other builds through the cache {directory:?}. Remove the entry with `zksync-llvm cache remove {}`, or build without `--cache-dir`",
+                toolchain.key
+            );
+        }
+        eprintln!("The host toolchain is taken from the cache {directory:?}");
+    }
+    HostToolchain::touch(directory.as_path())?;
+
+    target_plan.execute_modes(runner, target_modes, paths.journal().as_path())
+}
+
 ///
 /// Executes the build artifacts cleaning.
 ///
-pub fn clean() -> anyhow::Result<()> {
-    std::fs::remove_dir_all(PathBuf::from(LLVMPath::DIRECTORY_LLVM_TARGET))?;
+pub fn clean(paths: &LLVMPath) -> anyhow::Result<()> {
+    std::fs::remove_dir_all(paths.llvm_target())?;
     Ok(())
 }
@@ -1,13 +1,32 @@
 //!
-//! The ZKsync LLVM builder constants.
+//! The ZKsync LLVM builder paths.
 //!
 
+use std::path::Path;
 use std::path::PathBuf;
 
+use crate::config::Directories;
+
 ///
 /// The LLVM path resolver.
 ///
-pub struct LLVMPath {}
+/// The source, host source, and target directories default to the workspace root, which is
+/// the directory containing `LLVM.lock`, or the current directory if there is no lock file.
+/// All the build and target directories are derived from the target directory.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LLVMPath {
+    /// The workspace root directory.
+    pub root: PathBuf,
+    /// The LLVM source directory.
+    pub source: PathBuf,
+    /// The LLVM host source directory.
+    pub host_source: PathBuf,
+    /// The LLVM target directory.
+    pub target: PathBuf,
+    /// The shared host toolchain directory replacing the stage 1 target directories.
+    pub host_toolchain: Option<PathBuf>,
+}
 
 impl LLVMPath {
     /// The LLVM host source directory for stage 1 of multistage MUSL builds.
@@ -19,103 +38,239 @@ impl LLVMPath {
     /// The LLVM target directory.
     pub const DIRECTORY_LLVM_TARGET: &'static str = "./target-llvm/";
 
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            source: root.join(Self::DIRECTORY_LLVM_SOURCE),
+            host_source: root.join(Self::DIRECTORY_LLVM_HOST_SOURCE),
+            target: root.join(Self::DIRECTORY_LLVM_TARGET),
+            host_toolchain: None,
+        }
+    }
+
+    ///
+    /// Creates the resolver for the workspace containing the lock file, or the current
+    /// directory if the lock file is not specified.
+    ///
+    pub fn from_lock(lock_path: Option<&Path>) -> anyhow::Result<Self> {
+        let current_dir = std::env::current_dir()?;
+        let root = match lock_path.and_then(Path::parent) {
+            Some(parent) => current_dir.join(parent),
+            None => current_dir,
+        };
+        Ok(Self::new(root.as_path()))
+    }
+
+    ///
+    /// Overrides the directories that are set, resolving the relative ones against the
+    /// workspace root.
+    ///
+    pub fn directories(mut self, directories: &Directories) -> Self {
+        if let Some(source) = directories.source.as_ref() {
+            self.source = self.root.join(source);
+        }
+        if let Some(host_source) = directories.host_source.as_ref() {
+            self.host_source = self.root.join(host_source);
+        }
+        if let Some(target) = directories.target.as_ref() {
+            self.target = self.root.join(target);
+        }
+        self
+    }
+
+    ///
+    /// Installs the MUSL, CRT, and host toolchain targets into the shared host toolchain
+    /// directory instead of the LLVM target directory.
+    ///
+    pub fn host_toolchain(mut self, directory: &Path) -> Self {
+        self.host_toolchain = Some(directory.to_path_buf());
+        self
+    }
+
+    ///
+    /// Returns the path to the LLVM source directory.
+    ///
+    pub fn llvm_source(&self) -> PathBuf {
+        self.source.clone()
+    }
+
+    ///
+    /// Returns the path to the LLVM host source directory.
+    ///
+    pub fn llvm_host_source(&self) -> PathBuf {
+        self.host_source.clone()
+    }
+
+    ///
+    /// Returns the path to the LLVM target directory.
+    ///
+    pub fn llvm_target(&self) -> PathBuf {
+        self.target.clone()
+    }
+
     ///
     /// Returns the path to the `llvm` stage 1 host LLVM source module directory.
     ///
-    pub fn llvm_host_module_llvm() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_HOST_SOURCE);
+    pub fn llvm_host_module_llvm(&self) -> PathBuf {
+        let mut path = self.llvm_host_source();
         path.push("llvm");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the `llvm` LLVM source module directory.
     ///
-    pub fn llvm_module_llvm() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_SOURCE);
+    pub fn llvm_module_llvm(&self) -> PathBuf {
+        let mut path = self.llvm_source();
         path.push("llvm");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the MUSL source.
     ///
-    pub fn musl_source(name: &str) -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn musl_source(&self, name: &str) -> PathBuf {
+        let mut path = self.llvm_target();
         path.push(name);
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the MUSL build directory.
     ///
-    pub fn musl_build(source_directory: &str) -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn musl_build(&self, source_directory: &str) -> PathBuf {
+        let mut path = self.llvm_target();
         path.push(source_directory);
         path.push("build");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the LLVM CRT build directory.
     ///
-    pub fn llvm_build_crt() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn llvm_build_crt(&self) -> PathBuf {
+        let mut path = self.llvm_target();
         path.push("build-crt");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the LLVM host build directory.
     ///
-    pub fn llvm_build_host() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn llvm_build_host(&self) -> PathBuf {
+        let mut path = self.llvm_target();
         path.push("build-host");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the LLVM final build directory.
     ///
-    pub fn llvm_build_final() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn llvm_build_final(&self) -> PathBuf {
+        let mut path = self.llvm_target();
         path.push("build-final");
-        crate::utils::absolute_path(path)
+        path
+    }
+
+    ///
+    /// Returns the path to the native table generators build directory of cross-compilation.
+    ///
+    pub fn llvm_build_tblgen(&self) -> PathBuf {
+        let mut path = self.llvm_target();
+        path.push("build-tblgen");
+        path
+    }
+
+    ///
+    /// Returns the path to the CMake toolchain file of the cross-compiled build.
+    ///
+    pub fn cmake_toolchain_file(&self, build: &str) -> PathBuf {
+        let mut path = self.llvm_target();
+        path.push(format!("toolchain-{build}.cmake"));
+        path
     }
 
     ///
     /// Returns the path to the MUSL target directory.
     ///
-    pub fn musl_target() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn musl_target(&self) -> PathBuf {
+        let mut path = self
+            .host_toolchain
+            .clone()
+            .unwrap_or_else(|| self.llvm_target());
         path.push("target-musl");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the LLVM CRT target directory.
     ///
-    pub fn llvm_target_crt() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn llvm_target_crt(&self) -> PathBuf {
+        let mut path = self
+            .host_toolchain
+            .clone()
+            .unwrap_or_else(|| self.llvm_target());
         path.push("target-crt");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the LLVM host target directory.
     ///
-    pub fn llvm_target_host() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn llvm_target_host(&self) -> PathBuf {
+        let mut path = self
+            .host_toolchain
+            .clone()
+            .unwrap_or_else(|| self.llvm_target());
         path.push("target-host");
-        crate::utils::absolute_path(path)
+        path
     }
 
     ///
     /// Returns the path to the LLVM final target directory.
     ///
-    pub fn llvm_target_final() -> anyhow::Result<PathBuf> {
-        let mut path = PathBuf::from(Self::DIRECTORY_LLVM_TARGET);
+    pub fn llvm_target_final(&self) -> PathBuf {
+        let mut path = self.llvm_target();
         path.push("target-final");
-        crate::utils::absolute_path(path)
+        path
+    }
+
+    ///
+    /// Returns the path to the per-command log files directory.
+    ///
+    pub fn logs(&self) -> PathBuf {
+        let mut path = self.llvm_target();
+        path.push("logs");
+        path
+    }
+
+    ///
+    /// Returns the path to the build stage journal.
+    ///
+    pub fn journal(&self) -> PathBuf {
+        let mut path = self.llvm_target();
+        path.push(crate::journal::Journal::FILE_NAME);
+        path
+    }
+
+    ///
+    /// Returns the path to the build report.
+    ///
+    pub fn build_report(&self) -> PathBuf {
+        let mut path = self.llvm_target();
+        path.push(crate::report::BuildReport::FILE_NAME);
+        path
+    }
+
+    ///
+    /// Returns the path to the manifest of the host toolchain imported into the workspace.
+    ///
+    pub fn imported_host_toolchain(&self) -> PathBuf {
+        let mut path = self.llvm_target();
+        path.push("host-toolchain.json");
+        path
     }
 }
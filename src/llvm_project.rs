@@ -2,10 +2,14 @@
 //! The LLVM projects to enable during the build.
 //!
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// The list of LLVM projects used as constants.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LLVMProject {
     /// The Clang compiler.
     CLANG,
@@ -5,17 +5,36 @@
 use anyhow::Context;
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
 use std::path::PathBuf;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::plan::CommandStep;
+use crate::runner::CommandRunner;
+
 ///
 /// The lock file data.
 ///
-/// This file describes the exact reference of the LLVM framework.
+/// This file describes the exact reference of the LLVM framework, and optionally the
+/// sources of the host LLVM and musl used by the multistage MUSL builds:
+///
+/// ```toml
+/// url = "https://github.com/matter-labs/era-compiler-llvm"
+/// branch = "main"
+/// ref = "0123456789abcdef0123456789abcdef01234567"
+///
+/// [host]
+/// tag = "llvmorg-17.0.6"
 ///
-#[derive(Debug, Deserialize, Serialize)]
+/// [musl]
+/// version = "1.2.3"
+/// sha256 = "7d5b0b6062521e4627e099e4c9dc8248d32a30285e959b7eecaa780cf8cfd4a4"
+/// ```
+///
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Lock {
     /// The LLVM repository URL.
     pub url: String,
@@ -23,6 +42,298 @@ pub struct Lock {
     pub branch: String,
     /// The LLVM repository commit reference.
     pub r#ref: Option<String>,
+    /// The local LLVM source tree to build from instead of the cloned repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+    /// The host LLVM source for stage 1 of multistage MUSL builds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<HostSource>,
+    /// The musl source for MUSL builds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub musl: Option<MuslSource>,
+}
+
+///
+/// The local LLVM source tree.
+///
+/// ```toml
+/// source = { path = "../era-compiler-llvm" }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Source {
+    /// The source tree path, relative to the lock file directory.
+    pub path: PathBuf,
+}
+
+///
+/// The host LLVM source.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HostSource {
+    /// The repository URL.
+    pub url: String,
+    /// The repository tag.
+    pub tag: String,
+    /// The expected commit of the tag, checked after cloning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl Default for HostSource {
+    fn default() -> Self {
+        Self {
+            url: crate::utils::LLVM_HOST_SOURCE_URL.to_owned(),
+            tag: crate::utils::LLVM_HOST_SOURCE_TAG.to_owned(),
+            commit: None,
+        }
+    }
+}
+
+///
+/// The musl source.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MuslSource {
+    /// The URL of the directory with the release tarballs.
+    pub url: String,
+    /// The release version.
+    pub version: String,
+    /// The expected SHA-256 of the release tarball.
+    /// Required unless the version is the built-in one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// The Linux kernel headers installed into the sysroot.
+    /// The built-in kernel release is installed if not specified.
+    #[serde(rename = "kernel-headers", skip_serializing_if = "Option::is_none")]
+    pub kernel_headers: Option<KernelHeaders>,
+}
+
+///
+/// The Linux kernel headers of the musl sysroot.
+///
+/// Either the pinned kernel release, installed with `make headers_install`, which is the
+/// default if neither `version` nor `path` is specified:
+///
+/// ```toml
+/// [musl.kernel-headers]
+/// version = "6.6.58"
+/// sha256 = "<the SHA-256 of linux-6.6.58.tar.gz>"
+/// ```
+///
+/// or the directory of the installed headers with `linux`, `asm`, and `asm-generic`, which
+/// makes the sysroot depend on the build system if its headers are used:
+///
+/// ```toml
+/// [musl.kernel-headers]
+/// path = "/usr/include"
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KernelHeaders {
+    /// The URL of the directory with the `v<major>.x` release directories.
+    pub url: String,
+    /// The kernel release version.
+    /// The built-in one is used unless the directory is specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The expected SHA-256 of the release tarball.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// The installed headers directory, relative to the workspace root or absolute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl KernelHeaders {
+    ///
+    /// Returns the release name, which is also the tarball root directory name.
+    ///
+    pub fn name(version: &str) -> String {
+        format!("linux-{version}")
+    }
+
+    ///
+    /// Returns the release tarball URL.
+    ///
+    pub fn tarball_url(&self, version: &str) -> String {
+        let major = version.split('.').next().unwrap_or_default();
+        format!(
+            "{}/v{major}.x/{}.tar.gz",
+            self.url.trim_end_matches('/'),
+            Self::name(version)
+        )
+    }
+
+    ///
+    /// Returns the expected SHA-256 of the release tarball.
+    ///
+    pub fn sha256(&self, version: &str) -> anyhow::Result<String> {
+        self.sha256.clone().ok_or_else(|| {
+            anyhow::anyhow!(
+                "The SHA-256 of Linux {version} is unknown. Specify it as `sha256` in the `[musl.kernel-headers]` table of the lock file"
+            )
+        })
+    }
+}
+
+impl Default for KernelHeaders {
+    fn default() -> Self {
+        Self {
+            url: crate::utils::LINUX_RELEASES_URL.to_owned(),
+            version: None,
+            sha256: None,
+            path: None,
+        }
+    }
+}
+
+impl MuslSource {
+    ///
+    /// Returns the release name, which is also the tarball root directory name.
+    ///
+    pub fn name(&self) -> String {
+        format!("musl-{}", self.version)
+    }
+
+    ///
+    /// Returns the release tarball file name.
+    ///
+    pub fn file_name(&self) -> String {
+        format!("{}.tar.gz", self.name())
+    }
+
+    ///
+    /// Returns the release tarball URL.
+    ///
+    pub fn tarball_url(&self) -> String {
+        format!("{}/{}", self.url.trim_end_matches('/'), self.file_name())
+    }
+
+    ///
+    /// Returns the expected SHA-256 of the release tarball.
+    ///
+    pub fn sha256(&self) -> anyhow::Result<String> {
+        match self.sha256.as_deref() {
+            Some(sha256) => Ok(sha256.to_owned()),
+            None if self.version == crate::utils::MUSL_VERSION => {
+                Ok(crate::utils::MUSL_SHA256.to_owned())
+            }
+            None => anyhow::bail!(
+                "The SHA-256 of musl {} is unknown. Specify it as `sha256` in the `[musl]` table of the lock file",
+                self.version
+            ),
+        }
+    }
+
+    ///
+    /// Describes the kernel headers, so the sysroots with different ones are told apart.
+    ///
+    pub fn kernel_headers_description(&self) -> String {
+        let kernel_headers = self.kernel_headers.clone().unwrap_or_default();
+        match (
+            kernel_headers.version.as_deref(),
+            kernel_headers.path.as_deref(),
+        ) {
+            (None, Some(path)) => format!("directory {}", path.to_string_lossy()),
+            (version, _) => format!(
+                "{} {}",
+                KernelHeaders::name(version.unwrap_or(crate::utils::LINUX_VERSION)),
+                kernel_headers.sha256.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl Default for MuslSource {
+    fn default() -> Self {
+        Self {
+            url: crate::utils::MUSL_RELEASES_URL.to_owned(),
+            version: crate::utils::MUSL_VERSION.to_owned(),
+            sha256: None,
+            kernel_headers: None,
+        }
+    }
+}
+
+impl Lock {
+    /// The lock file name.
+    pub const FILE_NAME: &'static str = "LLVM.lock";
+
+    ///
+    /// Searches for the lock file in the directory and its parents, the way cargo finds
+    /// `Cargo.toml`.
+    ///
+    pub fn discover(directory: &Path) -> Option<PathBuf> {
+        directory
+            .ancestors()
+            .map(|directory| directory.join(Self::FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    ///
+    /// Fails if the lock points to a local source tree, which must not be cloned over or
+    /// checked out.
+    ///
+    pub fn ensure_remote(&self) -> anyhow::Result<()> {
+        match self.source.as_ref() {
+            Some(source) => anyhow::bail!(
+                "The lock file points to the local LLVM source {:?}, which is built as is",
+                source.path
+            ),
+            None => Ok(()),
+        }
+    }
+
+    ///
+    /// Resolves the remote head commit of the branch.
+    ///
+    pub fn remote_head(
+        &self,
+        branch: &str,
+        runner: &mut dyn CommandRunner,
+    ) -> anyhow::Result<String> {
+        let output = runner.output(
+            &CommandStep::new("git", "LLVM repository branch head resolving")
+                .args(["ls-remote", self.url.as_str()])
+                .arg(format!("refs/heads/{branch}")),
+        )?;
+        output
+            .split_whitespace()
+            .next()
+            .map(str::to_owned)
+            .ok_or_else(|| anyhow::anyhow!("Branch `{branch}` is not found in {}", self.url))
+    }
+
+    ///
+    /// Pins the branch and commit in the lock file, keeping the rest of the file intact.
+    ///
+    pub fn pin(path: &Path, branch: &str, r#ref: &str) -> anyhow::Result<()> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Error opening {path:?} file"))?;
+        let mut document = contents
+            .parse::<toml_edit::DocumentMut>()
+            .with_context(|| format!("Error parsing {path:?} file"))?;
+        for (key, value) in [("branch", branch), ("ref", r#ref)] {
+            match document
+                .get_mut(key)
+                .and_then(toml_edit::Item::as_value_mut)
+            {
+                Some(item) => {
+                    let decor = item.decor().clone();
+                    *item = value.into();
+                    *item.decor_mut() = decor;
+                }
+                None => document[key] = toml_edit::value(value),
+            }
+        }
+        std::fs::write(path, document.to_string())
+            .with_context(|| format!("Error writing {path:?} file"))
+    }
 }
 
 impl TryFrom<&PathBuf> for Lock {
@@ -33,6 +344,6 @@ impl TryFrom<&PathBuf> for Lock {
         let mut config_file =
             File::open(path).with_context(|| format!("Error opening {path:?} file"))?;
         config_file.read_to_string(&mut config_str)?;
-        Ok(toml::from_str(&config_str)?)
+        toml::from_str(&config_str).with_context(|| format!("Error parsing {path:?} file"))
     }
 }
@@ -0,0 +1,141 @@
+//!
+//! The upstream source mirrors.
+//!
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::runner::CommandRunner;
+
+///
+/// The mirrors of an upstream source, e.g. the musl releases directory or a Git repository.
+///
+/// ```toml
+/// [[mirror]]
+/// upstream = "https://musl.libc.org/releases"
+/// urls = ["https://mirror.example.com/musl", "https://backup.example.com/musl"]
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mirror {
+    /// The upstream URL, matched as a prefix of the source URLs.
+    pub upstream: String,
+    /// The mirror URLs replacing the upstream one, tried in order before it.
+    pub urls: Vec<String>,
+}
+
+///
+/// The URL rewrite rule, applied to the upstream URLs the way Git `insteadOf` does.
+///
+/// ```toml
+/// [[rewrite]]
+/// prefix = "https://github.com/"
+/// replacement = "https://git.example.com/github/"
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rewrite {
+    /// The replaced URL prefix.
+    pub prefix: String,
+    /// The replacement.
+    pub replacement: String,
+}
+
+///
+/// The mirrors and rewrite rules of all the upstream sources.
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Mirrors {
+    /// The mirror lists.
+    pub mirrors: Vec<Mirror>,
+    /// The rewrite rules, the first matching one is applied.
+    pub rewrites: Vec<Rewrite>,
+}
+
+impl Mirrors {
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(mirrors: Vec<Mirror>, rewrites: Vec<Rewrite>) -> Self {
+        Self { mirrors, rewrites }
+    }
+
+    ///
+    /// Returns the URLs to try in order: the mirrors of the upstream, and then the upstream
+    /// URL itself with the first matching rewrite rule applied.
+    ///
+    pub fn candidates(&self, url: &str) -> Vec<String> {
+        let mut candidates = Vec::new();
+        for mirror in self.mirrors.iter() {
+            let Some(rest) = url.strip_prefix(mirror.upstream.trim_end_matches('/')) else {
+                continue;
+            };
+            if !rest.is_empty() && !rest.starts_with('/') {
+                continue;
+            }
+            for mirror_url in mirror.urls.iter() {
+                candidates.push(format!("{}{rest}", mirror_url.trim_end_matches('/')));
+            }
+        }
+        candidates.push(self.rewrite(url));
+
+        let mut unique = Vec::with_capacity(candidates.len());
+        for candidate in candidates.into_iter() {
+            if !unique.contains(&candidate) {
+                unique.push(candidate);
+            }
+        }
+        unique
+    }
+
+    ///
+    /// Applies the first matching rewrite rule.
+    ///
+    pub fn rewrite(&self, url: &str) -> String {
+        self.rewrites
+            .iter()
+            .find_map(|rule| {
+                url.strip_prefix(rule.prefix.as_str())
+                    .map(|rest| format!("{}{rest}", rule.replacement))
+            })
+            .unwrap_or_else(|| url.to_owned())
+    }
+}
+
+///
+/// Returns the URLs to try in order for the upstream URL, according to the runner mirrors.
+///
+pub fn candidates(runner: &dyn CommandRunner, url: &str) -> Vec<String> {
+    match runner.mirrors() {
+        Some(mirrors) => mirrors.candidates(url),
+        None => vec![url.to_owned()],
+    }
+}
+
+///
+/// Runs the operation with every URL in order until it succeeds.
+///
+/// The error lists the failures of all the URLs.
+///
+pub fn try_each<T, F>(urls: &[String], description: &str, mut operation: F) -> anyhow::Result<T>
+where
+    F: FnMut(&str) -> anyhow::Result<T>,
+{
+    let mut failures = Vec::with_capacity(urls.len());
+    for url in urls.iter() {
+        match operation(url.as_str()) {
+            Ok(value) => return Ok(value),
+            Err(error) if urls.len() == 1 => return Err(error),
+            Err(error) => {
+                eprintln!("{description} from `{url}` failed: {error}");
+                failures.push(format!("{url}: {error}"));
+            }
+        }
+    }
+    anyhow::bail!(
+        "{description} failed from all the mirrors:\n    {}",
+        failures.join("\n    ")
+    )
+}
@@ -0,0 +1,112 @@
+//!
+//! The build plan subprocess command.
+//!
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The subprocess command of the build plan.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommandStep {
+    /// The human-readable description used in logs and errors.
+    pub description: String,
+    /// The program to run.
+    pub program: String,
+    /// The program arguments.
+    pub args: Vec<String>,
+    /// The working directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_directory: Option<PathBuf>,
+}
+
+impl CommandStep {
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(program: &str, description: &str) -> Self {
+        Self {
+            description: description.to_owned(),
+            program: program.to_owned(),
+            args: vec![],
+            current_directory: None,
+        }
+    }
+
+    ///
+    /// Appends an argument.
+    ///
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Appends arguments.
+    ///
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    ///
+    /// Sets the working directory.
+    ///
+    pub fn current_dir(mut self, path: &Path) -> Self {
+        self.current_directory = Some(path.to_path_buf());
+        self
+    }
+
+    ///
+    /// Creates the process command.
+    ///
+    pub fn to_command(&self) -> std::process::Command {
+        let mut command = std::process::Command::new(self.program.as_str());
+        command.args(self.args.as_slice());
+        if let Some(current_directory) = self.current_directory.as_ref() {
+            command.current_dir(current_directory);
+        }
+        command
+    }
+
+    ///
+    /// Renders the program and its arguments as a POSIX shell command line.
+    ///
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .map(crate::plan::shell_quote)
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    ///
+    /// Renders the command as a POSIX shell command line, entering the working directory.
+    ///
+    pub fn to_shell(&self) -> String {
+        let command_line = self.command_line();
+        match self.current_directory.as_ref() {
+            Some(current_directory) => format!(
+                "(cd {} && {command_line})",
+                crate::plan::shell_quote(current_directory.to_string_lossy().as_ref())
+            ),
+            None => command_line,
+        }
+    }
+}
+
+impl std::fmt::Display for CommandStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_shell())
+    }
+}
@@ -0,0 +1,35 @@
+//!
+//! The build plan output format.
+//!
+
+///
+/// The build plan output format.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// The structured JSON.
+    Json,
+    /// The runnable POSIX shell script.
+    Shell,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(Self::Json),
+            "shell" => Ok(Self::Shell),
+            value => Err(format!("Unsupported plan format: `{value}`")),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Shell => write!(f, "shell"),
+        }
+    }
+}
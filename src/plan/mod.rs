@@ -0,0 +1,214 @@
+//!
+//! The LLVM framework build plan.
+//!
+
+pub mod command;
+pub mod format;
+pub mod selection;
+pub mod stage;
+pub mod step;
+
+pub use self::command::CommandStep;
+pub use self::format::Format;
+pub use self::selection::StageMode;
+pub use self::selection::StageSelection;
+pub use self::stage::Stage;
+pub use self::step::Step;
+
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::journal::Journal;
+use crate::runner::CommandRunner;
+
+///
+/// The LLVM framework build plan.
+///
+/// Describes every step the build would run, so it can be either executed or printed
+/// without any side effects.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    /// The tools that must be available in `PATH`.
+    pub tools: Vec<String>,
+    /// The stages to run in order.
+    pub stages: Vec<Stage>,
+}
+
+impl Plan {
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(tools: &[&str]) -> Self {
+        Self {
+            tools: tools.iter().map(|tool| (*tool).to_owned()).collect(),
+            stages: vec![],
+        }
+    }
+
+    ///
+    /// Appends a stage.
+    ///
+    pub fn stage(mut self, name: &str, source_directory: Option<&Path>, steps: Vec<Step>) -> Self {
+        self.stages.push(Stage::new(name, source_directory, steps));
+        self
+    }
+
+    ///
+    /// Splits the plan at the stage index, returning the plan of the stages from the index on.
+    ///
+    pub fn split_off(&mut self, at: usize) -> Self {
+        Self {
+            tools: self.tools.clone(),
+            stages: self.stages.split_off(at),
+        }
+    }
+
+    ///
+    /// Checks the required tools and executes the selected stages in order with the runner.
+    ///
+    /// The completed stages are recorded in the journal, and the stages completed with
+    /// the same inputs are skipped unless forced by the selection. The journal is only used
+    /// if the runner applies side effects.
+    ///
+    pub fn execute(
+        &self,
+        runner: &mut dyn CommandRunner,
+        selection: &StageSelection,
+        journal_path: &Path,
+    ) -> anyhow::Result<()> {
+        let modes = selection.modes(self.stages.as_slice())?;
+        self.execute_modes(runner, modes.as_slice(), journal_path)
+    }
+
+    ///
+    /// Executes the stages in order with their resolved run modes.
+    ///
+    pub fn execute_modes(
+        &self,
+        runner: &mut dyn CommandRunner,
+        modes: &[StageMode],
+        journal_path: &Path,
+    ) -> anyhow::Result<()> {
+        if !runner.applies_side_effects() {
+            for (stage, mode) in self.stages.iter().zip(modes) {
+                if *mode != StageMode::Skipped {
+                    stage.execute(runner)?;
+                }
+            }
+            return Ok(());
+        }
+
+        for tool in self.tools.iter() {
+            crate::utils::exists(tool.as_str())?;
+        }
+        let tool_versions = self.tool_versions(runner)?;
+
+        let mut journal = Journal::load(journal_path)?;
+        let mut previous = Some(String::new());
+        for (stage, mode) in self.stages.iter().zip(modes) {
+            let fingerprint = match previous.as_deref() {
+                Some(previous) => stage.fingerprint(runner, tool_versions.as_slice(), previous)?,
+                None => None,
+            };
+            previous.clone_from(&fingerprint);
+
+            match (*mode, fingerprint.as_deref()) {
+                (StageMode::Skipped, _) => {
+                    eprintln!("Stage `{}` is not selected. Skipping...", stage.name);
+                    continue;
+                }
+                (StageMode::Auto, _) if journal.is_imported(stage.name.as_str()) => {
+                    eprintln!("Stage `{}` is imported. Skipping...", stage.name);
+                    continue;
+                }
+                (StageMode::Auto, Some(fingerprint))
+                    if journal.is_completed(stage.name.as_str(), fingerprint) =>
+                {
+                    eprintln!("Stage `{}` is up to date. Skipping...", stage.name);
+                    continue;
+                }
+                _ => {}
+            }
+
+            journal.invalidate(stage.name.as_str());
+            journal.save(journal_path)?;
+            stage.execute(runner)?;
+            if let Some(fingerprint) = fingerprint {
+                journal.complete(stage.name.as_str(), fingerprint);
+                journal.save(journal_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Returns the first line of `--version` of every required tool.
+    ///
+    /// The version of the tool failing to print it is empty, e.g. of the `lld` generic driver,
+    /// which only prints its usage and exits with an error.
+    ///
+    pub fn tool_versions(&self, runner: &mut dyn CommandRunner) -> anyhow::Result<Vec<String>> {
+        self.tools
+            .iter()
+            .map(|tool| {
+                let command =
+                    CommandStep::new(tool.as_str(), "Tool version reading").arg("--version");
+                let output = runner.output(&command).unwrap_or_default();
+                Ok(output.lines().next().unwrap_or_default().to_owned())
+            })
+            .collect()
+    }
+
+    ///
+    /// Renders the plan in the specified format.
+    ///
+    pub fn render(&self, format: Format) -> anyhow::Result<String> {
+        match format {
+            Format::Json => self.to_json(),
+            Format::Shell => Ok(self.to_shell_script()),
+        }
+    }
+
+    ///
+    /// Renders the plan as pretty-printed JSON.
+    ///
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    ///
+    /// Renders the plan as a runnable POSIX shell script.
+    ///
+    pub fn to_shell_script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\nset -eu\n");
+        for tool in self.tools.iter() {
+            script.push_str(format!("command -v {} >/dev/null\n", shell_quote(tool)).as_str());
+        }
+        for stage in self.stages.iter() {
+            script.push_str(format!("\n# Stage `{}`\n", stage.name).as_str());
+            for step in stage.steps.iter() {
+                script.push_str(step.to_shell().as_str());
+                script.push('\n');
+            }
+        }
+        script
+    }
+}
+
+///
+/// Quotes the string for a POSIX shell if it contains any special characters.
+///
+pub fn shell_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_./=:,+@%".contains(character));
+    if is_safe {
+        value.to_owned()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,77 @@
+//!
+//! The build plan stage selection.
+//!
+
+use crate::plan::stage::Stage;
+
+///
+/// The stages forced by the user.
+///
+/// By default, the stages completed with the same inputs are skipped.
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StageSelection {
+    /// Skip the stages before this one and run it with all the following ones.
+    pub from_stage: Option<String>,
+    /// Run only this stage.
+    pub only_stage: Option<String>,
+}
+
+///
+/// The stage run mode resolved from the selection.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageMode {
+    /// Run the stage unless it has been completed with the same inputs.
+    Auto,
+    /// Run the stage unconditionally.
+    Forced,
+    /// Do not run the stage.
+    Skipped,
+}
+
+impl StageSelection {
+    ///
+    /// Resolves the run mode of every stage, checking that the selected stages exist.
+    ///
+    pub fn modes(&self, stages: &[Stage]) -> anyhow::Result<Vec<StageMode>> {
+        let position = |name: &str| {
+            stages
+                .iter()
+                .position(|stage| stage.name == name)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown stage `{name}`. Available stages: {}",
+                        stages
+                            .iter()
+                            .map(|stage| stage.name.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    )
+                })
+        };
+
+        let mut modes = vec![StageMode::Auto; stages.len()];
+        if let Some(from_stage) = self.from_stage.as_deref() {
+            let from = position(from_stage)?;
+            for (index, mode) in modes.iter_mut().enumerate() {
+                *mode = if index < from {
+                    StageMode::Skipped
+                } else {
+                    StageMode::Forced
+                };
+            }
+        }
+        if let Some(only_stage) = self.only_stage.as_deref() {
+            let only = position(only_stage)?;
+            for (index, mode) in modes.iter_mut().enumerate() {
+                *mode = if index == only {
+                    StageMode::Forced
+                } else {
+                    StageMode::Skipped
+                };
+            }
+        }
+        Ok(modes)
+    }
+}
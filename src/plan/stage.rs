@@ -0,0 +1,106 @@
+//!
+//! The build plan stage.
+//!
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+
+use crate::plan::command::CommandStep;
+use crate::plan::step::Step;
+use crate::runner::CommandRunner;
+
+///
+/// The named group of build plan steps, e.g. the `musl` or `final` stage.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Stage {
+    /// The stage name.
+    pub name: String,
+    /// The LLVM source repository the stage is built from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_directory: Option<PathBuf>,
+    /// The stage steps.
+    pub steps: Vec<Step>,
+}
+
+impl Stage {
+    /// The MUSL building stage name.
+    pub const MUSL: &'static str = "musl";
+
+    /// The CRT building stage name.
+    pub const CRT: &'static str = "crt";
+
+    /// The host toolchain building stage name.
+    pub const HOST: &'static str = "host";
+
+    /// The native table generators building stage name, required by cross-compilation.
+    pub const TBLGEN: &'static str = "tblgen";
+
+    /// The final toolchain building stage name.
+    pub const FINAL: &'static str = "final";
+
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(name: &str, source_directory: Option<&Path>, steps: Vec<Step>) -> Self {
+        Self {
+            name: name.to_owned(),
+            source_directory: source_directory.map(Path::to_path_buf),
+            steps,
+        }
+    }
+
+    ///
+    /// Executes the stage steps.
+    ///
+    pub fn execute(&self, runner: &mut dyn CommandRunner) -> anyhow::Result<()> {
+        for step in self.steps.iter() {
+            step.execute(runner)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Computes the fingerprint of the stage inputs: the steps with all their arguments,
+    /// the source repository state, the tool versions, and the previous stage fingerprint.
+    ///
+    /// Returns `None` if the source directory state cannot be identified, e.g. if it is not
+    /// a Git repository, so the stage is always run.
+    ///
+    pub fn fingerprint(
+        &self,
+        runner: &mut dyn CommandRunner,
+        tool_versions: &[String],
+        previous: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(serde_json::to_vec(&self.steps)?);
+        for tool_version in tool_versions.iter() {
+            hasher.update(tool_version.as_bytes());
+        }
+        hasher.update(previous.as_bytes());
+
+        if let Some(source_directory) = self.source_directory.as_ref() {
+            for args in [
+                vec!["rev-parse", "HEAD"],
+                vec!["diff", "HEAD"],
+                vec!["ls-files", "--others", "--exclude-standard"],
+            ] {
+                let command = CommandStep::new("git", "LLVM repository state reading")
+                    .args(args)
+                    .current_dir(source_directory);
+                match runner.output(&command) {
+                    Ok(output) => hasher.update(output.as_bytes()),
+                    Err(_) => return Ok(None),
+                }
+            }
+        }
+
+        Ok(Some(format!("{:x}", hasher.finalize())))
+    }
+}
@@ -0,0 +1,256 @@
+//!
+//! The build plan step.
+//!
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::plan::command::CommandStep;
+use crate::plan::shell_quote;
+use crate::runner::CommandRunner;
+
+///
+/// The single build plan step.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Step {
+    /// Creates a directory with all its parents.
+    CreateDirectory {
+        /// The directory path.
+        path: PathBuf,
+    },
+    /// Downloads a file unless it has already been downloaded, and verifies its digest.
+    #[serde(rename_all = "kebab-case")]
+    Download {
+        /// The source URL.
+        url: String,
+        /// The destination directory.
+        destination: PathBuf,
+        /// The downloaded file name.
+        file_name: String,
+        /// The expected SHA-256 of the file.
+        sha256: String,
+    },
+    /// Unpacks a `.tar.gz` archive.
+    Unpack {
+        /// The archive path.
+        archive: PathBuf,
+        /// The destination directory.
+        destination: PathBuf,
+    },
+    /// Runs a subprocess.
+    Command(CommandStep),
+    /// Copies a directory, overwriting the existing files.
+    #[serde(rename_all = "kebab-case")]
+    CopyDirectory {
+        /// The source directory.
+        source: PathBuf,
+        /// The destination directory.
+        destination: PathBuf,
+        /// Whether to copy the directory contents instead of the directory itself.
+        content_only: bool,
+    },
+    /// Copies the Linux kernel headers, `linux`, `asm`, and `asm-generic`, into the include
+    /// directory. The `asm` headers are taken from the multiarch directory if the source
+    /// has one, e.g. `/usr/include/x86_64-linux-gnu/asm`.
+    CopyKernelHeaders {
+        /// The headers source directory, e.g. `/usr/include`.
+        source: PathBuf,
+        /// The multiarch directory name, e.g. `x86_64-linux-gnu`.
+        multiarch: String,
+        /// The destination include directory.
+        destination: PathBuf,
+    },
+    /// Writes a text file, e.g. a CMake toolchain file, creating its parent directories.
+    WriteFile {
+        /// The file path.
+        path: PathBuf,
+        /// The file contents.
+        content: String,
+    },
+    /// Copies a file, overwriting the existing one.
+    CopyFile {
+        /// The source file.
+        source: PathBuf,
+        /// The destination file.
+        destination: PathBuf,
+        /// Whether the missing source file must be skipped.
+        optional: bool,
+    },
+}
+
+impl Step {
+    ///
+    /// Executes the step, running the commands with the runner.
+    ///
+    /// The other steps are skipped if the runner does not apply side effects.
+    ///
+    pub fn execute(&self, runner: &mut dyn CommandRunner) -> anyhow::Result<()> {
+        if let Self::Command(command) = self {
+            return runner.run(command);
+        }
+        if !runner.applies_side_effects() {
+            return Ok(());
+        }
+
+        match self {
+            Self::CreateDirectory { path } => {
+                std::fs::create_dir_all(path)?;
+            }
+            Self::Download {
+                url,
+                destination,
+                file_name,
+                sha256,
+            } => {
+                crate::utils::download(
+                    url.as_str(),
+                    destination.as_path(),
+                    file_name.as_str(),
+                    sha256.as_str(),
+                    &runner.retry_policy(),
+                    runner.cache(),
+                    runner.mirrors(),
+                )?;
+            }
+            Self::Unpack {
+                archive,
+                destination,
+            } => {
+                crate::utils::unpack_tar(
+                    archive.to_owned(),
+                    destination.to_string_lossy().as_ref(),
+                )?;
+            }
+            Self::Command(_) => {}
+            Self::CopyDirectory {
+                source,
+                destination,
+                content_only,
+            } => {
+                std::fs::create_dir_all(destination)?;
+                let copy_options = fs_extra::dir::CopyOptions {
+                    overwrite: true,
+                    copy_inside: true,
+                    content_only: *content_only,
+                    ..Default::default()
+                };
+                fs_extra::dir::copy(source, destination, &copy_options)?;
+            }
+            Self::CopyKernelHeaders {
+                source,
+                multiarch,
+                destination,
+            } => {
+                crate::utils::copy_kernel_headers(
+                    source.as_path(),
+                    multiarch.as_str(),
+                    destination.as_path(),
+                )?;
+            }
+            Self::WriteFile { path, content } => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, content)?;
+            }
+            Self::CopyFile {
+                source,
+                destination,
+                optional,
+            } => {
+                if *optional && !source.exists() {
+                    return Ok(());
+                }
+                let copy_options = fs_extra::file::CopyOptions::new().overwrite(true);
+                fs_extra::file::copy(source, destination, &copy_options)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Renders the step as a POSIX shell command line.
+    ///
+    pub fn to_shell(&self) -> String {
+        let quote = |path: &PathBuf| shell_quote(path.to_string_lossy().as_ref());
+        match self {
+            Self::CreateDirectory { path } => format!("mkdir -p {}", quote(path)),
+            Self::Download {
+                url,
+                destination,
+                file_name,
+                sha256,
+            } => {
+                let file = destination.join(file_name);
+                let path = quote(&file);
+                let check = format!("{sha256}  {}", file.to_string_lossy());
+                format!(
+                    "{{ [ -f {path} ] || curl --fail --location --output {path} {}; }} && echo {} | sha256sum -c -",
+                    shell_quote(url.as_str()),
+                    shell_quote(check.as_str()),
+                )
+            }
+            Self::Unpack {
+                archive,
+                destination,
+            } => format!("tar -xzf {} -C {}", quote(archive), quote(destination)),
+            Self::Command(command) => command.to_shell(),
+            Self::CopyDirectory {
+                source,
+                destination,
+                content_only,
+            } => {
+                let source = if *content_only {
+                    source.join(".")
+                } else {
+                    source.to_owned()
+                };
+                format!(
+                    "mkdir -p {destination} && cp -R {} {destination}",
+                    quote(&source),
+                    destination = quote(destination),
+                )
+            }
+            Self::CopyKernelHeaders {
+                source,
+                multiarch,
+                destination,
+            } => {
+                let multiarch_asm = source.join(multiarch).join("asm");
+                format!(
+                    "mkdir -p {destination} && cp -R {} {} {destination} && if [ -d {multiarch_asm} ]; then cp -R {multiarch_asm} {destination}; else cp -R {} {destination}; fi",
+                    quote(&source.join("linux")),
+                    quote(&source.join("asm-generic")),
+                    quote(&source.join("asm")),
+                    destination = quote(destination),
+                    multiarch_asm = quote(&multiarch_asm),
+                )
+            }
+            Self::WriteFile { path, content } => {
+                let parent = path.parent().map(PathBuf::from).unwrap_or_default();
+                format!(
+                    "mkdir -p {} && printf '%s' {} > {}",
+                    quote(&parent),
+                    shell_quote(content.as_str()),
+                    quote(path)
+                )
+            }
+            Self::CopyFile {
+                source,
+                destination,
+                optional,
+            } => {
+                let copy = format!("cp -f {} {}", quote(source), quote(destination));
+                if *optional {
+                    format!("[ ! -f {} ] || {copy}", quote(source))
+                } else {
+                    copy
+                }
+            }
+        }
+    }
+}
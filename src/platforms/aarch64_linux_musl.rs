@@ -2,374 +2,499 @@
 //! The ZKsync LLVM arm64 `linux-musl` builder.
 //!
 
-use crate::build_type::BuildType;
+use std::path::Path;
+
+use crate::build_options::BuildOptions;
 use crate::ccache_variant::CcacheVariant;
+use crate::cmake_config::CMakeConfig;
+use crate::cmake_config::CMakeOrigin;
+use crate::host::Arch;
+use crate::host::Host;
+use crate::host::OS;
 use crate::llvm_path::LLVMPath;
-use crate::llvm_project::LLVMProject;
+use crate::lock::MuslSource;
+use crate::plan::CommandStep;
+use crate::plan::Plan;
+use crate::plan::Stage;
+use crate::plan::Step;
 use crate::platforms::Platform;
-use crate::sanitizer::Sanitizer;
-use crate::target_triple::TargetTriple;
-use std::collections::HashSet;
-use std::path::Path;
-use std::process::Command;
+use crate::target_env::TargetEnv;
 
 ///
-/// The building sequence.
+/// The building plan.
 ///
-#[allow(clippy::too_many_arguments)]
-pub fn build(
-    build_type: BuildType,
-    targets: HashSet<Platform>,
-    llvm_projects: HashSet<LLVMProject>,
-    enable_rtti: bool,
-    default_target: Option<TargetTriple>,
-    enable_tests: bool,
-    enable_coverage: bool,
-    extra_args: Vec<String>,
-    ccache_variant: Option<CcacheVariant>,
-    enable_assertions: bool,
-    sanitizer: Option<Sanitizer>,
-    enable_valgrind: bool,
-    valgrind_options: Vec<String>,
-) -> anyhow::Result<()> {
-    crate::utils::exists("cmake")?;
-    crate::utils::exists("clang")?;
-    crate::utils::exists("clang++")?;
-    crate::utils::exists("lld")?;
-    crate::utils::exists("ninja")?;
-
-    let musl_name = "musl-1.2.3";
-    let musl_build = LLVMPath::musl_build(musl_name)?;
-    let musl_target = LLVMPath::musl_target()?;
-
-    let llvm_module_llvm = LLVMPath::llvm_module_llvm()?;
-    let llvm_host_module_llvm = LLVMPath::llvm_host_module_llvm()?;
-
-    let llvm_build_crt = LLVMPath::llvm_build_crt()?;
-    let llvm_target_crt = LLVMPath::llvm_target_crt()?;
-
-    let llvm_build_host = LLVMPath::llvm_build_host()?;
-    let llvm_target_host = LLVMPath::llvm_target_host()?;
-
-    let llvm_build_final = LLVMPath::llvm_build_final()?;
-    let llvm_target_final = LLVMPath::llvm_target_final()?;
-
-    if !LLVMPath::musl_source(musl_name)?.exists() {
-        crate::utils::download_musl(musl_name)?;
-    }
-    crate::platforms::shared::build_musl(musl_build.as_path(), musl_target.as_path())?;
-    build_crt(
-        targets.clone(),
+pub fn plan(options: &BuildOptions, paths: &LLVMPath, musl: &MuslSource) -> anyhow::Result<Plan> {
+    let musl_build = paths.musl_build(musl.name().as_str());
+    let musl_target = paths.musl_target();
+
+    let llvm_module_llvm = paths.llvm_module_llvm();
+    let llvm_host_module_llvm = paths.llvm_host_module_llvm();
+
+    let llvm_build_crt = paths.llvm_build_crt();
+    let llvm_target_crt = paths.llvm_target_crt();
+
+    let llvm_build_host = paths.llvm_build_host();
+    let llvm_target_host = paths.llvm_target_host();
+
+    let llvm_build_final = paths.llvm_build_final();
+    let llvm_target_final = paths.llvm_target_final();
+
+    let mut musl_steps = vec![Step::CreateDirectory {
+        path: paths.llvm_target(),
+    }];
+    musl_steps.extend(crate::utils::download_musl(musl, paths)?);
+    musl_steps.extend(crate::platforms::shared::build_musl(
+        musl_build.as_path(),
+        musl_target.as_path(),
+        None,
+        options.musl_jobs,
+    ));
+    musl_steps.extend(crate::utils::install_kernel_headers(
+        musl,
+        paths,
+        Arch::AArch64,
+    )?);
+
+    Ok(Plan::new(&["cmake", "clang", "clang++", "lld", "ninja"])
+        .stage(Stage::MUSL, None, musl_steps)
+        .stage(
+            Stage::CRT,
+            Some(llvm_host_module_llvm.as_path()),
+            build_crt(crt_cmake_config(
+                llvm_host_module_llvm.as_path(),
+                llvm_build_crt.as_path(),
+                llvm_target_crt.as_path(),
+                options.ccache_variant,
+            )),
+        )
+        .stage(
+            Stage::HOST,
+            Some(llvm_host_module_llvm.as_path()),
+            build_host(
+                host_cmake_config(
+                    llvm_host_module_llvm.as_path(),
+                    llvm_build_host.as_path(),
+                    llvm_target_host.as_path(),
+                    musl_target.as_path(),
+                    options.ccache_variant,
+                ),
+                llvm_target_crt.as_path(),
+            ),
+        )
+        .stage(
+            Stage::FINAL,
+            Some(llvm_module_llvm.as_path()),
+            build_target(
+                target_cmake_config(
+                    options,
+                    llvm_module_llvm.as_path(),
+                    llvm_build_final.as_path(),
+                    llvm_target_final.as_path(),
+                    llvm_target_host.as_path(),
+                ),
+                llvm_target_final.as_path(),
+                musl_target.as_path(),
+                llvm_target_host.as_path(),
+            ),
+        ))
+}
+
+///
+/// The building plan of cross-compiling on an x86_64 Linux builder.
+///
+/// musl and the CRT objects are cross-compiled with the builder clang, and the stage 1 host
+/// toolchain running on the builder is built with the aarch64 musl sysroot and runtimes.
+/// After the native table generators are built, the final build uses the stage 1 clang.
+///
+pub fn cross_plan(
+    options: &BuildOptions,
+    paths: &LLVMPath,
+    musl: &MuslSource,
+) -> anyhow::Result<Plan> {
+    let host = Host::new(Arch::AArch64, OS::Linux, TargetEnv::MUSL);
+    let triple = host.triple();
+
+    let musl_build = paths.musl_build(musl.name().as_str());
+    let musl_target = paths.musl_target();
+
+    let llvm_module_llvm = paths.llvm_module_llvm();
+    let llvm_host_module_llvm = paths.llvm_host_module_llvm();
+
+    let llvm_build_crt = paths.llvm_build_crt();
+    let llvm_target_crt = paths.llvm_target_crt();
+
+    let llvm_build_host = paths.llvm_build_host();
+    let llvm_target_host = paths.llvm_target_host();
+
+    let llvm_build_tblgen = paths.llvm_build_tblgen();
+
+    let llvm_build_final = paths.llvm_build_final();
+    let llvm_target_final = paths.llvm_target_final();
+
+    let mut musl_steps = vec![Step::CreateDirectory {
+        path: paths.llvm_target(),
+    }];
+    musl_steps.extend(crate::utils::download_musl(musl, paths)?);
+    musl_steps.extend(crate::platforms::shared::build_musl(
+        musl_build.as_path(),
+        musl_target.as_path(),
+        Some(triple.as_str()),
+        options.musl_jobs,
+    ));
+    musl_steps.extend(crate::utils::install_kernel_headers(
+        musl,
+        paths,
+        Arch::AArch64,
+    )?);
+
+    let crt_toolchain_file = paths.cmake_toolchain_file(Stage::CRT);
+    let mut crt_config = crt_cmake_config(
         llvm_host_module_llvm.as_path(),
         llvm_build_crt.as_path(),
         llvm_target_crt.as_path(),
-        ccache_variant,
-    )?;
-    build_host(
-        llvm_host_module_llvm.as_path(),
-        llvm_build_host.as_path(),
-        llvm_target_host.as_path(),
-        musl_target.as_path(),
-        llvm_target_crt.as_path(),
-        ccache_variant,
-    )?;
-    build_target(
-        build_type,
-        targets,
-        llvm_projects,
-        enable_rtti,
-        default_target,
+        options.ccache_variant,
+    );
+    crt_config
+        .unset("CMAKE_C_COMPILER")
+        .unset("CMAKE_CXX_COMPILER")
+        .set_file_path(
+            "CMAKE_TOOLCHAIN_FILE",
+            crt_toolchain_file.to_string_lossy(),
+            CMakeOrigin::Platform,
+        )
+        .set_string("LLVM_HOST_TRIPLE", triple.as_str(), CMakeOrigin::Platform)
+        .set_bool(
+            "COMPILER_RT_DEFAULT_TARGET_ONLY",
+            true,
+            CMakeOrigin::Platform,
+        );
+    let mut crt_steps = vec![Step::WriteFile {
+        path: crt_toolchain_file,
+        content: crate::platforms::shared::cross_toolchain_file(
+            host,
+            Path::new("clang"),
+            Path::new("clang++"),
+            Some(musl_target.as_path()),
+            musl_target.as_path(),
+            true,
+        ),
+    }];
+    crt_steps.extend(build_crt(crt_config));
+
+    let final_toolchain_file = paths.cmake_toolchain_file(host.to_string().as_str());
+    let mut final_config = target_cmake_config(
+        options,
         llvm_module_llvm.as_path(),
         llvm_build_final.as_path(),
         llvm_target_final.as_path(),
+        llvm_target_host.as_path(),
+    );
+    crate::platforms::shared::shared_build_opts_cross(
+        &mut final_config,
+        options,
+        host,
+        final_toolchain_file.as_path(),
+        llvm_build_tblgen.as_path(),
+    );
+    let mut final_steps = vec![Step::WriteFile {
+        path: final_toolchain_file,
+        content: crate::platforms::shared::cross_toolchain_file(
+            host,
+            llvm_target_host.join("bin/clang").as_path(),
+            llvm_target_host.join("bin/clang++").as_path(),
+            Some(musl_target.as_path()),
+            musl_target.as_path(),
+            false,
+        ),
+    }];
+    final_steps.extend(build_target(
+        final_config,
+        llvm_target_final.as_path(),
         musl_target.as_path(),
         llvm_target_host.as_path(),
-        enable_tests,
-        enable_coverage,
-        extra_args,
-        ccache_variant,
-        enable_assertions,
-        sanitizer,
-        enable_valgrind,
-        valgrind_options,
-    )?;
-
-    Ok(())
+    ));
+
+    Ok(Plan::new(&["cmake", "clang", "clang++", "lld", "ninja"])
+        .stage(Stage::MUSL, None, musl_steps)
+        .stage(Stage::CRT, Some(llvm_host_module_llvm.as_path()), crt_steps)
+        .stage(
+            Stage::HOST,
+            Some(llvm_host_module_llvm.as_path()),
+            build_host(
+                host_cmake_config(
+                    llvm_host_module_llvm.as_path(),
+                    llvm_build_host.as_path(),
+                    llvm_target_host.as_path(),
+                    musl_target.as_path(),
+                    options.ccache_variant,
+                ),
+                llvm_target_crt.as_path(),
+            ),
+        )
+        .stage(
+            Stage::TBLGEN,
+            Some(llvm_module_llvm.as_path()),
+            crate::platforms::shared::build_tblgen(
+                options,
+                llvm_module_llvm.as_path(),
+                llvm_build_tblgen.as_path(),
+            ),
+        )
+        .stage(Stage::FINAL, Some(llvm_module_llvm.as_path()), final_steps))
 }
 
 ///
-/// The `crt` building sequence.
+/// Returns the CMake configuration of the `crt` build.
 ///
-fn build_crt(
-    mut targets: HashSet<Platform>,
+pub fn crt_cmake_config(
     source_directory: &Path,
     build_directory: &Path,
     target_directory: &Path,
     ccache_variant: Option<CcacheVariant>,
-) -> anyhow::Result<()> {
-    targets.insert(Platform::AArch64);
-
-    crate::utils::command(
-        Command::new("cmake")
-            .args([
-                "-S",
-                source_directory.to_string_lossy().as_ref(),
-                "-B",
-                build_directory.to_string_lossy().as_ref(),
-                "-G",
-                "Ninja",
-                format!(
-                    "-DCMAKE_INSTALL_PREFIX='{}'",
-                    target_directory.to_string_lossy()
-                )
-                .as_str(),
-                "-DCMAKE_BUILD_TYPE='Release'",
-                "-DCMAKE_C_COMPILER='clang'",
-                "-DCMAKE_CXX_COMPILER='clang++'",
-                "-DLLVM_ENABLE_PROJECTS='compiler-rt'",
-                format!("-DLLVM_TARGETS_TO_BUILD='{}'", Platform::AArch64).as_str(),
-                "-DLLVM_DEFAULT_TARGET_TRIPLE='aarch64-unknown-linux-musl'",
-                "-DLLVM_BUILD_TESTS='Off'",
-                "-DLLVM_BUILD_RUNTIMES='Off'",
-                "-DLLVM_BUILD_UTILS='Off'",
-                "-DLLVM_INCLUDE_TESTS='Off'",
-                "-DLLVM_INCLUDE_RUNTIMES='Off'",
-                "-DLLVM_INCLUDE_UTILS='Off'",
-                "-DCOMPILER_RT_DEFAULT_TARGET_ARCH='aarch64'",
-                "-DCOMPILER_RT_BUILD_CRT='On'",
-                "-DCOMPILER_RT_BUILD_BUILTINS='On'",
-                "-DCOMPILER_RT_BUILD_SANITIZERS='Off'",
-                "-DCOMPILER_RT_BUILD_XRAY='Off'",
-                "-DCOMPILER_RT_BUILD_LIBFUZZER='Off'",
-                "-DCOMPILER_RT_BUILD_PROFILE='Off'",
-                "-DCOMPILER_RT_BUILD_MEMPROF='Off'",
-                "-DCOMPILER_RT_BUILD_ORC='Off'",
-            ])
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS)
-            .args(crate::platforms::shared::shared_build_opts_ccache(
-                ccache_variant,
-            )),
-        "CRT building cmake",
-    )?;
-
-    crate::utils::command(
-        Command::new("ninja")
-            .arg("-C")
-            .arg(build_directory)
-            .arg("install-crt"),
-        "CRT building ninja",
-    )?;
-
-    Ok(())
+) -> CMakeConfig {
+    let mut config = CMakeConfig::new(source_directory, build_directory);
+    config
+        .set_path(
+            "CMAKE_INSTALL_PREFIX",
+            target_directory,
+            CMakeOrigin::Platform,
+        )
+        .set_string("CMAKE_BUILD_TYPE", "Release", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_C_COMPILER", "clang", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_CXX_COMPILER", "clang++", CMakeOrigin::Platform)
+        .set_string("LLVM_ENABLE_PROJECTS", "compiler-rt", CMakeOrigin::Platform)
+        .set_string(
+            "LLVM_TARGETS_TO_BUILD",
+            Platform::AArch64.to_string(),
+            CMakeOrigin::Platform,
+        )
+        .set_string(
+            "LLVM_DEFAULT_TARGET_TRIPLE",
+            "aarch64-unknown-linux-musl",
+            CMakeOrigin::Platform,
+        )
+        .set_bool("LLVM_BUILD_TESTS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_BUILD_RUNTIMES", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_BUILD_UTILS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_TESTS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_RUNTIMES", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_UTILS", false, CMakeOrigin::Platform)
+        .set_string(
+            "COMPILER_RT_DEFAULT_TARGET_ARCH",
+            "aarch64",
+            CMakeOrigin::Platform,
+        )
+        .set_bool("COMPILER_RT_BUILD_CRT", true, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_BUILTINS", true, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_SANITIZERS", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_XRAY", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_LIBFUZZER", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_PROFILE", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_MEMPROF", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_ORC", false, CMakeOrigin::Platform);
+    crate::platforms::shared::shared_build_opts(&mut config);
+    crate::platforms::shared::shared_build_opts_ccache(&mut config, ccache_variant);
+    config
 }
 
 ///
-/// The host toolchain building sequence.
+/// Returns the CMake configuration of the host toolchain build.
 ///
-fn build_host(
+pub fn host_cmake_config(
     source_directory: &Path,
     build_directory: &Path,
     target_directory: &Path,
     musl_target_directory: &Path,
-    crt_target_directory: &Path,
     ccache_variant: Option<CcacheVariant>,
-) -> anyhow::Result<()> {
-    crate::utils::command(
-        Command::new("cmake")
-            .args([
-                "-S",
-                source_directory.to_string_lossy().as_ref(),
-                "-B",
-                build_directory.to_string_lossy().as_ref(),
-                "-G",
-                "Ninja",
-                format!(
-                    "-DDEFAULT_SYSROOT='{}'",
-                    musl_target_directory.to_string_lossy()
-                )
-                .as_str(),
-                "-DLINKER_SUPPORTS_COLOR_DIAGNOSTICS=0",
-                format!(
-                    "-DCMAKE_INSTALL_PREFIX='{}'",
-                    target_directory.to_string_lossy()
-                )
-                .as_str(),
-                "-DCMAKE_BUILD_TYPE='Release'",
-                "-DCMAKE_C_COMPILER='clang'",
-                "-DCMAKE_CXX_COMPILER='clang++'",
-                "-DCLANG_DEFAULT_CXX_STDLIB='libc++'",
-                "-DCLANG_DEFAULT_RTLIB='compiler-rt'",
-                "-DLLVM_DEFAULT_TARGET_TRIPLE='aarch64-unknown-linux-musl'",
-                "-DLLVM_TARGETS_TO_BUILD='AArch64'",
-                "-DLLVM_BUILD_TESTS='Off'",
-                "-DLLVM_BUILD_UTILS='Off'",
-                "-DLLVM_INCLUDE_TESTS='Off'",
-                "-DLLVM_INCLUDE_UTILS='Off'",
-                "-DLLVM_ENABLE_PROJECTS='clang;lld'",
-                "-DLLVM_ENABLE_RUNTIMES='compiler-rt;libcxx;libcxxabi;libunwind'",
-                "-DLIBCXX_CXX_ABI='libcxxabi'",
-                "-DLIBCXX_HAS_MUSL_LIBC='On'",
-                "-DLIBCXX_ENABLE_SHARED='Off'",
-                "-DLIBCXX_ENABLE_STATIC='On'",
-                "-DLIBCXX_ENABLE_STATIC_ABI_LIBRARY='On'",
-                "-DLIBCXXABI_ENABLE_SHARED='Off'",
-                "-DLIBCXXABI_ENABLE_STATIC='On'",
-                "-DLIBCXXABI_ENABLE_STATIC_UNWINDER='On'",
-                "-DLIBCXXABI_USE_LLVM_UNWINDER='On'",
-                "-DLIBCXXABI_USE_COMPILER_RT='On'",
-                "-DLIBUNWIND_ENABLE_STATIC='On'",
-                "-DLIBUNWIND_ENABLE_SHARED='Off'",
-                "-DCOMPILER_RT_BUILD_CRT='On'",
-                "-DCOMPILER_RT_BUILD_SANITIZERS='Off'",
-                "-DCOMPILER_RT_BUILD_XRAY='Off'",
-                "-DCOMPILER_RT_BUILD_LIBFUZZER='Off'",
-                "-DCOMPILER_RT_BUILD_PROFILE='On'",
-                "-DCOMPILER_RT_BUILD_MEMPROF='Off'",
-                "-DCOMPILER_RT_BUILD_ORC='Off'",
-                "-DCOMPILER_RT_DEFAULT_TARGET_ARCH='aarch64'",
-                "-DCOMPILER_RT_DEFAULT_TARGET_ONLY='On'",
-            ])
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS)
-            .args(crate::platforms::shared::shared_build_opts_ccache(
-                ccache_variant,
-            )),
-        "LLVM host building cmake",
-    )?;
-
-    let mut crt_lib_directory = crt_target_directory.to_path_buf();
-    crt_lib_directory.push("lib/");
-
-    let mut build_lib_directory = build_directory.to_path_buf();
-    build_lib_directory.push("lib/");
-
-    let copy_options = fs_extra::dir::CopyOptions {
-        overwrite: true,
-        copy_inside: true,
-        content_only: true,
-        ..Default::default()
-    };
-    fs_extra::dir::copy(crt_lib_directory, build_lib_directory, &copy_options)?;
-
-    crate::utils::command(
-        Command::new("ninja")
-            .arg("-C")
-            .arg(build_directory)
-            .arg("install"),
-        "LLVM host building ninja",
-    )?;
-
-    Ok(())
+) -> CMakeConfig {
+    let mut config = CMakeConfig::new(source_directory, build_directory);
+    config
+        .set_path(
+            "DEFAULT_SYSROOT",
+            musl_target_directory,
+            CMakeOrigin::Platform,
+        )
+        .set_string(
+            "LINKER_SUPPORTS_COLOR_DIAGNOSTICS",
+            "0",
+            CMakeOrigin::Platform,
+        )
+        .set_path(
+            "CMAKE_INSTALL_PREFIX",
+            target_directory,
+            CMakeOrigin::Platform,
+        )
+        .set_string("CMAKE_BUILD_TYPE", "Release", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_C_COMPILER", "clang", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_CXX_COMPILER", "clang++", CMakeOrigin::Platform)
+        .set_string("CLANG_DEFAULT_CXX_STDLIB", "libc++", CMakeOrigin::Platform)
+        .set_string("CLANG_DEFAULT_RTLIB", "compiler-rt", CMakeOrigin::Platform)
+        .set_string(
+            "LLVM_DEFAULT_TARGET_TRIPLE",
+            "aarch64-unknown-linux-musl",
+            CMakeOrigin::Platform,
+        )
+        .set_string(
+            "LLVM_TARGETS_TO_BUILD",
+            Platform::AArch64.to_string(),
+            CMakeOrigin::Platform,
+        )
+        .set_bool("LLVM_BUILD_TESTS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_BUILD_UTILS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_TESTS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_UTILS", false, CMakeOrigin::Platform)
+        .set_string("LLVM_ENABLE_PROJECTS", "clang;lld", CMakeOrigin::Platform)
+        .set_string(
+            "LLVM_ENABLE_RUNTIMES",
+            "compiler-rt;libcxx;libcxxabi;libunwind",
+            CMakeOrigin::Platform,
+        )
+        .set_string("LIBCXX_CXX_ABI", "libcxxabi", CMakeOrigin::Platform)
+        .set_bool("LIBCXX_HAS_MUSL_LIBC", true, CMakeOrigin::Platform)
+        .set_bool("LIBCXX_ENABLE_SHARED", false, CMakeOrigin::Platform)
+        .set_bool("LIBCXX_ENABLE_STATIC", true, CMakeOrigin::Platform)
+        .set_bool(
+            "LIBCXX_ENABLE_STATIC_ABI_LIBRARY",
+            true,
+            CMakeOrigin::Platform,
+        )
+        .set_bool("LIBCXXABI_ENABLE_SHARED", false, CMakeOrigin::Platform)
+        .set_bool("LIBCXXABI_ENABLE_STATIC", true, CMakeOrigin::Platform)
+        .set_bool(
+            "LIBCXXABI_ENABLE_STATIC_UNWINDER",
+            true,
+            CMakeOrigin::Platform,
+        )
+        .set_bool("LIBCXXABI_USE_LLVM_UNWINDER", true, CMakeOrigin::Platform)
+        .set_bool("LIBCXXABI_USE_COMPILER_RT", true, CMakeOrigin::Platform)
+        .set_bool("LIBUNWIND_ENABLE_STATIC", true, CMakeOrigin::Platform)
+        .set_bool("LIBUNWIND_ENABLE_SHARED", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_CRT", true, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_SANITIZERS", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_XRAY", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_LIBFUZZER", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_PROFILE", true, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_MEMPROF", false, CMakeOrigin::Platform)
+        .set_bool("COMPILER_RT_BUILD_ORC", false, CMakeOrigin::Platform)
+        .set_string(
+            "COMPILER_RT_DEFAULT_TARGET_ARCH",
+            "aarch64",
+            CMakeOrigin::Platform,
+        )
+        .set_bool(
+            "COMPILER_RT_DEFAULT_TARGET_ONLY",
+            true,
+            CMakeOrigin::Platform,
+        );
+    crate::platforms::shared::shared_build_opts(&mut config);
+    crate::platforms::shared::shared_build_opts_ccache(&mut config, ccache_variant);
+    config
 }
 
 ///
-/// The target toolchain building sequence.
+/// Returns the CMake configuration of the target toolchain build.
 ///
-#[allow(clippy::too_many_arguments)]
-fn build_target(
-    build_type: BuildType,
-    targets: HashSet<Platform>,
-    llvm_projects: HashSet<LLVMProject>,
-    enable_rtti: bool,
-    default_target: Option<TargetTriple>,
+pub fn target_cmake_config(
+    options: &BuildOptions,
     source_directory: &Path,
     build_directory: &Path,
     target_directory: &Path,
-    musl_target_directory: &Path,
     host_target_directory: &Path,
-    enable_tests: bool,
-    enable_coverage: bool,
-    extra_args: Vec<String>,
-    ccache_variant: Option<CcacheVariant>,
-    enable_assertions: bool,
-    sanitizer: Option<Sanitizer>,
-    enable_valgrind: bool,
-    valgrind_options: Vec<String>,
-) -> anyhow::Result<()> {
+) -> CMakeConfig {
     let mut clang_path = host_target_directory.to_path_buf();
     clang_path.push("bin/clang");
 
     let mut clang_cxx_path = host_target_directory.to_path_buf();
     clang_cxx_path.push("bin/clang++");
 
-    crate::utils::command(
-        Command::new("cmake")
-            .args([
-                "-S",
-                source_directory.to_string_lossy().as_ref(),
-                "-B",
-                build_directory.to_string_lossy().as_ref(),
-                "-G",
-                "Ninja",
-                "-DBUILD_SHARED_LIBS='Off'",
-                "-DLINKER_SUPPORTS_COLOR_DIAGNOSTICS=0",
-                format!(
-                    "-DCMAKE_INSTALL_PREFIX='{}'",
-                    target_directory.to_string_lossy()
-                )
-                .as_str(),
-                format!("-DCMAKE_BUILD_TYPE='{build_type}'").as_str(),
-                format!("-DCMAKE_C_COMPILER='{}'", clang_path.to_string_lossy()).as_str(),
-                format!(
-                    "-DCMAKE_CXX_COMPILER='{}'",
-                    clang_cxx_path.to_string_lossy()
-                )
-                .as_str(),
-                "-DCMAKE_FIND_LIBRARY_SUFFIXES='.a'",
-                "-DCMAKE_BUILD_WITH_INSTALL_RPATH=1",
-                "-DCMAKE_EXE_LINKER_FLAGS='-fuse-ld=lld -static'",
-                format!(
-                    "-DLLVM_TARGETS_TO_BUILD='{}'",
-                    targets
-                        .into_iter()
-                        .map(|platform| platform.to_string())
-                        .collect::<Vec<String>>()
-                        .join(";")
-                )
-                .as_str(),
-                format!(
-                    "-DLLVM_ENABLE_PROJECTS='{}'",
-                    llvm_projects
-                        .into_iter()
-                        .map(|project| project.to_string())
-                        .collect::<Vec<String>>()
-                        .join(";")
-                )
-                .as_str(),
-            ])
-            .args(crate::platforms::shared::shared_build_opts_default_target(
-                default_target,
-            ))
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS)
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS_NOT_MUSL)
-            .args(crate::platforms::shared::shared_build_opts_werror())
-            .args(crate::platforms::shared::shared_build_opts_tests(
-                enable_tests,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_coverage(
-                enable_coverage,
-            ))
-            .args(extra_args)
-            .args(crate::platforms::shared::shared_build_opts_ccache(
-                ccache_variant,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_assertions(
-                enable_assertions,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_rtti(
-                enable_rtti,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_sanitizers(
-                sanitizer,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_valgrind(
-                enable_valgrind,
-                valgrind_options,
-            )),
-        "LLVM target building cmake",
-    )?;
+    let mut config = crate::platforms::shared::final_config(
+        options,
+        source_directory,
+        build_directory,
+        target_directory,
+    );
+    config
+        .set_bool("BUILD_SHARED_LIBS", false, CMakeOrigin::Platform)
+        .set_string(
+            "LINKER_SUPPORTS_COLOR_DIAGNOSTICS",
+            "0",
+            CMakeOrigin::Platform,
+        )
+        .set_file_path(
+            "CMAKE_C_COMPILER",
+            clang_path.to_string_lossy(),
+            CMakeOrigin::Platform,
+        )
+        .set_file_path(
+            "CMAKE_CXX_COMPILER",
+            clang_cxx_path.to_string_lossy(),
+            CMakeOrigin::Platform,
+        )
+        .set_string("CMAKE_FIND_LIBRARY_SUFFIXES", ".a", CMakeOrigin::Platform)
+        .set_string(
+            "CMAKE_EXE_LINKER_FLAGS",
+            "-fuse-ld=lld -static",
+            CMakeOrigin::Platform,
+        );
+    crate::platforms::shared::final_config_options(&mut config, options);
+    crate::platforms::shared::shared_build_opts_valgrind(
+        &mut config,
+        options.enable_valgrind,
+        options.valgrind_options.as_slice(),
+    );
+    config
+}
 
-    crate::utils::ninja(build_directory)?;
+///
+/// The `crt` building sequence.
+///
+fn build_crt(config: CMakeConfig) -> Vec<Step> {
+    vec![
+        Step::Command(config.to_step("CRT building cmake")),
+        Step::Command(
+            CommandStep::new("ninja", "CRT building ninja")
+                .arg("-C")
+                .arg(config.build_directory.to_string_lossy())
+                .arg("install-crt"),
+        ),
+    ]
+}
 
+///
+/// The host toolchain building sequence.
+///
+fn build_host(config: CMakeConfig, crt_target_directory: &Path) -> Vec<Step> {
+    let mut crt_lib_directory = crt_target_directory.to_path_buf();
+    crt_lib_directory.push("lib/");
+
+    let mut build_lib_directory = config.build_directory.clone();
+    build_lib_directory.push("lib/");
+
+    vec![
+        Step::Command(config.to_step("LLVM host building cmake")),
+        Step::CopyDirectory {
+            source: crt_lib_directory,
+            destination: build_lib_directory,
+            content_only: true,
+        },
+        Step::Command(
+            CommandStep::new("ninja", "LLVM host building ninja")
+                .arg("-C")
+                .arg(config.build_directory.to_string_lossy())
+                .arg("install"),
+        ),
+    ]
+}
+
+///
+/// The target toolchain building sequence.
+///
+fn build_target(
+    config: CMakeConfig,
+    target_directory: &Path,
+    musl_target_directory: &Path,
+    host_target_directory: &Path,
+) -> Vec<Step> {
     let mut musl_lib_directory = musl_target_directory.to_path_buf();
     musl_lib_directory.push("lib/");
 
@@ -379,22 +504,18 @@ fn build_target(
     let mut target_lib_directory = target_directory.to_path_buf();
     target_lib_directory.push("lib/");
 
-    let copy_options = fs_extra::dir::CopyOptions {
-        overwrite: true,
-        copy_inside: true,
-        content_only: true,
-        ..Default::default()
-    };
-    fs_extra::dir::copy(
-        musl_lib_directory,
-        target_lib_directory.as_path(),
-        &copy_options,
-    )?;
-    fs_extra::dir::copy(
-        host_lib_directory,
-        target_lib_directory.as_path(),
-        &copy_options,
-    )?;
-
-    Ok(())
+    vec![
+        Step::Command(config.to_step("LLVM target building cmake")),
+        Step::Command(crate::utils::ninja(config.build_directory.as_path())),
+        Step::CopyDirectory {
+            source: musl_lib_directory,
+            destination: target_lib_directory.clone(),
+            content_only: true,
+        },
+        Step::CopyDirectory {
+            source: host_lib_directory,
+            destination: target_lib_directory,
+            content_only: true,
+        },
+    ]
 }
@@ -13,10 +13,13 @@ pub mod x86_64_windows_gnu;
 
 use std::str::FromStr;
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// The list of platforms used as constants.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Platform {
     /// The native X86 platform.
     X86,
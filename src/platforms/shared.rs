@@ -2,203 +2,389 @@
 //! The shared options for building various platforms.
 //!
 
+use std::collections::BTreeSet;
 use std::path::Path;
-use std::process::Command;
 
+use crate::build_options::BuildOptions;
 use crate::ccache_variant::CcacheVariant;
+use crate::cmake_config::CMakeConfig;
+use crate::cmake_config::CMakeOrigin;
+use crate::host::Host;
+use crate::host::OS;
+use crate::llvm_project::LLVMProject;
+use crate::plan::CommandStep;
+use crate::plan::Step;
+use crate::platforms::Platform;
 use crate::sanitizer::Sanitizer;
 use crate::target_triple::TargetTriple;
 
+///
+/// The base configuration of the final LLVM build shared by all platforms.
+///
+/// Platforms apply their overrides on top of it, and then finish it with
+/// [`final_config_options`]. The mandatory targets and projects are always built, even if
+/// the options were deserialized with explicit lists omitting them.
+///
+pub fn final_config(
+    options: &BuildOptions,
+    source_directory: &Path,
+    build_directory: &Path,
+    target_directory: &Path,
+) -> CMakeConfig {
+    let mut config = CMakeConfig::new(source_directory, build_directory);
+    config
+        .set_path(
+            "CMAKE_INSTALL_PREFIX",
+            target_directory,
+            CMakeOrigin::Shared,
+        )
+        .set_string(
+            "CMAKE_BUILD_TYPE",
+            options.build_type.to_string(),
+            CMakeOrigin::Options,
+        )
+        .set_string(
+            "LLVM_TARGETS_TO_BUILD",
+            BuildOptions::TARGETS_MANDATORY
+                .into_iter()
+                .chain(options.targets.iter().copied())
+                .collect::<BTreeSet<Platform>>()
+                .into_iter()
+                .map(|platform| platform.to_string())
+                .collect::<Vec<String>>()
+                .join(";"),
+            CMakeOrigin::Options,
+        )
+        .set_string(
+            "LLVM_ENABLE_PROJECTS",
+            BuildOptions::LLVM_PROJECTS_MANDATORY
+                .into_iter()
+                .chain(options.llvm_projects.iter().copied())
+                .collect::<BTreeSet<LLVMProject>>()
+                .into_iter()
+                .map(|project| project.to_string())
+                .collect::<Vec<String>>()
+                .join(";"),
+            CMakeOrigin::Options,
+        );
+    shared_build_opts_default_target(&mut config, options.default_target);
+    shared_build_opts_tests(&mut config, options.enable_tests);
+    shared_build_opts_coverage(&mut config, options.enable_coverage);
+    shared_build_opts_ccache(&mut config, options.ccache_variant);
+    shared_build_opts(&mut config);
+    shared_build_opts_not_musl(&mut config);
+    shared_build_opts_werror(&mut config);
+    config
+}
+
+///
+/// The options of the final LLVM build applied after the platform overrides.
+///
+/// The extra arguments override the shared and platform entries, but not the assertions,
+/// RTTI, and sanitizer options set after them, nor the platform options set afterwards.
+///
+pub fn final_config_options(config: &mut CMakeConfig, options: &BuildOptions) {
+    config.extra_args(options.extra_args.iter().cloned());
+    shared_build_opts_assertions(config, options.enable_assertions);
+    shared_build_opts_rtti(config, options.enable_rtti);
+    shared_build_opts_sanitizers(config, options.sanitizer);
+}
+
+///
 /// The build options shared by all platforms.
-pub const SHARED_BUILD_OPTS: [&str; 19] = [
-    "-DPACKAGE_VENDOR='Matter Labs'",
-    "-DCMAKE_BUILD_WITH_INSTALL_RPATH=1",
-    "-DLLVM_BUILD_DOCS='Off'",
-    "-DLLVM_INCLUDE_DOCS='Off'",
-    "-DLLVM_INCLUDE_BENCHMARKS='Off'",
-    "-DLLVM_INCLUDE_EXAMPLES='Off'",
-    "-DLLVM_ENABLE_DOXYGEN='Off'",
-    "-DLLVM_ENABLE_SPHINX='Off'",
-    "-DLLVM_ENABLE_OCAMLDOC='Off'",
-    "-DLLVM_ENABLE_ZLIB='Off'",
-    "-DLLVM_ENABLE_ZSTD='Off'",
-    "-DLLVM_ENABLE_LIBXML2='Off'",
-    "-DLLVM_ENABLE_BINDINGS='Off'",
-    "-DLLVM_ENABLE_TERMINFO='Off'",
-    "-DLLVM_ENABLE_LIBEDIT='Off'",
-    "-DLLVM_ENABLE_LIBPFM='Off'",
-    "-DCMAKE_EXPORT_COMPILE_COMMANDS='On'",
-    "-DPython3_FIND_REGISTRY='LAST'", // Use Python version from $PATH, not from registry
-    "-DBUG_REPORT_URL='https://github.com/matter-labs/era-compiler-llvm/issues/'",
-];
+///
+pub fn shared_build_opts(config: &mut CMakeConfig) {
+    config
+        .set_string("PACKAGE_VENDOR", "Matter Labs", CMakeOrigin::Shared)
+        .set_bool("CMAKE_BUILD_WITH_INSTALL_RPATH", true, CMakeOrigin::Shared)
+        .set_bool("LLVM_BUILD_DOCS", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_INCLUDE_DOCS", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_INCLUDE_BENCHMARKS", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_INCLUDE_EXAMPLES", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_DOXYGEN", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_SPHINX", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_OCAMLDOC", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_ZLIB", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_ZSTD", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_LIBXML2", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_BINDINGS", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_TERMINFO", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_LIBEDIT", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_ENABLE_LIBPFM", false, CMakeOrigin::Shared)
+        .set_bool("CMAKE_EXPORT_COMPILE_COMMANDS", true, CMakeOrigin::Shared)
+        // Use Python version from $PATH, not from registry
+        .set_string("Python3_FIND_REGISTRY", "LAST", CMakeOrigin::Shared)
+        .set_string(
+            "BUG_REPORT_URL",
+            "https://github.com/matter-labs/era-compiler-llvm/issues/",
+            CMakeOrigin::Shared,
+        );
+}
 
+///
 /// The build options shared by all platforms except MUSL.
-pub const SHARED_BUILD_OPTS_NOT_MUSL: [&str; 4] = [
-    "-DLLVM_OPTIMIZED_TABLEGEN='Off'",
-    "-DLLVM_BUILD_RUNTIME='Off'",
-    "-DLLVM_BUILD_RUNTIMES='Off'",
-    "-DLLVM_INCLUDE_RUNTIMES='Off'",
-];
+///
+pub fn shared_build_opts_not_musl(config: &mut CMakeConfig) {
+    config
+        .set_bool("LLVM_OPTIMIZED_TABLEGEN", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_BUILD_RUNTIME", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_BUILD_RUNTIMES", false, CMakeOrigin::Shared)
+        .set_bool("LLVM_INCLUDE_RUNTIMES", false, CMakeOrigin::Shared);
+}
 
 ///
 /// The shared build options to treat warnings as errors.
 ///
-/// Disabled on Windows due to the following upstream issue with MSYS2 with mingw-w64:
-/// ProgramTest.cpp:23:15: error: '__p__environ' redeclared without 'dllimport' attribute
-///
-pub fn shared_build_opts_werror() -> Vec<String> {
-    vec![format!(
-        "-DLLVM_ENABLE_WERROR='{}'",
-        if cfg!(target_os = "windows") {
-            "Off"
-        } else {
-            "On"
-        },
-    )]
+pub fn shared_build_opts_werror(config: &mut CMakeConfig) {
+    config.set_bool("LLVM_ENABLE_WERROR", true, CMakeOrigin::Shared);
 }
 
 ///
 /// The build options to set the default target.
 ///
-pub fn shared_build_opts_default_target(target: Option<TargetTriple>) -> Vec<String> {
-    match target {
-        Some(target) => vec![format!(
-            "-DLLVM_DEFAULT_TARGET_TRIPLE='{}'",
-            target.to_string()
-        )],
-        None => vec![format!(
-            "-DLLVM_DEFAULT_TARGET_TRIPLE='{}'",
-            TargetTriple::EraVM
-        )],
-    }
+pub fn shared_build_opts_default_target(config: &mut CMakeConfig, target: Option<TargetTriple>) {
+    config.set_string(
+        "LLVM_DEFAULT_TARGET_TRIPLE",
+        target.unwrap_or(TargetTriple::EraVM).to_string(),
+        CMakeOrigin::Options,
+    );
 }
 
 ///
 /// The `musl` building sequence.
 ///
-pub fn build_musl(build_directory: &Path, target_directory: &Path) -> anyhow::Result<()> {
-    std::fs::create_dir_all(build_directory)?;
-    std::fs::create_dir_all(target_directory)?;
-
-    crate::utils::command(
-        Command::new("../configure")
-            .current_dir(build_directory)
-            .arg(format!("--prefix={}", target_directory.to_string_lossy()))
-            .arg(format!(
-                "--syslibdir={}/lib/",
-                target_directory.to_string_lossy()
-            ))
-            .arg("--enable-wrapper='clang'"),
-        "MUSL configuring",
-    )?;
-    crate::utils::command(
-        Command::new("make")
-            .current_dir(build_directory)
-            .arg("-j")
-            .arg(num_cpus::get().to_string()),
-        "MUSL building",
-    )?;
-    crate::utils::command(
-        Command::new("make")
-            .current_dir(build_directory)
-            .arg("install"),
-        "MUSL installing",
-    )?;
-
-    let mut include_directory = target_directory.to_path_buf();
-    include_directory.push("include/");
-
-    let mut asm_include_directory = include_directory.clone();
-    asm_include_directory.push("asm/");
-    std::fs::create_dir_all(asm_include_directory.as_path())?;
+/// If the target triple is specified, musl is cross-compiled for it with clang and `lld`.
+/// The shared library is not built in this case, as the toolchains are linked statically.
+///
+pub fn build_musl(
+    build_directory: &Path,
+    target_directory: &Path,
+    cross_triple: Option<&str>,
+    jobs: Option<usize>,
+) -> Vec<Step> {
+    let mut configure = CommandStep::new("../configure", "MUSL configuring")
+        .current_dir(build_directory)
+        .arg(format!("--prefix={}", target_directory.to_string_lossy()))
+        .arg(format!(
+            "--syslibdir={}/lib/",
+            target_directory.to_string_lossy()
+        ))
+        .arg("--enable-wrapper='clang'");
+    if let Some(triple) = cross_triple {
+        configure = configure
+            .arg(format!("--target={triple}"))
+            .arg("--disable-shared")
+            .arg("CC=clang")
+            .arg(format!("CFLAGS=--target={triple}"))
+            .arg("LDFLAGS=-fuse-ld=lld");
+    }
 
-    let mut types_header_path = asm_include_directory.clone();
-    types_header_path.push("types.h");
+    vec![
+        Step::CreateDirectory {
+            path: build_directory.to_path_buf(),
+        },
+        Step::CreateDirectory {
+            path: target_directory.to_path_buf(),
+        },
+        Step::Command(configure),
+        Step::Command(
+            CommandStep::new("make", "MUSL building")
+                .current_dir(build_directory)
+                .arg("-j")
+                .arg(jobs.unwrap_or_else(num_cpus::get).to_string()),
+        ),
+        Step::Command(
+            CommandStep::new("make", "MUSL installing")
+                .current_dir(build_directory)
+                .arg("install"),
+        ),
+    ]
+}
 
-    let copy_options = fs_extra::dir::CopyOptions {
-        overwrite: true,
-        copy_inside: true,
-        ..Default::default()
+///
+/// Returns the CMake toolchain file contents of cross-compiling for the host.
+///
+/// The libraries and headers are only searched in the root directory of the host
+/// libraries, while the programs, e.g. the native table generators, are searched on the
+/// builder. If `is_try_compile_static` is set, the CMake checks do not link executables,
+/// which is required before the host C runtime is built.
+///
+pub fn cross_toolchain_file(
+    host: Host,
+    c_compiler: &Path,
+    cxx_compiler: &Path,
+    sysroot: Option<&Path>,
+    find_root: &Path,
+    is_try_compile_static: bool,
+) -> String {
+    let system_name = match host.os {
+        OS::Linux => "Linux",
+        OS::MacOS => "Darwin",
+        OS::Windows => "Windows",
     };
-    fs_extra::dir::copy("/usr/include/linux", include_directory, &copy_options)?;
+    let triple = host.triple();
+    let mut lines = vec![
+        format!("set(CMAKE_SYSTEM_NAME {system_name})"),
+        format!("set(CMAKE_SYSTEM_PROCESSOR {})", host.arch),
+    ];
+    if let Some(sysroot) = sysroot {
+        lines.push(format!(
+            "set(CMAKE_SYSROOT \"{}\")",
+            sysroot.to_string_lossy()
+        ));
+    }
+    lines.extend([
+        format!(
+            "set(CMAKE_FIND_ROOT_PATH \"{}\")",
+            find_root.to_string_lossy()
+        ),
+        format!("set(CMAKE_C_COMPILER \"{}\")", c_compiler.to_string_lossy()),
+        format!("set(CMAKE_C_COMPILER_TARGET {triple})"),
+        format!(
+            "set(CMAKE_CXX_COMPILER \"{}\")",
+            cxx_compiler.to_string_lossy()
+        ),
+        format!("set(CMAKE_CXX_COMPILER_TARGET {triple})"),
+        format!("set(CMAKE_ASM_COMPILER_TARGET {triple})"),
+        "set(CMAKE_FIND_ROOT_PATH_MODE_PROGRAM NEVER)".to_owned(),
+        "set(CMAKE_FIND_ROOT_PATH_MODE_LIBRARY ONLY)".to_owned(),
+        "set(CMAKE_FIND_ROOT_PATH_MODE_INCLUDE ONLY)".to_owned(),
+        "set(CMAKE_FIND_ROOT_PATH_MODE_PACKAGE ONLY)".to_owned(),
+    ]);
+    if host.os == OS::Windows {
+        lines.push(format!("set(CMAKE_RC_COMPILER {triple}-windres)"));
+    }
+    if is_try_compile_static {
+        lines.push("set(CMAKE_TRY_COMPILE_TARGET_TYPE STATIC_LIBRARY)".to_owned());
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
 
-    let copy_options = fs_extra::dir::CopyOptions {
-        overwrite: true,
-        copy_inside: true,
-        content_only: true,
-        ..Default::default()
-    };
-    fs_extra::dir::copy(
-        "/usr/include/asm-generic",
-        asm_include_directory.as_path(),
-        &copy_options,
-    )?;
+///
+/// The native table generators building sequence of cross-compilation.
+///
+/// The generators run on the builder during the build of the host toolchain, so they are
+/// built from the same source with the builder compilers first.
+///
+pub fn build_tblgen(
+    options: &BuildOptions,
+    source_directory: &Path,
+    build_directory: &Path,
+) -> Vec<Step> {
+    let mut config = CMakeConfig::new(source_directory, build_directory);
+    config
+        .set_string("CMAKE_BUILD_TYPE", "Release", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_C_COMPILER", "clang", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_CXX_COMPILER", "clang++", CMakeOrigin::Platform)
+        .set_string("LLVM_USE_LINKER", "lld", CMakeOrigin::Platform)
+        .set_string(
+            "LLVM_ENABLE_PROJECTS",
+            if options.llvm_projects.contains(&LLVMProject::CLANG) {
+                "clang"
+            } else {
+                ""
+            },
+            CMakeOrigin::Platform,
+        )
+        .set_bool("LLVM_BUILD_TESTS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_TESTS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_BUILD_UTILS", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_INCLUDE_UTILS", false, CMakeOrigin::Platform);
+    shared_build_opts(&mut config);
+    shared_build_opts_not_musl(&mut config);
+    shared_build_opts_ccache(&mut config, options.ccache_variant);
 
-    let arch_asm_path = format!("/usr/include/{}-linux-gnu/asm", std::env::consts::ARCH);
-    let copy_options = fs_extra::file::CopyOptions::new().overwrite(true);
-    for file in [
-        "byteorder.h",
-        "ptrace.h",
-        "hwcap.h",
-        "sve_context.h",
-        "unistd_64.h",
-    ]
-    .into_iter()
-    {
-        let source = std::path::PathBuf::from(arch_asm_path.as_str()).join(file);
-        let destination = asm_include_directory.join(file);
-        if source.exists() {
-            fs_extra::file::copy(source.as_path(), destination.as_path(), &copy_options)?;
-        }
+    let mut ninja = CommandStep::new("ninja", "LLVM table generators building ninja")
+        .arg("-C")
+        .arg(build_directory.to_string_lossy())
+        .args(["llvm-tblgen", "llvm-min-tblgen"]);
+    if options.llvm_projects.contains(&LLVMProject::CLANG) {
+        ninja = ninja.arg("clang-tblgen");
     }
+    vec![
+        Step::Command(config.to_step("LLVM table generators building cmake")),
+        Step::Command(ninja),
+    ]
+}
 
-    crate::utils::command(
-        Command::new("sed")
-            .arg("-i")
-            .arg("s/asm-generic/asm/")
-            .arg(types_header_path),
-        "types_header asm signature replacement",
-    )?;
-
-    Ok(())
+///
+/// The build options of cross-compiling with the toolchain file and the native table
+/// generators built by [`build_tblgen`].
+///
+pub fn shared_build_opts_cross(
+    config: &mut CMakeConfig,
+    options: &BuildOptions,
+    host: Host,
+    toolchain_file: &Path,
+    tblgen_build_directory: &Path,
+) {
+    let tblgen_directory = tblgen_build_directory.join("bin");
+    config
+        .unset("CMAKE_C_COMPILER")
+        .unset("CMAKE_CXX_COMPILER")
+        .set_file_path(
+            "CMAKE_TOOLCHAIN_FILE",
+            toolchain_file.to_string_lossy(),
+            CMakeOrigin::Platform,
+        )
+        .set_string("LLVM_HOST_TRIPLE", host.triple(), CMakeOrigin::Platform)
+        .set_path(
+            "LLVM_NATIVE_TOOL_DIR",
+            tblgen_directory.as_path(),
+            CMakeOrigin::Platform,
+        )
+        .set_file_path(
+            "LLVM_TABLEGEN",
+            tblgen_directory.join("llvm-tblgen").to_string_lossy(),
+            CMakeOrigin::Platform,
+        );
+    if options.llvm_projects.contains(&LLVMProject::CLANG) {
+        config.set_file_path(
+            "CLANG_TABLEGEN",
+            tblgen_directory.join("clang-tblgen").to_string_lossy(),
+            CMakeOrigin::Platform,
+        );
+    }
 }
 
 ///
 /// The build options to enable assertions.
 ///
-pub fn shared_build_opts_assertions(enabled: bool) -> Vec<String> {
-    vec![format!(
-        "-DLLVM_ENABLE_ASSERTIONS='{}'",
-        if enabled { "On" } else { "Off" },
-    )]
+pub fn shared_build_opts_assertions(config: &mut CMakeConfig, enabled: bool) {
+    config.set_bool("LLVM_ENABLE_ASSERTIONS", enabled, CMakeOrigin::Options);
 }
 
 ///
 /// The build options to build with RTTI support.
 ///
-pub fn shared_build_opts_rtti(enabled: bool) -> Vec<String> {
-    vec![format!(
-        "-DLLVM_ENABLE_RTTI='{}'",
-        if enabled { "On" } else { "Off" },
-    )]
+pub fn shared_build_opts_rtti(config: &mut CMakeConfig, enabled: bool) {
+    config.set_bool("LLVM_ENABLE_RTTI", enabled, CMakeOrigin::Options);
 }
 
 ///
 /// The build options to enable sanitizers.
 ///
-pub fn shared_build_opts_sanitizers(sanitizer: Option<Sanitizer>) -> Vec<String> {
-    match sanitizer {
-        Some(sanitizer) => vec![format!("-DLLVM_USE_SANITIZER='{sanitizer}'")],
-        None => vec![],
+pub fn shared_build_opts_sanitizers(config: &mut CMakeConfig, sanitizer: Option<Sanitizer>) {
+    if let Some(sanitizer) = sanitizer {
+        config.set_string(
+            "LLVM_USE_SANITIZER",
+            sanitizer.to_string(),
+            CMakeOrigin::Options,
+        );
     }
 }
 
 ///
 /// The build options to enable Valgrind for LLVM regression tests.
 ///
-pub fn shared_build_opts_valgrind(enabled: bool, valgrind_options: Vec<String>) -> Vec<String> {
+pub fn shared_build_opts_valgrind(
+    config: &mut CMakeConfig,
+    enabled: bool,
+    valgrind_options: &[String],
+) {
     if !enabled {
-        return vec![];
+        return;
     }
 
     let vg_args = valgrind_options
@@ -207,74 +393,72 @@ pub fn shared_build_opts_valgrind(enabled: bool, valgrind_options: Vec<String>)
         .collect::<Vec<_>>()
         .join(" ");
 
-    vec![format!("-DLLVM_LIT_ARGS='-sv --vg --vg-leak {vg_args}'")]
+    config.set_string(
+        "LLVM_LIT_ARGS",
+        format!("-sv --vg --vg-leak {vg_args}"),
+        CMakeOrigin::Options,
+    );
 }
 
 ///
 /// The LLVM tests build options shared by all platforms.
 ///
-pub fn shared_build_opts_tests(enabled: bool) -> Vec<String> {
-    vec![
-        format!(
-            "-DLLVM_BUILD_UTILS='{}'",
-            if enabled { "On" } else { "Off" },
-        ),
-        format!(
-            "-DLLVM_BUILD_TESTS='{}'",
-            if enabled { "On" } else { "Off" },
-        ),
-        format!(
-            "-DLLVM_INCLUDE_UTILS='{}'",
-            if enabled { "On" } else { "Off" },
-        ),
-        format!(
-            "-DLLVM_INCLUDE_TESTS='{}'",
-            if enabled { "On" } else { "Off" },
-        ),
-    ]
+pub fn shared_build_opts_tests(config: &mut CMakeConfig, enabled: bool) {
+    config
+        .set_bool("LLVM_BUILD_UTILS", enabled, CMakeOrigin::Options)
+        .set_bool("LLVM_BUILD_TESTS", enabled, CMakeOrigin::Options)
+        .set_bool("LLVM_INCLUDE_UTILS", enabled, CMakeOrigin::Options)
+        .set_bool("LLVM_INCLUDE_TESTS", enabled, CMakeOrigin::Options);
 }
 
 ///
 /// The code coverage build options shared by all platforms.
 ///
-pub fn shared_build_opts_coverage(enabled: bool) -> Vec<String> {
-    vec![format!(
-        "-DLLVM_BUILD_INSTRUMENTED_COVERAGE='{}'",
-        if enabled { "On" } else { "Off" },
-    )]
+pub fn shared_build_opts_coverage(config: &mut CMakeConfig, enabled: bool) {
+    config.set_bool(
+        "LLVM_BUILD_INSTRUMENTED_COVERAGE",
+        enabled,
+        CMakeOrigin::Options,
+    );
 }
 
 ///
 /// Use of compiler cache (ccache) to speed up the build process.
 ///
-pub fn shared_build_opts_ccache(ccache_variant: Option<CcacheVariant>) -> Vec<String> {
-    match ccache_variant {
-        Some(ccache_variant) => vec![
-            format!(
-                "-DCMAKE_C_COMPILER_LAUNCHER='{}'",
-                ccache_variant.to_string()
-            ),
-            format!(
-                "-DCMAKE_CXX_COMPILER_LAUNCHER='{}'",
-                ccache_variant.to_string()
-            ),
-        ],
-        None => vec![],
+pub fn shared_build_opts_ccache(config: &mut CMakeConfig, ccache_variant: Option<CcacheVariant>) {
+    if let Some(ccache_variant) = ccache_variant {
+        config
+            .set_file_path(
+                "CMAKE_C_COMPILER_LAUNCHER",
+                ccache_variant.to_string(),
+                CMakeOrigin::Options,
+            )
+            .set_file_path(
+                "CMAKE_CXX_COMPILER_LAUNCHER",
+                ccache_variant.to_string(),
+                CMakeOrigin::Options,
+            );
     }
 }
 
 ///
 /// Ignore duplicate libraries warnings for MacOS with XCode>=15.
 ///
-pub fn macos_build_opts_ignore_dupicate_libs_warnings() -> Vec<String> {
-    let xcode_version =
-        crate::utils::get_xcode_version().unwrap_or(crate::utils::XCODE_MIN_VERSION);
-    if xcode_version >= crate::utils::XCODE_VERSION_15 {
-        vec![
-            "-DCMAKE_EXE_LINKER_FLAGS='-Wl,-no_warn_duplicate_libraries'".to_owned(),
-            "-DCMAKE_SHARED_LINKER_FLAGS='-Wl,-no_warn_duplicate_libraries'".to_owned(),
-        ]
-    } else {
-        vec![]
+pub fn macos_build_opts_ignore_dupicate_libs_warnings(
+    config: &mut CMakeConfig,
+    xcode_version: Option<u32>,
+) {
+    if xcode_version.unwrap_or(crate::utils::XCODE_MIN_VERSION) >= crate::utils::XCODE_VERSION_15 {
+        config
+            .set_string(
+                "CMAKE_EXE_LINKER_FLAGS",
+                "-Wl,-no_warn_duplicate_libraries",
+                CMakeOrigin::Platform,
+            )
+            .set_string(
+                "CMAKE_SHARED_LINKER_FLAGS",
+                "-Wl,-no_warn_duplicate_libraries",
+                CMakeOrigin::Platform,
+            );
     }
 }
@@ -2,106 +2,49 @@
 //! The ZKsync LLVM amd64 `macos` builder.
 //!
 
-use std::collections::HashSet;
-use std::process::Command;
-
-use crate::build_type::BuildType;
-use crate::ccache_variant::CcacheVariant;
+use crate::build_options::BuildOptions;
+use crate::cmake_config::CMakeConfig;
+use crate::cmake_config::CMakeOrigin;
 use crate::llvm_path::LLVMPath;
-use crate::llvm_project::LLVMProject;
-use crate::platforms::Platform;
-use crate::sanitizer::Sanitizer;
-use crate::target_triple::TargetTriple;
+use crate::plan::Plan;
+use crate::plan::Stage;
+use crate::plan::Step;
 
 ///
-/// The building sequence.
+/// The building plan.
 ///
-#[allow(clippy::too_many_arguments)]
-pub fn build(
-    build_type: BuildType,
-    targets: HashSet<Platform>,
-    llvm_projects: HashSet<LLVMProject>,
-    enable_rtti: bool,
-    default_target: Option<TargetTriple>,
-    enable_tests: bool,
-    enable_coverage: bool,
-    extra_args: Vec<String>,
-    ccache_variant: Option<CcacheVariant>,
-    enable_assertions: bool,
-    sanitizer: Option<Sanitizer>,
-) -> anyhow::Result<()> {
-    crate::utils::exists("cmake")?;
-    crate::utils::exists("ninja")?;
-
-    let llvm_module_llvm = LLVMPath::llvm_module_llvm()?;
-    let llvm_build_final = LLVMPath::llvm_build_final()?;
-    let llvm_target_final = LLVMPath::llvm_target_final()?;
+pub fn plan(options: &BuildOptions, paths: &LLVMPath) -> anyhow::Result<Plan> {
+    let config = cmake_config(options, paths)?;
 
-    crate::utils::command(
-        Command::new("cmake")
-            .args([
-                "-S",
-                llvm_module_llvm.to_string_lossy().as_ref(),
-                "-B",
-                llvm_build_final.to_string_lossy().as_ref(),
-                "-G",
-                "Ninja",
-                format!(
-                    "-DCMAKE_INSTALL_PREFIX='{}'",
-                    llvm_target_final.to_string_lossy().as_ref(),
-                )
-                .as_str(),
-                format!("-DCMAKE_BUILD_TYPE='{build_type}'").as_str(),
-                format!(
-                    "-DLLVM_TARGETS_TO_BUILD='{}'",
-                    targets
-                        .into_iter()
-                        .map(|platform| platform.to_string())
-                        .collect::<Vec<String>>()
-                        .join(";")
-                )
-                .as_str(),
-                format!(
-                    "-DLLVM_ENABLE_PROJECTS='{}'",
-                    llvm_projects
-                        .into_iter()
-                        .map(|project| project.to_string())
-                        .collect::<Vec<String>>()
-                        .join(";")
-                )
-                .as_str(),
-                "-DCMAKE_OSX_DEPLOYMENT_TARGET='11.0'",
-            ])
-            .args(crate::platforms::shared::shared_build_opts_default_target(
-                default_target,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_tests(
-                enable_tests,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_coverage(
-                enable_coverage,
-            ))
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS)
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS_NOT_MUSL)
-            .args(crate::platforms::shared::shared_build_opts_werror())
-            .args(extra_args)
-            .args(crate::platforms::shared::shared_build_opts_ccache(
-                ccache_variant,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_assertions(
-                enable_assertions,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_rtti(
-                enable_rtti,
-            ))
-            .args(crate::platforms::shared::macos_build_opts_ignore_dupicate_libs_warnings())
-            .args(crate::platforms::shared::shared_build_opts_sanitizers(
-                sanitizer,
-            )),
-        "LLVM building cmake",
-    )?;
+    Ok(Plan::new(&["cmake", "ninja"]).stage(
+        Stage::FINAL,
+        Some(config.source_directory.as_path()),
+        vec![
+            Step::Command(config.to_step("LLVM building cmake")),
+            Step::Command(crate::utils::ninja(config.build_directory.as_path())),
+        ],
+    ))
+}
 
-    crate::utils::ninja(llvm_build_final.as_ref())?;
+///
+/// Returns the CMake configuration of the final build.
+///
+pub fn cmake_config(options: &BuildOptions, paths: &LLVMPath) -> anyhow::Result<CMakeConfig> {
+    let llvm_module_llvm = paths.llvm_module_llvm();
+    let llvm_build_final = paths.llvm_build_final();
+    let llvm_target_final = paths.llvm_target_final();
 
-    Ok(())
+    let mut config = crate::platforms::shared::final_config(
+        options,
+        llvm_module_llvm.as_path(),
+        llvm_build_final.as_path(),
+        llvm_target_final.as_path(),
+    );
+    config.set_string("CMAKE_OSX_DEPLOYMENT_TARGET", "11.0", CMakeOrigin::Platform);
+    crate::platforms::shared::final_config_options(&mut config, options);
+    crate::platforms::shared::macos_build_opts_ignore_dupicate_libs_warnings(
+        &mut config,
+        options.xcode_version,
+    );
+    Ok(config)
 }
@@ -2,114 +2,26 @@
 //! The ZKsync LLVM amd64 `windows-gnu` builder.
 //!
 
-use std::collections::HashSet;
+use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
 
-use crate::build_type::BuildType;
-use crate::ccache_variant::CcacheVariant;
+use crate::build_options::BuildOptions;
+use crate::cmake_config::CMakeConfig;
+use crate::cmake_config::CMakeOrigin;
+use crate::host::Arch;
+use crate::host::Host;
+use crate::host::OS;
 use crate::llvm_path::LLVMPath;
-use crate::llvm_project::LLVMProject;
-use crate::platforms::Platform;
-use crate::sanitizer::Sanitizer;
-use crate::target_triple::TargetTriple;
+use crate::plan::Plan;
+use crate::plan::Stage;
+use crate::plan::Step;
+use crate::target_env::TargetEnv;
 
 ///
-/// The building sequence.
+/// The building plan.
 ///
-#[allow(clippy::too_many_arguments)]
-pub fn build(
-    build_type: BuildType,
-    targets: HashSet<Platform>,
-    llvm_projects: HashSet<LLVMProject>,
-    enable_rtti: bool,
-    default_target: Option<TargetTriple>,
-    enable_tests: bool,
-    enable_coverage: bool,
-    extra_args: Vec<String>,
-    ccache_variant: Option<CcacheVariant>,
-    enable_assertions: bool,
-    sanitizer: Option<Sanitizer>,
-) -> anyhow::Result<()> {
-    crate::utils::exists("cmake")?;
-    crate::utils::exists("clang")?;
-    crate::utils::exists("clang++")?;
-    crate::utils::exists("lld")?;
-    crate::utils::exists("ninja")?;
-
-    let llvm_module_llvm =
-        LLVMPath::llvm_module_llvm().and_then(crate::utils::path_windows_to_unix)?;
-    let llvm_build_final =
-        LLVMPath::llvm_build_final().and_then(crate::utils::path_windows_to_unix)?;
-    let llvm_target_final =
-        LLVMPath::llvm_target_final().and_then(crate::utils::path_windows_to_unix)?;
-
-    crate::utils::command(
-        Command::new("cmake")
-            .args([
-                "-S",
-                llvm_module_llvm.to_string_lossy().as_ref(),
-                "-B",
-                llvm_build_final.to_string_lossy().as_ref(),
-                "-G",
-                "Ninja",
-                format!(
-                    "-DCMAKE_INSTALL_PREFIX='{}'",
-                    llvm_target_final.to_string_lossy().as_ref(),
-                )
-                .as_str(),
-                format!("-DCMAKE_BUILD_TYPE='{build_type}'").as_str(),
-                "-DCMAKE_C_COMPILER='clang'",
-                "-DCMAKE_CXX_COMPILER='clang++'",
-                format!(
-                    "-DLLVM_TARGETS_TO_BUILD='{}'",
-                    targets
-                        .into_iter()
-                        .map(|platform| platform.to_string())
-                        .collect::<Vec<String>>()
-                        .join(";")
-                )
-                .as_str(),
-                format!(
-                    "-DLLVM_ENABLE_PROJECTS='{}'",
-                    llvm_projects
-                        .into_iter()
-                        .map(|project| project.to_string())
-                        .collect::<Vec<String>>()
-                        .join(";")
-                )
-                .as_str(),
-                "-DLLVM_USE_LINKER='lld'",
-            ])
-            .args(crate::platforms::shared::shared_build_opts_default_target(
-                default_target,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_tests(
-                enable_tests,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_coverage(
-                enable_coverage,
-            ))
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS)
-            .args(crate::platforms::shared::SHARED_BUILD_OPTS_NOT_MUSL)
-            .args(crate::platforms::shared::shared_build_opts_werror())
-            .args(extra_args)
-            .args(crate::platforms::shared::shared_build_opts_ccache(
-                ccache_variant,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_assertions(
-                enable_assertions,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_rtti(
-                enable_rtti,
-            ))
-            .args(crate::platforms::shared::shared_build_opts_sanitizers(
-                sanitizer,
-            )),
-        "LLVM building cmake",
-    )?;
-
-    crate::utils::ninja(llvm_build_final.as_ref())?;
+pub fn plan(options: &BuildOptions, paths: &LLVMPath) -> anyhow::Result<Plan> {
+    let config = cmake_config(options, paths)?;
 
     let libstdcpp_source_path = match std::env::var("LIBSTDCPP_SOURCE_PATH") {
         Ok(libstdcpp_source_path) => PathBuf::from(libstdcpp_source_path),
@@ -117,13 +29,118 @@ pub fn build(
             "The `LIBSTDCPP_SOURCE_PATH` must be set to the path to the libstdc++.a static library: {error}"
         ),
     };
-    let mut libstdcpp_destination_path = llvm_target_final;
+    let mut libstdcpp_destination_path =
+        crate::utils::path_windows_to_unix(paths.llvm_target_final())?;
     libstdcpp_destination_path.push("./lib/libstdc++.a");
-    fs_extra::file::copy(
-        crate::utils::path_windows_to_unix(libstdcpp_source_path)?,
-        crate::utils::path_windows_to_unix(libstdcpp_destination_path)?,
-        &fs_extra::file::CopyOptions::default(),
-    )?;
 
-    Ok(())
+    Ok(
+        Plan::new(&["cmake", "clang", "clang++", "lld", "ninja"]).stage(
+            Stage::FINAL,
+            Some(config.source_directory.as_path()),
+            vec![
+                Step::Command(config.to_step("LLVM building cmake")),
+                Step::Command(crate::utils::ninja(config.build_directory.as_path())),
+                Step::CopyFile {
+                    source: crate::utils::path_windows_to_unix(libstdcpp_source_path)?,
+                    destination: crate::utils::path_windows_to_unix(libstdcpp_destination_path)?,
+                    optional: false,
+                },
+            ],
+        ),
+    )
+}
+
+///
+/// The building plan of cross-compiling on an x86_64 Linux builder.
+///
+/// The native table generators are built first, and the final build uses the builder
+/// clang with the mingw-w64 sysroot, which is `/usr/x86_64-w64-mingw32` by default and
+/// can be overridden with `MINGW_SYSROOT`. The static C++ library is taken from
+/// `LIBSTDCPP_SOURCE_PATH` if set, or located in the sysroot otherwise. The llvm-mingw
+/// sysroots have no libstdc++, so `LIBSTDCPP_SOURCE_PATH` is required with them.
+///
+pub fn cross_plan(options: &BuildOptions, paths: &LLVMPath) -> anyhow::Result<Plan> {
+    let host = Host::new(Arch::X86_64, OS::Windows, TargetEnv::GNU);
+    let sysroot = std::env::var("MINGW_SYSROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(crate::utils::X86_64_W64_MINGW32_SYSROOT));
+    let libstdcpp_source_path = match std::env::var("LIBSTDCPP_SOURCE_PATH") {
+        Ok(libstdcpp_source_path) => PathBuf::from(libstdcpp_source_path),
+        Err(_) => crate::utils::find_mingw_libstdcpp(sysroot.as_path(), host.triple().as_str())?,
+    };
+
+    let llvm_module_llvm = paths.llvm_module_llvm();
+    let llvm_build_tblgen = paths.llvm_build_tblgen();
+    let toolchain_file = paths.cmake_toolchain_file(host.to_string().as_str());
+    let mut libstdcpp_destination_path = paths.llvm_target_final();
+    libstdcpp_destination_path.push("./lib/libstdc++.a");
+
+    let mut config = cmake_config(options, paths)?;
+    crate::platforms::shared::shared_build_opts_cross(
+        &mut config,
+        options,
+        host,
+        toolchain_file.as_path(),
+        llvm_build_tblgen.as_path(),
+    );
+
+    Ok(Plan::new(&["cmake", "clang", "clang++", "lld", "ninja"])
+        .stage(
+            Stage::TBLGEN,
+            Some(llvm_module_llvm.as_path()),
+            crate::platforms::shared::build_tblgen(
+                options,
+                llvm_module_llvm.as_path(),
+                llvm_build_tblgen.as_path(),
+            ),
+        )
+        .stage(
+            Stage::FINAL,
+            Some(config.source_directory.as_path()),
+            vec![
+                Step::WriteFile {
+                    path: toolchain_file,
+                    content: crate::platforms::shared::cross_toolchain_file(
+                        host,
+                        Path::new("clang"),
+                        Path::new("clang++"),
+                        Some(sysroot.as_path()),
+                        sysroot.as_path(),
+                        false,
+                    ),
+                },
+                Step::Command(config.to_step("LLVM building cmake")),
+                Step::Command(crate::utils::ninja(config.build_directory.as_path())),
+                Step::CopyFile {
+                    source: libstdcpp_source_path,
+                    destination: libstdcpp_destination_path,
+                    optional: false,
+                },
+            ],
+        ))
+}
+
+///
+/// Returns the CMake configuration of the final build.
+///
+pub fn cmake_config(options: &BuildOptions, paths: &LLVMPath) -> anyhow::Result<CMakeConfig> {
+    let llvm_module_llvm = crate::utils::path_windows_to_unix(paths.llvm_module_llvm())?;
+    let llvm_build_final = crate::utils::path_windows_to_unix(paths.llvm_build_final())?;
+    let llvm_target_final = crate::utils::path_windows_to_unix(paths.llvm_target_final())?;
+
+    let mut config = crate::platforms::shared::final_config(
+        options,
+        llvm_module_llvm.as_path(),
+        llvm_build_final.as_path(),
+        llvm_target_final.as_path(),
+    );
+    config
+        .set_file_path("CMAKE_C_COMPILER", "clang", CMakeOrigin::Platform)
+        .set_file_path("CMAKE_CXX_COMPILER", "clang++", CMakeOrigin::Platform)
+        .set_string("LLVM_USE_LINKER", "lld", CMakeOrigin::Platform)
+        // Disabled due to the following upstream issue with MSYS2 with mingw-w64:
+        // ProgramTest.cpp:23:15: error: '__p__environ' redeclared without 'dllimport' attribute
+        .set_bool("LLVM_ENABLE_WERROR", false, CMakeOrigin::Platform);
+    crate::platforms::shared::final_config_options(&mut config, options);
+    Ok(config)
 }
@@ -0,0 +1,98 @@
+//!
+//! The build report.
+//!
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::plan::CommandStep;
+use crate::runner::CommandRunner;
+
+///
+/// The build report.
+///
+/// Written to the target directory after a successful build, so the artifacts can be traced
+/// back to the exact LLVM source state, including local changes.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildReport {
+    /// The LLVM source directory.
+    pub source: PathBuf,
+    /// The LLVM source `HEAD` commit, if the source is a Git repository.
+    pub head: Option<String>,
+    /// Whether the LLVM source has uncommitted changes or untracked files.
+    pub dirty: bool,
+    /// The host the toolchain is built for.
+    pub host: String,
+    /// The build completion timestamp.
+    pub completed: String,
+}
+
+impl BuildReport {
+    /// The report file name in the target directory.
+    pub const FILE_NAME: &'static str = "build-report.json";
+
+    ///
+    /// Reads the LLVM source repository state.
+    ///
+    /// The repository state is read before building, so the changes made during the build
+    /// are not attributed to the artifacts.
+    ///
+    pub fn new(source: &Path, host: String, runner: &mut dyn CommandRunner) -> Self {
+        let head = runner
+            .output(
+                &CommandStep::new("git", "LLVM repository HEAD reading")
+                    .args(["rev-parse", "HEAD"])
+                    .current_dir(source),
+            )
+            .ok()
+            .map(|output| output.trim().to_owned())
+            .filter(|head| !head.is_empty());
+        let dirty = runner
+            .output(
+                &CommandStep::new("git", "LLVM repository status reading")
+                    .args(["status", "--porcelain"])
+                    .current_dir(source),
+            )
+            .is_ok_and(|output| !output.trim().is_empty());
+
+        Self {
+            source: source.to_path_buf(),
+            head,
+            dirty,
+            host,
+            completed: String::new(),
+        }
+    }
+
+    ///
+    /// Records the completion time and saves the report.
+    ///
+    pub fn save(mut self, path: &Path) -> anyhow::Result<()> {
+        self.completed = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self)?)
+            .map_err(|error| anyhow::anyhow!("Build report {path:?} writing: {error}"))
+    }
+}
+
+impl std::fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source.display())?;
+        match self.head.as_deref() {
+            Some(head) => write!(f, " at {head}")?,
+            None => write!(f, " (not a Git repository)")?,
+        }
+        if self.dirty {
+            write!(f, " with uncommitted changes")?;
+        }
+        Ok(())
+    }
+}
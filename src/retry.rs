@@ -0,0 +1,110 @@
+//!
+//! The download retry policy.
+//!
+
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+///
+/// The retry policy with exponential backoff, jitter, and a total deadline.
+///
+/// Set in the `[download]` table of `zksync-llvm.toml`:
+///
+/// ```toml
+/// [download]
+/// max-attempts = 5
+/// initial-backoff = "2s"
+/// max-backoff = "1m"
+/// deadline = "10m"
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the second attempt, doubled after every failure.
+    #[serde(with = "humantime_serde")]
+    pub initial_backoff: Duration,
+    /// The delay limit.
+    #[serde(with = "humantime_serde")]
+    pub max_backoff: Duration,
+    /// The total time limit of all the attempts and delays.
+    #[serde(with = "humantime_serde")]
+    pub deadline: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            deadline: Duration::from_secs(600),
+        }
+    }
+}
+
+impl RetryPolicy {
+    ///
+    /// Runs the operation until it succeeds, the attempts are exhausted, or the next attempt
+    /// would start after the deadline.
+    ///
+    /// The operation receives the deadline, so it can limit its own waiting. The error lists
+    /// the failures of all the attempts.
+    ///
+    pub fn run<T, F>(&self, description: &str, mut operation: F) -> anyhow::Result<T>
+    where
+        F: FnMut(Instant) -> anyhow::Result<T>,
+    {
+        let started = Instant::now();
+        let deadline = started + self.deadline;
+        let mut failures = Vec::new();
+        let mut backoff = self.initial_backoff;
+        let mut attempts = 0;
+        for attempt in 1..=self.max_attempts.max(1) {
+            attempts = attempt;
+            let error = match operation(deadline) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            eprintln!(
+                "{description} attempt {attempt}/{} failed: {error}",
+                self.max_attempts
+            );
+            failures.push(format!("attempt {attempt}: {error}"));
+            if attempt >= self.max_attempts {
+                break;
+            }
+
+            let delay = Self::jitter(backoff);
+            if started.elapsed() + delay > self.deadline {
+                failures.push(format!(
+                    "the deadline of {} would be exceeded by the next attempt",
+                    humantime::format_duration(self.deadline)
+                ));
+                break;
+            }
+            std::thread::sleep(delay);
+            backoff = backoff.saturating_mul(2).min(self.max_backoff);
+        }
+
+        anyhow::bail!(
+            "{description} failed after {attempts} attempt(s) in {}:\n    {}",
+            humantime::format_duration(Duration::from_millis(started.elapsed().as_millis() as u64)),
+            failures.join("\n    ")
+        )
+    }
+
+    ///
+    /// Returns a random delay between the half and the whole of the backoff, so the clients
+    /// failed at the same time do not retry at the same time.
+    ///
+    fn jitter(backoff: Duration) -> Duration {
+        let half = backoff / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
@@ -0,0 +1,24 @@
+//!
+//! The subprocess runner printing the commands instead of running them.
+//!
+
+use crate::plan::CommandStep;
+use crate::runner::CommandRunner;
+
+///
+/// The subprocess runner printing the commands instead of running them.
+///
+#[derive(Debug, Default, Clone)]
+pub struct DryRunRunner {}
+
+impl CommandRunner for DryRunRunner {
+    fn run(&mut self, command: &CommandStep) -> anyhow::Result<()> {
+        println!("\ndescription: {}; command: {command}", command.description);
+        println!("\tOnly a dry run; not executing the command.");
+        Ok(())
+    }
+
+    fn applies_side_effects(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,61 @@
+//!
+//! The subprocess error.
+//!
+
+use std::path::PathBuf;
+
+///
+/// The subprocess error.
+///
+/// Carries everything needed to understand the failure without scrolling the build log.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    /// The human-readable description of the command.
+    pub description: String,
+    /// The command rendered as a shell command line.
+    pub command: String,
+    /// The working directory.
+    pub current_directory: Option<PathBuf>,
+    /// The exit code, if the process has exited normally.
+    pub exit_code: Option<i32>,
+    /// The terminating signal, if the process has been killed.
+    pub signal: Option<i32>,
+    /// The last lines of `stdout`.
+    pub stdout_tail: Vec<String>,
+    /// The last lines of `stderr`.
+    pub stderr_tail: Vec<String>,
+    /// The log file with the full output.
+    pub log_path: Option<PathBuf>,
+}
+
+impl std::error::Error for CommandError {}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} failed", self.description)?;
+        match (self.exit_code, self.signal) {
+            (Some(exit_code), _) => write!(f, " with exit code {exit_code}")?,
+            (None, Some(signal)) => write!(f, " terminated by signal {signal}")?,
+            (None, None) => {}
+        }
+        writeln!(f)?;
+        writeln!(f, "  command: {}", self.command)?;
+        if let Some(current_directory) = self.current_directory.as_ref() {
+            writeln!(f, "  directory: {}", current_directory.display())?;
+        }
+        for (name, tail) in [("stdout", &self.stdout_tail), ("stderr", &self.stderr_tail)] {
+            if tail.is_empty() {
+                continue;
+            }
+            writeln!(f, "  {name} (last {} lines):", tail.len())?;
+            for line in tail.iter() {
+                writeln!(f, "    | {line}")?;
+            }
+        }
+        if let Some(log_path) = self.log_path.as_ref() {
+            writeln!(f, "  log: {}", log_path.display())?;
+        }
+        Ok(())
+    }
+}
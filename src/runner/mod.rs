@@ -0,0 +1,75 @@
+//!
+//! The subprocess runners.
+//!
+
+pub mod dry_run;
+pub mod error;
+pub mod real;
+pub mod recording;
+pub mod replay;
+
+pub use self::dry_run::DryRunRunner;
+pub use self::error::CommandError;
+pub use self::real::RealRunner;
+pub use self::recording::RecordingRunner;
+pub use self::replay::ReplayRunner;
+
+use crate::cache::Cache;
+use crate::mirrors::Mirrors;
+use crate::plan::CommandStep;
+use crate::retry::RetryPolicy;
+
+///
+/// The subprocess runner.
+///
+/// Every `git`, `cmake`, `ninja`, `make`, and musl `configure` invocation goes through the
+/// runner injected by the caller, so library users can intercept, log, sandbox, or mock them.
+///
+pub trait CommandRunner {
+    ///
+    /// Runs the command, failing if it does not succeed.
+    ///
+    /// The failed processes are reported with `CommandError`.
+    ///
+    fn run(&mut self, command: &CommandStep) -> anyhow::Result<()>;
+
+    ///
+    /// Runs the command and returns its `stdout`, failing if it does not succeed.
+    ///
+    /// Used to query the environment, e.g. the tool versions, so the output is not logged.
+    /// Runners that do not spawn processes return an empty string.
+    ///
+    fn output(&mut self, _command: &CommandStep) -> anyhow::Result<String> {
+        Ok(String::new())
+    }
+
+    ///
+    /// Whether the steps other than commands, e.g. downloads and copies, must be applied.
+    ///
+    /// Runners that do not spawn processes return `false`, so the file system is left intact.
+    ///
+    fn applies_side_effects(&self) -> bool {
+        true
+    }
+
+    ///
+    /// The retry policy of the downloads made by the steps.
+    ///
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    ///
+    /// The local source cache checked before the network access, if any.
+    ///
+    fn cache(&self) -> Option<&Cache> {
+        None
+    }
+
+    ///
+    /// The mirrors and rewrite rules of the upstream sources, if any.
+    ///
+    fn mirrors(&self) -> Option<&Mirrors> {
+        None
+    }
+}
@@ -0,0 +1,308 @@
+//!
+//! The subprocess runner spawning the processes.
+//!
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use crate::cache::Cache;
+use crate::mirrors::Mirrors;
+use crate::plan::CommandStep;
+use crate::retry::RetryPolicy;
+use crate::runner::CommandError;
+use crate::runner::CommandRunner;
+
+///
+/// The subprocess runner spawning the processes.
+///
+/// Streams `stdout` and `stderr` through while keeping their last lines for the error.
+/// If the log directory is set, every command also writes its output to a numbered log file,
+/// e.g. `03-llvm-host-building-cmake.log`, with the start and finish timestamps.
+///
+#[derive(Debug, Clone)]
+pub struct RealRunner {
+    /// Whether to print every command before running it.
+    pub verbose: bool,
+    /// The number of the last `stdout` and `stderr` lines kept for the error.
+    pub tail_lines: usize,
+    /// The directory to write the per-command log files to.
+    pub log_directory: Option<PathBuf>,
+    /// The number of commands run so far, used to number the log files.
+    pub commands_run: usize,
+    /// The retry policy of the downloads.
+    pub retry_policy: RetryPolicy,
+    /// The local source cache.
+    pub cache: Option<Cache>,
+    /// The mirrors of the upstream sources.
+    pub mirrors: Option<Mirrors>,
+}
+
+impl Default for RealRunner {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl RealRunner {
+    /// The default number of the output lines kept for the error.
+    pub const TAIL_LINES_DEFAULT: usize = 20;
+
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            tail_lines: Self::TAIL_LINES_DEFAULT,
+            log_directory: None,
+            commands_run: 0,
+            retry_policy: RetryPolicy::default(),
+            cache: None,
+            mirrors: None,
+        }
+    }
+
+    ///
+    /// Sets the retry policy of the downloads.
+    ///
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    ///
+    /// Sets the local source cache.
+    ///
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    ///
+    /// Sets the mirrors of the upstream sources.
+    ///
+    pub fn mirrors(mut self, mirrors: Mirrors) -> Self {
+        self.mirrors = Some(mirrors);
+        self
+    }
+
+    ///
+    /// Sets the directory to write the per-command log files to.
+    ///
+    pub fn log_directory(mut self, path: &Path) -> Self {
+        self.log_directory = Some(path.to_path_buf());
+        self
+    }
+
+    ///
+    /// Creates the log file of the next command and writes its header.
+    ///
+    fn open_log(&mut self, command: &CommandStep) -> anyhow::Result<Option<(PathBuf, File)>> {
+        self.commands_run += 1;
+        let Some(log_directory) = self.log_directory.as_ref() else {
+            return Ok(None);
+        };
+
+        std::fs::create_dir_all(log_directory)?;
+        let path = log_directory.join(format!(
+            "{:02}-{}.log",
+            self.commands_run,
+            log_name(command.description.as_str())
+        ));
+        let mut file = File::create(path.as_path())
+            .map_err(|error| anyhow::anyhow!("Log file {path:?} creating: {error}"))?;
+        writeln!(file, "# description: {}", command.description)?;
+        writeln!(file, "# command: {}", command.command_line())?;
+        if let Some(current_directory) = command.current_directory.as_ref() {
+            writeln!(file, "# directory: {}", current_directory.display())?;
+        }
+        writeln!(
+            file,
+            "# started: {}",
+            humantime::format_rfc3339_seconds(SystemTime::now())
+        )?;
+        Ok(Some((path, file)))
+    }
+}
+
+impl CommandRunner for RealRunner {
+    fn run(&mut self, command: &CommandStep) -> anyhow::Result<()> {
+        let description = command.description.as_str();
+        if self.verbose {
+            println!("\ndescription: {description}; command: {command}");
+        }
+        let (log_path, log) = match self.open_log(command)? {
+            Some((path, file)) => (Some(path), Some(Arc::new(Mutex::new(file)))),
+            None => (None, None),
+        };
+
+        let started = Instant::now();
+        let mut child = command
+            .to_command()
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| anyhow::anyhow!("{description} process: {error}"))?;
+
+        let tail_lines = self.tail_lines;
+        let stdout = child.stdout.take().expect("Always piped");
+        let stdout_log = log.clone();
+        let stdout_thread =
+            std::thread::spawn(move || tee(stdout, std::io::stdout(), stdout_log, tail_lines));
+        let stderr = child.stderr.take().expect("Always piped");
+        let stderr_log = log.clone();
+        let stderr_thread =
+            std::thread::spawn(move || tee(stderr, std::io::stderr(), stderr_log, tail_lines));
+
+        let status = child
+            .wait()
+            .map_err(|error| anyhow::anyhow!("{description} process: {error}"))?;
+        let stdout_tail = stdout_thread.join().expect("Output thread panicked")?;
+        let stderr_tail = stderr_thread.join().expect("Output thread panicked")?;
+
+        if let Some(log) = log {
+            let mut file = log.lock().expect("Sync");
+            writeln!(
+                file,
+                "# finished: {}",
+                humantime::format_rfc3339_seconds(SystemTime::now())
+            )?;
+            writeln!(
+                file,
+                "# duration: {}",
+                humantime::format_duration(std::time::Duration::from_secs(
+                    started.elapsed().as_secs()
+                ))
+            )?;
+            writeln!(file, "# status: {status}")?;
+        }
+
+        if !status.success() {
+            return Err(CommandError {
+                description: description.to_owned(),
+                command: command.command_line(),
+                current_directory: command.current_directory.clone(),
+                exit_code: status.code(),
+                signal: signal(status),
+                stdout_tail,
+                stderr_tail,
+                log_path,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn output(&mut self, command: &CommandStep) -> anyhow::Result<String> {
+        let description = command.description.as_str();
+        let output = command
+            .to_command()
+            .output()
+            .map_err(|error| anyhow::anyhow!("{description} process: {error}"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(output.stderr.as_slice());
+            let stderr_lines: Vec<&str> = stderr.lines().collect();
+            return Err(CommandError {
+                description: description.to_owned(),
+                command: command.command_line(),
+                current_directory: command.current_directory.clone(),
+                exit_code: output.status.code(),
+                signal: signal(output.status),
+                stdout_tail: vec![],
+                stderr_tail: stderr_lines[stderr_lines.len().saturating_sub(self.tail_lines)..]
+                    .iter()
+                    .map(|line| (*line).to_owned())
+                    .collect(),
+                log_path: None,
+            }
+            .into());
+        }
+        Ok(String::from_utf8_lossy(output.stdout.as_slice()).into_owned())
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    fn mirrors(&self) -> Option<&Mirrors> {
+        self.mirrors.as_ref()
+    }
+}
+
+///
+/// Copies the process output to the sink and the log file, returning its last lines.
+///
+fn tee(
+    source: impl Read,
+    mut sink: impl Write,
+    log: Option<Arc<Mutex<File>>>,
+    tail_lines: usize,
+) -> std::io::Result<Vec<String>> {
+    let mut reader = std::io::BufReader::new(source);
+    let mut tail = VecDeque::with_capacity(tail_lines + 1);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        sink.write_all(line.as_slice())?;
+        sink.flush()?;
+        if let Some(log) = log.as_ref() {
+            log.lock().expect("Sync").write_all(line.as_slice())?;
+        }
+
+        tail.push_back(
+            String::from_utf8_lossy(line.as_slice())
+                .trim_end()
+                .to_owned(),
+        );
+        if tail.len() > tail_lines {
+            tail.pop_front();
+        }
+    }
+    Ok(tail.into())
+}
+
+///
+/// Converts the command description into a log file name, e.g. `llvm-host-building-cmake`.
+///
+fn log_name(description: &str) -> String {
+    description
+        .split(|character: char| !character.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+///
+/// Returns the signal that terminated the process.
+///
+#[cfg(unix)]
+fn signal(status: ExitStatus) -> Option<i32> {
+    std::os::unix::process::ExitStatusExt::signal(&status)
+}
+
+///
+/// Returns the signal that terminated the process.
+///
+#[cfg(not(unix))]
+fn signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
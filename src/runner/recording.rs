@@ -0,0 +1,141 @@
+//!
+//! The subprocess runner recording the commands.
+//!
+
+use std::path::Path;
+
+use crate::cache::Cache;
+use crate::mirrors::Mirrors;
+use crate::plan::CommandStep;
+use crate::runner::CommandRunner;
+
+///
+/// The subprocess runner recording the commands instead of running them.
+///
+/// The recording can be saved and checked later with the `ReplayRunner`. The outputs and
+/// failures of the commands can be scripted to simulate the servers and repositories.
+///
+#[derive(Debug, Default, Clone)]
+pub struct RecordingRunner {
+    /// The recorded commands in the order they were run.
+    pub commands: Vec<CommandStep>,
+    /// The scripted results checked in the order they were added.
+    scripts: Vec<Script>,
+    /// Whether the steps other than commands are applied.
+    side_effects: bool,
+    /// The mirrors of the upstream sources.
+    mirrors: Option<Mirrors>,
+    /// The local source cache.
+    cache: Option<Cache>,
+}
+
+///
+/// The scripted result of the commands containing all the arguments.
+///
+#[derive(Debug, Clone)]
+struct Script {
+    /// The arguments the command must contain.
+    args: Vec<String>,
+    /// The output of the command, or the error message if it fails.
+    result: Result<String, String>,
+}
+
+impl RecordingRunner {
+    ///
+    /// Makes the commands containing all the arguments fail with the message.
+    ///
+    pub fn fail_on(mut self, args: &[&str], message: &str) -> Self {
+        self.scripts.push(Script {
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+            result: Err(message.to_owned()),
+        });
+        self
+    }
+
+    ///
+    /// Makes the commands containing all the arguments return the output.
+    ///
+    /// An empty list of arguments matches all the commands.
+    ///
+    pub fn output_on(mut self, args: &[&str], output: &str) -> Self {
+        self.scripts.push(Script {
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+            result: Ok(output.to_owned()),
+        });
+        self
+    }
+
+    ///
+    /// Makes the runner apply the steps other than commands, e.g. to write the journal.
+    ///
+    pub fn side_effects(mut self, enabled: bool) -> Self {
+        self.side_effects = enabled;
+        self
+    }
+
+    ///
+    /// Sets the mirrors of the upstream sources.
+    ///
+    pub fn mirrors(mut self, mirrors: Mirrors) -> Self {
+        self.mirrors = Some(mirrors);
+        self
+    }
+
+    ///
+    /// Sets the local source cache.
+    ///
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    ///
+    /// Saves the recorded commands as JSON.
+    ///
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let recording = serde_json::to_string_pretty(&self.commands)?;
+        std::fs::write(path, recording)
+            .map_err(|error| anyhow::anyhow!("Recording {path:?} writing: {error}"))?;
+        Ok(())
+    }
+
+    ///
+    /// Returns the scripted result of the command, if any.
+    ///
+    fn scripted(&self, command: &CommandStep) -> Option<&Result<String, String>> {
+        self.scripts
+            .iter()
+            .find(|script| script.args.iter().all(|arg| command.args.contains(arg)))
+            .map(|script| &script.result)
+    }
+}
+
+impl CommandRunner for RecordingRunner {
+    fn run(&mut self, command: &CommandStep) -> anyhow::Result<()> {
+        self.commands.push(command.to_owned());
+        match self.scripted(command) {
+            Some(Err(message)) => anyhow::bail!("{message}"),
+            _ => Ok(()),
+        }
+    }
+
+    fn output(&mut self, command: &CommandStep) -> anyhow::Result<String> {
+        match self.scripted(command) {
+            Some(Ok(output)) => Ok(output.to_owned()),
+            Some(Err(message)) => anyhow::bail!("{message}"),
+            None => Ok(String::new()),
+        }
+    }
+
+    fn applies_side_effects(&self) -> bool {
+        self.side_effects
+    }
+
+    fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    fn mirrors(&self) -> Option<&Mirrors> {
+        self.mirrors.as_ref()
+    }
+}
@@ -0,0 +1,76 @@
+//!
+//! The subprocess runner replaying the recorded commands.
+//!
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use crate::plan::CommandStep;
+use crate::runner::CommandRunner;
+
+///
+/// The subprocess runner replaying the recorded commands.
+///
+/// Instead of running the commands, checks that they match the recording in order,
+/// which allows testing the command sequences without any tools installed.
+///
+#[derive(Debug, Default, Clone)]
+pub struct ReplayRunner {
+    /// The commands that have not been replayed yet.
+    pub expected: VecDeque<CommandStep>,
+}
+
+impl ReplayRunner {
+    ///
+    /// A shortcut constructor.
+    ///
+    pub fn new(commands: Vec<CommandStep>) -> Self {
+        Self {
+            expected: commands.into(),
+        }
+    }
+
+    ///
+    /// Loads the recording saved by the `RecordingRunner`.
+    ///
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let recording = std::fs::read_to_string(path)
+            .map_err(|error| anyhow::anyhow!("Recording {path:?} reading: {error}"))?;
+        let commands: Vec<CommandStep> = serde_json::from_str(recording.as_str())
+            .map_err(|error| anyhow::anyhow!("Recording {path:?} parsing: {error}"))?;
+        Ok(Self::new(commands))
+    }
+
+    ///
+    /// Checks that all the recorded commands have been replayed.
+    ///
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if let Some(command) = self.expected.front() {
+            anyhow::bail!(
+                "{} recorded command(s) have not been run, starting with `{command}`",
+                self.expected.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl CommandRunner for ReplayRunner {
+    fn run(&mut self, command: &CommandStep) -> anyhow::Result<()> {
+        match self.expected.pop_front() {
+            Some(expected) if &expected == command => Ok(()),
+            Some(expected) => anyhow::bail!(
+                "{} does not match the recording:\n  expected: `{expected}`\n  actual:   `{command}`",
+                command.description
+            ),
+            None => anyhow::bail!(
+                "{} is not in the recording: `{command}`",
+                command.description
+            ),
+        }
+    }
+
+    fn applies_side_effects(&self) -> bool {
+        false
+    }
+}
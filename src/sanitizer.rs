@@ -2,10 +2,13 @@
 //! LLVM sanitizers.
 //!
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// LLVM sanitizers.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Sanitizer {
     /// The address sanitizer.
     Address,
@@ -20,6 +23,7 @@ pub enum Sanitizer {
     /// The data flow sanitizer.
     DataFlow,
     /// Combine address and undefined behavior sanitizer.
+    #[serde(rename = "Address;Undefined")]
     AddressUndefined,
 }
 
@@ -2,10 +2,14 @@
 //! The target environments to build LLVM.
 //!
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// The list of target environments used as constants.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TargetEnv {
     /// The GNU target environment.
     GNU,
@@ -2,12 +2,16 @@
 //! The ZKsync LLVM target triples.
 //!
 
+use serde::Deserialize;
+use serde::Serialize;
+
 ///
 /// The list of target triples used as constants.
 ///
 /// It must be in the lowercase.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TargetTriple {
     /// The EraVM back end developed by Matter Labs.
     EraVM,
@@ -8,8 +8,16 @@ use std::path::PathBuf;
 use std::process::Command;
 use std::process::Stdio;
 use std::time::Duration;
+use std::time::Instant;
 
 use path_slash::PathBufExt;
+use sha2::Digest;
+
+use crate::cache::Cache;
+use crate::mirrors::Mirrors;
+use crate::plan::CommandStep;
+use crate::plan::Step;
+use crate::retry::RetryPolicy;
 
 /// The LLVM host repository URL.
 pub const LLVM_HOST_SOURCE_URL: &str = "https://github.com/llvm/llvm-project";
@@ -23,52 +31,133 @@ pub const XCODE_MIN_VERSION: u32 = 11;
 /// The XCode version 15.
 pub const XCODE_VERSION_15: u32 = 15;
 
-/// The number of download retries if failed.
-pub const DOWNLOAD_RETRIES: u16 = 16;
-
 /// The number of parallel download requests.
 pub const DOWNLOAD_PARALLEL_REQUESTS: u16 = 1;
 
 /// The download timeout in seconds.
 pub const DOWNLOAD_TIMEOUT_SECONDS: u64 = 300;
 
-/// The musl snapshots URL.
-pub const MUSL_SNAPSHOTS_URL: &str = "https://git.musl-libc.org/cgit/musl/snapshot";
+/// The lock branch history depths fetched in turn if fetching the commit is refused.
+pub const FETCH_DEPTHS: [u32; 3] = [100, 1_000, 10_000];
+
+/// The musl releases URL.
+///
+/// The release tarballs are used instead of the cgit snapshots, which are generated on
+/// request and are not guaranteed to be byte-for-byte stable.
+pub const MUSL_RELEASES_URL: &str = "https://musl.libc.org/releases";
+
+/// The Linux kernel releases URL.
+pub const LINUX_RELEASES_URL: &str = "https://cdn.kernel.org/pub/linux/kernel";
+
+/// The sysroot of the Debian and Ubuntu `aarch64-linux-gnu` cross-compilation packages.
+pub const AARCH64_LINUX_GNU_SYSROOT: &str = "/usr/aarch64-linux-gnu";
+
+/// The sysroot of the Debian and Ubuntu `mingw-w64` cross-compilation packages.
+pub const X86_64_W64_MINGW32_SYSROOT: &str = "/usr/x86_64-w64-mingw32";
+
+/// The musl version.
+pub const MUSL_VERSION: &str = "1.2.3";
+
+/// The musl release tarball SHA-256.
+pub const MUSL_SHA256: &str = "7d5b0b6062521e4627e099e4c9dc8248d32a30285e959b7eecaa780cf8cfd4a4";
+
+/// The Linux kernel release the musl sysroot headers are installed from by default.
+pub const LINUX_VERSION: &str = "6.6.58";
 
 ///
-/// The subprocess runner.
+/// Downloads a file from the URL to the directory, unless it has already been downloaded,
+/// and verifies its SHA-256.
 ///
-/// Checks the status and prints `stderr`.
+/// The cache is checked first, and the verified file is stored there. In the offline mode,
+/// the file missing from the cache is an error. Every attempt tries the mirrors of the URL
+/// in order, and the failed attempts are retried according to the policy. The file with
+/// a wrong digest is removed, so it is downloaded again on the next run.
 ///
-pub fn command(command: &mut Command, description: &str) -> anyhow::Result<()> {
-    if std::env::var("VERBOSE").is_ok() {
-        println!("\ndescription: {description}; command: {command:?}");
-    }
-    if std::env::var("DRY_RUN").is_ok() {
-        println!("\tOnly a dry run; not executing the command.");
-    } else {
-        let status = command
-            .status()
-            .map_err(|error| anyhow::anyhow!("{description} process: {error}"))?;
-        if !status.success() {
-            anyhow::bail!("{description} failed");
+pub fn download(
+    url: &str,
+    destination: &Path,
+    file_name: &str,
+    sha256: &str,
+    retry_policy: &RetryPolicy,
+    cache: Option<&Cache>,
+    mirrors: Option<&Mirrors>,
+) -> anyhow::Result<()> {
+    let path = destination.join(file_name);
+    let cached = cache.map(|cache| cache.file(sha256));
+    if !path.exists() {
+        match (cached.as_deref(), cache) {
+            (Some(cached), _) if cached.exists() => {
+                std::fs::copy(cached, path.as_path())
+                    .map_err(|error| anyhow::anyhow!("Cache entry {cached:?} copying: {error}"))?;
+            }
+            (_, Some(cache)) if cache.offline => anyhow::bail!(
+                "`{file_name}` is not in the cache {:?}, and downloading it from `{url}` is disabled in the offline mode",
+                cache.root
+            ),
+            _ => {
+                let urls = match mirrors {
+                    Some(mirrors) => mirrors.candidates(url),
+                    None => vec![url.to_owned()],
+                };
+                let description = format!("Download of `{file_name}`");
+                let result = retry_policy.run(description.as_str(), |deadline| {
+                    crate::mirrors::try_each(urls.as_slice(), description.as_str(), |url| {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        if remaining.is_zero() {
+                            anyhow::bail!("the deadline is exceeded before trying `{url}`");
+                        }
+                        if path.exists() {
+                            std::fs::remove_file(path.as_path())?;
+                        }
+                        let mut downloader = downloader::Downloader::builder()
+                            .download_folder(destination)
+                            .parallel_requests(DOWNLOAD_PARALLEL_REQUESTS)
+                            .retries(1)
+                            .timeout(remaining.min(Duration::from_secs(DOWNLOAD_TIMEOUT_SECONDS)))
+                            .build()?;
+                        for result in downloader.download(&[downloader::Download::new(url)])? {
+                            result?;
+                        }
+                        Ok(())
+                    })
+                });
+                if result.is_err() && path.exists() {
+                    std::fs::remove_file(path.as_path())?;
+                }
+                result?;
+            }
         }
     }
+
+    if let Err(error) = verify_sha256(path.as_path(), sha256) {
+        std::fs::remove_file(path.as_path())?;
+        anyhow::bail!("Download from `{url}` is rejected: {error}");
+    }
+    if let Some(cache) = cache {
+        cache.store_file(path.as_path(), sha256)?;
+    }
     Ok(())
 }
 
 ///
-/// Download a file from the URL to the path.
+/// Computes the SHA-256 of a file.
+///
+pub fn sha256(path: &Path) -> anyhow::Result<String> {
+    let mut file =
+        File::open(path).map_err(|error| anyhow::anyhow!("File {path:?} opening: {error}"))?;
+    let mut hasher = sha2::Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|error| anyhow::anyhow!("File {path:?} reading: {error}"))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+///
+/// Checks that the file has the expected SHA-256.
 ///
-pub fn download(url: &str, path: &str) -> anyhow::Result<()> {
-    let mut downloader = downloader::Downloader::builder()
-        .download_folder(Path::new(path))
-        .parallel_requests(DOWNLOAD_PARALLEL_REQUESTS)
-        .retries(DOWNLOAD_RETRIES)
-        .timeout(Duration::from_secs(DOWNLOAD_TIMEOUT_SECONDS))
-        .build()?;
-    while let Err(error) = downloader.download(&[downloader::Download::new(url)]) {
-        eprintln!("MUSL download from `{url}` failed: {error}");
+pub fn verify_sha256(path: &Path, expected: &str) -> anyhow::Result<()> {
+    let actual = sha256(path)?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        anyhow::bail!("SHA-256 mismatch of {path:?}: expected {expected}, actual {actual}");
     }
     Ok(())
 }
@@ -85,26 +174,138 @@ pub fn unpack_tar(filename: PathBuf, path: &str) -> anyhow::Result<()> {
 }
 
 ///
-/// The `musl` downloading sequence.
+/// Copies the Linux kernel headers into the include directory, taking `asm` from the
+/// multiarch directory if the source has one.
 ///
-pub fn download_musl(name: &str) -> anyhow::Result<()> {
-    let tar_file_name = format!("{name}.tar.gz");
-    let url = format!("{MUSL_SNAPSHOTS_URL}/{tar_file_name}");
-    download(url.as_str(), crate::LLVMPath::DIRECTORY_LLVM_TARGET)?;
-    let musl_tarball = crate::LLVMPath::musl_source(tar_file_name.as_str())?;
-    unpack_tar(musl_tarball, crate::LLVMPath::DIRECTORY_LLVM_TARGET)?;
+pub fn copy_kernel_headers(
+    source: &Path,
+    multiarch: &str,
+    destination: &Path,
+) -> anyhow::Result<()> {
+    let multiarch_asm = source.join(multiarch).join("asm");
+    let asm = if multiarch_asm.is_dir() {
+        multiarch_asm
+    } else {
+        source.join("asm")
+    };
+    std::fs::create_dir_all(destination)?;
+    for (source, name) in [
+        (source.join("linux"), "linux"),
+        (source.join("asm-generic"), "asm-generic"),
+        (asm, "asm"),
+    ] {
+        if !source.is_dir() {
+            anyhow::bail!("The kernel headers directory {source:?} does not exist");
+        }
+        let copy_options = fs_extra::dir::CopyOptions {
+            overwrite: true,
+            content_only: true,
+            ..Default::default()
+        };
+        let destination = destination.join(name);
+        std::fs::create_dir_all(destination.as_path())?;
+        fs_extra::dir::copy(source.as_path(), destination.as_path(), &copy_options)
+            .map_err(|error| anyhow::anyhow!("Kernel headers {source:?} copying: {error}"))?;
+    }
     Ok(())
 }
 
-/// Call ninja to build the LLVM.
-pub fn ninja(build_dir: &Path) -> anyhow::Result<()> {
-    let mut ninja = Command::new("ninja");
-    ninja.args(["-C", build_dir.to_string_lossy().as_ref()]);
-    if std::env::var("DRY_RUN").is_ok() {
-        ninja.arg("-n");
+///
+/// The Linux kernel headers installation sequence.
+///
+/// The headers are installed from the pinned kernel release with `make headers_install`,
+/// which is [`LINUX_VERSION`] by default, or copied from the directory specified in the
+/// lock file, e.g. `/usr/include` of the build system.
+///
+pub fn install_kernel_headers(
+    musl: &crate::lock::MuslSource,
+    paths: &crate::LLVMPath,
+    arch: crate::host::Arch,
+) -> anyhow::Result<Vec<Step>> {
+    let multiarch = format!("{arch}-linux-gnu");
+    let destination = paths.musl_target().join("include");
+    let kernel_headers = musl.kernel_headers.clone().unwrap_or_default();
+
+    match (kernel_headers.version.as_deref(), kernel_headers.path.as_ref()) {
+        (None, Some(path)) => Ok(vec![Step::CopyKernelHeaders {
+            source: paths.root.join(path),
+            multiarch,
+            destination,
+        }]),
+        (version, None) => {
+            let version = version.unwrap_or(LINUX_VERSION);
+            let name = crate::lock::KernelHeaders::name(version);
+            let file_name = format!("{name}.tar.gz");
+            let target_directory = paths.llvm_target();
+            let source_directory = target_directory.join(name.as_str());
+            let install_directory = source_directory.join("install");
+            let kernel_arch = match arch {
+                crate::host::Arch::X86_64 => "x86",
+                crate::host::Arch::AArch64 => "arm64",
+            };
+            Ok(vec![
+                Step::Download {
+                    url: kernel_headers.tarball_url(version),
+                    destination: target_directory.clone(),
+                    file_name: file_name.clone(),
+                    sha256: kernel_headers.sha256(version)?,
+                },
+                Step::Unpack {
+                    archive: target_directory.join(file_name),
+                    destination: target_directory,
+                },
+                Step::Command(
+                    CommandStep::new("make", "Linux kernel headers installing")
+                        .current_dir(source_directory.as_path())
+                        .arg(format!("ARCH={kernel_arch}"))
+                        .arg("HOSTCC=clang")
+                        .arg(format!(
+                            "INSTALL_HDR_PATH={}",
+                            install_directory.to_string_lossy()
+                        ))
+                        .arg("headers_install"),
+                ),
+                Step::CopyKernelHeaders {
+                    source: install_directory.join("include"),
+                    multiarch,
+                    destination,
+                },
+            ])
+        }
+        (Some(_), Some(_)) => anyhow::bail!(
+            "Specify either `version` or `path` in the `[musl.kernel-headers]` table of the lock file"
+        ),
     }
-    command(ninja.arg("install"), "Running ninja install")?;
-    Ok(())
+}
+
+///
+/// The `musl` downloading sequence.
+///
+pub fn download_musl(
+    musl: &crate::lock::MuslSource,
+    paths: &crate::LLVMPath,
+) -> anyhow::Result<Vec<Step>> {
+    let tar_file_name = musl.file_name();
+    let target_directory = paths.llvm_target();
+    Ok(vec![
+        Step::Download {
+            url: musl.tarball_url(),
+            destination: target_directory.clone(),
+            file_name: tar_file_name.clone(),
+            sha256: musl.sha256()?,
+        },
+        Step::Unpack {
+            archive: paths.musl_source(tar_file_name.as_str()),
+            destination: target_directory,
+        },
+    ])
+}
+
+/// Call ninja to build the LLVM.
+pub fn ninja(build_dir: &Path) -> CommandStep {
+    CommandStep::new("ninja", "Running ninja install")
+        .args(["-C", build_dir.to_string_lossy().as_ref()])
+        .arg("install")
 }
 
 ///
@@ -116,6 +317,53 @@ pub fn absolute_path<P: AsRef<Path>>(path: P) -> anyhow::Result<PathBuf> {
     Ok(full_path)
 }
 
+///
+/// Locates the static C++ library of the mingw-w64 sysroot.
+///
+/// The library is searched in the sysroot, and then in the GCC cross-compiler directories
+/// next to it, e.g. `/usr/lib/gcc/x86_64-w64-mingw32/12-posix/`, preferring the newest
+/// version with the POSIX threads. The llvm-mingw sysroots only have `libc++.a`, which is
+/// not ABI-compatible with `libstdc++.a`, so they are rejected.
+///
+pub fn find_mingw_libstdcpp(sysroot: &Path, triple: &str) -> anyhow::Result<PathBuf> {
+    let sysroot_libstdcpp = sysroot.join("lib/libstdc++.a");
+    if sysroot_libstdcpp.exists() {
+        return Ok(sysroot_libstdcpp);
+    }
+
+    let gcc_directory = sysroot.join("../lib/gcc").join(triple);
+    let mut candidates: Vec<(bool, Vec<u32>, PathBuf)> = std::fs::read_dir(gcc_directory.as_path())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path().join("libstdc++.a");
+            if !path.exists() {
+                return None;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let version = name
+                .split(|character: char| !character.is_ascii_digit())
+                .map_while(|part| part.parse::<u32>().ok())
+                .collect();
+            Some((!name.ends_with("-win32"), version, path))
+        })
+        .collect();
+    candidates.sort();
+    if let Some((_, _, path)) = candidates.pop() {
+        return Ok(path);
+    }
+
+    if sysroot.join("lib/libc++.a").exists() {
+        anyhow::bail!(
+            "The sysroot {sysroot:?} only has libc++.a, which cannot be used as libstdc++.a. Set `LIBSTDCPP_SOURCE_PATH` to the path to the libstdc++.a static library"
+        );
+    }
+    anyhow::bail!(
+        "The static C++ library is not found in {sysroot:?} and {gcc_directory:?}. Set `LIBSTDCPP_SOURCE_PATH` to the path to the libstdc++.a static library"
+    )
+}
+
 ///
 /// Converts a Windows path into a Unix path.
 ///
@@ -2,7 +2,12 @@
 //! The ZKsync LLVM builder arguments.
 //!
 
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::Args;
 use clap::Parser;
+use clap::Subcommand;
 use compiler_llvm_builder::ccache_variant::CcacheVariant;
 
 ///
@@ -10,7 +15,42 @@ use compiler_llvm_builder::ccache_variant::CcacheVariant;
 ///
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
-pub enum Arguments {
+pub struct Arguments {
+    /// Path to the `LLVM.lock` file.
+    /// Searched in the current directory and its parents by default.
+    /// The directory containing it is the workspace root.
+    #[arg(long, global = true)]
+    pub lock: Option<PathBuf>,
+
+    /// The workspace directories.
+    #[command(flatten)]
+    pub directories: DirectoryArguments,
+
+    /// The download retry policy.
+    #[command(flatten)]
+    pub download: DownloadArguments,
+
+    /// Disable the network access, taking the musl and host LLVM sources from the cache.
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// The source cache directory, checked before the network access.
+    /// Defaults to `~/.cache/zksync-llvm/` in the offline mode and for `fetch` and `cache`,
+    /// and is not used otherwise. The stage 1 host toolchains of the MUSL builds are shared
+    /// through the cache if the directory is set.
+    #[arg(long, global = true, env = "ZKSYNC_LLVM_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// The command to run.
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+///
+/// The ZKsync LLVM builder commands.
+///
+#[derive(Debug, Subcommand)]
+pub enum Command {
     /// Clone the branch specified in `LLVM.lock`.
     Clone {
         /// Clone with full commits history.
@@ -20,75 +60,318 @@ pub enum Arguments {
         /// Target environment to build LLVM (GNU or MUSL).
         #[arg(long, default_value_t = compiler_llvm_builder::target_env::TargetEnv::GNU)]
         target_env: compiler_llvm_builder::target_env::TargetEnv,
+
+        /// Host to clone the sources for (`<arch>-<os>[-<env>]`, e.g. `x86_64-linux-musl`).
+        /// Detected from the running system by default. The environment overrides `--target-env`.
+        #[arg(long)]
+        host: Option<compiler_llvm_builder::Host>,
     },
 
     /// Build the LLVM framework.
     Build {
-        /// LLVM build type (`Debug`, `Release`, `RelWithDebInfo`, or `MinSizeRel`).
-        #[arg(long, default_value_t = compiler_llvm_builder::BuildType::Release)]
-        build_type: compiler_llvm_builder::BuildType,
+        #[command(flatten)]
+        build: BuildArguments,
 
-        /// Target environment to build LLVM (`gnu` or `musl`).
-        #[arg(long, default_value = "gnu")]
-        target_env: compiler_llvm_builder::target_env::TargetEnv,
+        /// Skip the stages before this one and force it with all the following ones
+        /// (`musl`, `crt`, `host`, `tblgen`, or `final`).
+        #[arg(long, conflicts_with = "only_stage")]
+        from_stage: Option<String>,
 
-        /// Additional targets to build LLVM with.
+        /// Force only this stage, skipping all the others.
         #[arg(long)]
-        targets: Vec<String>,
+        only_stage: Option<String>,
+    },
 
-        /// LLVM projects to build LLVM with.
-        #[arg(long)]
-        llvm_projects: Vec<compiler_llvm_builder::llvm_project::LLVMProject>,
+    /// Print the LLVM framework building plan without running it.
+    Plan {
+        #[command(flatten)]
+        build: BuildArguments,
 
-        /// Whether to build LLVM with run-time type information (RTTI) enabled.
-        #[arg(long)]
-        enable_rtti: bool,
+        /// The plan output format (`json` or `shell`).
+        #[arg(long, default_value_t = compiler_llvm_builder::plan::Format::Json)]
+        format: compiler_llvm_builder::plan::Format,
+    },
 
-        /// The default target to build LLVM with.
+    /// Checkout the branch specified in `LLVM.lock`.
+    Checkout {
+        /// Remove all artifacts preventing the checkout (removes all local changes!).
         #[arg(long)]
-        default_target: Option<compiler_llvm_builder::target_triple::TargetTriple>,
+        force: bool,
+    },
 
-        /// Whether to build the LLVM tests.
-        #[arg(long)]
-        enable_tests: bool,
+    /// Download the musl and host LLVM sources to the cache for the offline builds.
+    Fetch,
 
-        /// Whether to build LLVM for source-based code coverage.
-        #[arg(long)]
-        enable_coverage: bool,
+    /// Clean the build artifacts.
+    Clean,
 
-        /// Extra arguments to pass to CMake.  
-        /// A leading backslash will be unescaped.
-        #[arg(long, num_args = 1..)]
-        extra_args: Vec<String>,
+    /// Manage `LLVM.lock`.
+    Lock {
+        /// The lock file command to run.
+        #[command(subcommand)]
+        command: LockCommand,
+    },
 
-        /// Whether to use compiler cache (ccache) to speed-up builds.
-        #[arg(long)]
-        ccache_variant: Option<CcacheVariant>,
+    /// Manage the shared host toolchains in the cache.
+    Cache {
+        /// The cache command to run.
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
 
-        /// Whether to build with assertions enabled or not.
-        #[arg(long)]
-        enable_assertions: bool,
+    /// Export or import the stage 1 host toolchain of the MUSL builds.
+    Host {
+        /// The host toolchain command to run.
+        #[command(subcommand)]
+        command: HostCommand,
+    },
+}
 
-        /// Build LLVM with sanitizer enabled (`Address`, `Memory`, `MemoryWithOrigins`, `Undefined`, `Thread`, `DataFlow`, or `Address;Undefined`).
+///
+/// The lock file commands.
+///
+#[derive(Debug, Subcommand)]
+pub enum LockCommand {
+    /// Pin the remote head of the branch into `LLVM.lock`.
+    Update {
+        /// Switch the lock file to another branch.
         #[arg(long)]
-        sanitizer: Option<compiler_llvm_builder::sanitizer::Sanitizer>,
+        branch: Option<String>,
 
-        /// Whether to run LLVM unit tests under valgrind or not.
+        /// Fail if the pinned commit is stale instead of updating it.
         #[arg(long)]
-        enable_valgrind: bool,
+        check: bool,
+    },
+}
 
-        /// Additional valgrind options to pass to the valgrind command.
-        #[arg(long)]
-        valgrind_options: Vec<String>,
+///
+/// The workspace directory arguments.
+///
+/// Override the `[directories]` table of `zksync-llvm.toml`.
+/// Relative paths are resolved against the current directory.
+///
+#[derive(Debug, Args)]
+pub struct DirectoryArguments {
+    /// The LLVM source directory.
+    #[arg(long, global = true, env = "ZKSYNC_LLVM_SOURCE_DIR")]
+    pub llvm_source_dir: Option<PathBuf>,
+
+    /// The LLVM host source directory for stage 1 of multistage MUSL builds.
+    #[arg(long, global = true, env = "ZKSYNC_LLVM_HOST_SOURCE_DIR")]
+    pub llvm_host_source_dir: Option<PathBuf>,
+
+    /// The LLVM target directory with all the build trees and artifacts.
+    #[arg(long, global = true, env = "ZKSYNC_LLVM_TARGET_DIR")]
+    pub llvm_target_dir: Option<PathBuf>,
+}
+
+impl DirectoryArguments {
+    ///
+    /// Converts the arguments into the directory overrides.
+    ///
+    pub fn into_directories(
+        self,
+        current_dir: &Path,
+    ) -> compiler_llvm_builder::config::Directories {
+        compiler_llvm_builder::config::Directories {
+            source: self.llvm_source_dir.map(|path| current_dir.join(path)),
+            host_source: self.llvm_host_source_dir.map(|path| current_dir.join(path)),
+            target: self.llvm_target_dir.map(|path| current_dir.join(path)),
+        }
+    }
+}
+
+///
+/// The download retry policy arguments.
+///
+/// Override the `[download]` table of `zksync-llvm.toml`.
+///
+#[derive(Debug, Args)]
+pub struct DownloadArguments {
+    /// The maximum number of download attempts.
+    #[arg(long, global = true)]
+    pub download_attempts: Option<u32>,
+
+    /// The delay before the second download attempt, e.g. `2s`, doubled after every failure.
+    #[arg(long, global = true)]
+    pub download_backoff: Option<humantime::Duration>,
+
+    /// The total time limit of a download with all its retries, e.g. `10m`.
+    #[arg(long, global = true)]
+    pub download_deadline: Option<humantime::Duration>,
+}
+
+impl DownloadArguments {
+    ///
+    /// Overrides the retry policy with the arguments.
+    ///
+    pub fn apply(
+        self,
+        mut policy: compiler_llvm_builder::retry::RetryPolicy,
+    ) -> compiler_llvm_builder::retry::RetryPolicy {
+        if let Some(attempts) = self.download_attempts {
+            policy.max_attempts = attempts;
+        }
+        if let Some(backoff) = self.download_backoff {
+            policy.initial_backoff = backoff.into();
+        }
+        if let Some(deadline) = self.download_deadline {
+            policy.deadline = deadline.into();
+        }
+        policy
+    }
+}
+
+///
+/// The LLVM framework building arguments.
+///
+#[derive(Debug, Args)]
+pub struct BuildArguments {
+    /// Build profile from `zksync-llvm.toml` to start with.
+    /// The arguments below override the profile values, and the lists are appended to its lists.
+    /// The `--no-*` flags switch off the options enabled by the profile.
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Existing LLVM checkout to build from as is, including uncommitted changes.
+    /// Takes precedence over the lock file `source` entry and `--llvm-source-dir`.
+    #[arg(long)]
+    pub llvm_source: Option<PathBuf>,
+
+    /// LLVM build type (`Debug`, `Release`, `RelWithDebInfo`, or `MinSizeRel`) [default: Release].
+    #[arg(long)]
+    pub build_type: Option<compiler_llvm_builder::BuildType>,
+
+    /// Target environment to build LLVM (`gnu` or `musl`) [default: gnu].
+    #[arg(long)]
+    pub target_env: Option<compiler_llvm_builder::target_env::TargetEnv>,
+
+    /// Host to build LLVM for (`<arch>-<os>[-<env>]`, e.g. `aarch64-macos` or `x86_64-linux-musl`).
+    /// Detected from the running system by default. The environment overrides `--target-env`.
+    #[arg(long)]
+    pub host: Option<compiler_llvm_builder::Host>,
+
+    /// Host triple to cross-compile LLVM for on the running system, e.g. `aarch64-unknown-linux-musl`.
+    /// The `aarch64-unknown-linux-gnu`, `aarch64-unknown-linux-musl`, and `x86_64-w64-mingw32` hosts are supported on x86_64 Linux.
+    #[arg(long, conflicts_with = "host", value_parser = compiler_llvm_builder::Host::from_triple)]
+    pub host_triple: Option<compiler_llvm_builder::Host>,
+
+    /// Additional targets to build LLVM with.
+    #[arg(long)]
+    pub targets: Vec<String>,
+
+    /// LLVM projects to build LLVM with.
+    #[arg(long)]
+    pub llvm_projects: Vec<compiler_llvm_builder::llvm_project::LLVMProject>,
+
+    /// Whether to build LLVM with run-time type information (RTTI) enabled.
+    #[arg(long, overrides_with = "no_enable_rtti")]
+    pub enable_rtti: bool,
+
+    /// Build LLVM without run-time type information (RTTI).
+    #[arg(long, overrides_with = "enable_rtti")]
+    pub no_enable_rtti: bool,
+
+    /// The default target to build LLVM with.
+    #[arg(long)]
+    pub default_target: Option<compiler_llvm_builder::target_triple::TargetTriple>,
+
+    /// Whether to build the LLVM tests.
+    #[arg(long, overrides_with = "no_enable_tests")]
+    pub enable_tests: bool,
+
+    /// Do not build the LLVM tests.
+    #[arg(long, overrides_with = "enable_tests")]
+    pub no_enable_tests: bool,
+
+    /// Whether to build LLVM for source-based code coverage.
+    #[arg(long, overrides_with = "no_enable_coverage")]
+    pub enable_coverage: bool,
+
+    /// Build LLVM without source-based code coverage.
+    #[arg(long, overrides_with = "enable_coverage")]
+    pub no_enable_coverage: bool,
+
+    /// Extra arguments to pass to CMake.  
+    /// A leading backslash will be unescaped.
+    /// The assertions, RTTI, sanitizer, and valgrind options take precedence over them.
+    #[arg(long, num_args = 1..)]
+    pub extra_args: Vec<String>,
+
+    /// Whether to use compiler cache (ccache) to speed-up builds.
+    #[arg(long)]
+    pub ccache_variant: Option<CcacheVariant>,
+
+    /// Whether to build with assertions enabled or not.
+    #[arg(long, overrides_with = "no_enable_assertions")]
+    pub enable_assertions: bool,
+
+    /// Build with assertions disabled.
+    #[arg(long, overrides_with = "enable_assertions")]
+    pub no_enable_assertions: bool,
+
+    /// Build LLVM with sanitizer enabled (`Address`, `Memory`, `MemoryWithOrigins`, `Undefined`, `Thread`, `DataFlow`, or `Address;Undefined`).
+    #[arg(long)]
+    pub sanitizer: Option<compiler_llvm_builder::sanitizer::Sanitizer>,
+
+    /// Whether to run LLVM unit tests under valgrind or not.
+    #[arg(long, overrides_with = "no_enable_valgrind")]
+    pub enable_valgrind: bool,
+
+    /// Do not run LLVM unit tests under valgrind.
+    #[arg(long, overrides_with = "enable_valgrind")]
+    pub no_enable_valgrind: bool,
+
+    /// Additional valgrind options to pass to the valgrind command.
+    #[arg(long)]
+    pub valgrind_options: Vec<String>,
+
+    /// The number of parallel jobs of the musl build [default: number of CPUs].
+    #[arg(long)]
+    pub musl_jobs: Option<usize>,
+}
+
+///
+/// The cache commands.
+///
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// List the shared host toolchains.
+    List,
+
+    /// Remove the host toolchain with the key, e.g. to rebuild it.
+    Remove {
+        /// The key of the host toolchain, as listed by `cache list`.
+        key: String,
     },
 
-    /// Checkout the branch specified in `LLVM.lock`.
-    Checkout {
-        /// Remove all artifacts preventing the checkout (removes all local changes!).
-        #[structopt(long = "force")]
-        force: bool,
+    /// Remove the host toolchains not used recently, and the incomplete ones.
+    Prune {
+        /// Remove the toolchains not used for this duration, e.g. `30days` or `0s` for all.
+        #[arg(long, default_value = "30days")]
+        unused_for: humantime::Duration,
     },
+}
 
-    /// Clean the build artifacts.
-    Clean,
+///
+/// The host toolchain commands.
+///
+#[derive(Debug, Subcommand)]
+pub enum HostCommand {
+    /// Package the MUSL, CRT, and host toolchain targets with their manifest.
+    Export {
+        /// The archive to write, e.g. `host-toolchain.tar.zst`.
+        archive: PathBuf,
+
+        /// Host to export the toolchain of (`<arch>-linux-musl`).
+        /// Detected from the running system by default.
+        #[arg(long)]
+        host: Option<compiler_llvm_builder::Host>,
+    },
+
+    /// Unpack the exported toolchain and mark its stages complete in the workspace.
+    Import {
+        /// The archive to read.
+        archive: PathBuf,
+    },
 }
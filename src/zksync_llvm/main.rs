@@ -4,15 +4,24 @@
 
 pub(crate) mod arguments;
 
-use std::collections::HashSet;
 use std::env;
+use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 use anyhow::Context;
 use clap::Parser;
+use compiler_llvm_builder::runner::CommandError;
+use compiler_llvm_builder::runner::CommandRunner;
+use compiler_llvm_builder::runner::DryRunRunner;
+use compiler_llvm_builder::runner::RealRunner;
 
 use self::arguments::Arguments;
+use self::arguments::BuildArguments;
+use self::arguments::CacheCommand;
+use self::arguments::Command;
+use self::arguments::HostCommand;
+use self::arguments::LockCommand;
 
 /// The default path to the LLVM lock file.
 pub const LLVM_LOCK_DEFAULT_PATH: &str = "LLVM.lock";
@@ -24,7 +33,10 @@ fn main() {
     match main_inner() {
         Ok(()) => std::process::exit(0),
         Err(error) => {
-            eprintln!("Error: {error:?}");
+            match error.downcast_ref::<CommandError>() {
+                Some(error) => eprint!("Error: {error}"),
+                None => eprintln!("Error: {error:?}"),
+            }
             std::process::exit(1)
         }
     }
@@ -35,87 +47,381 @@ fn main() {
 ///
 fn main_inner() -> anyhow::Result<()> {
     let arguments = Arguments::parse();
+    let current_dir = env::current_dir()?;
 
-    match arguments {
-        Arguments::Clone { deep, target_env } => {
-            let lock = compiler_llvm_builder::Lock::try_from(&PathBuf::from("LLVM.lock"))?;
-            compiler_llvm_builder::clone(lock, deep, target_env)?;
+    let lock_path = match arguments.lock {
+        Some(lock_path) => Some(lock_path),
+        None => compiler_llvm_builder::Lock::discover(current_dir.as_path()),
+    };
+    let mut paths = compiler_llvm_builder::LLVMPath::from_lock(lock_path.as_deref())?;
+    let config = compiler_llvm_builder::Config::load(paths.root.as_path())?;
+    if let Some(config) = config.as_ref() {
+        paths = paths.directories(&config.directories);
+    }
+    let paths = paths.directories(
+        &arguments
+            .directories
+            .into_directories(current_dir.as_path()),
+    );
+    let lock_path = lock_path.unwrap_or_else(|| PathBuf::from(LLVM_LOCK_DEFAULT_PATH));
+    let retry_policy = arguments.download.apply(
+        config
+            .as_ref()
+            .map(|config| config.download)
+            .unwrap_or_default(),
+    );
+    let is_cache_required =
+        arguments.offline || matches!(arguments.command, Command::Fetch | Command::Cache { .. });
+    let cache = match compiler_llvm_builder::cache::Cache::resolve(
+        arguments
+            .cache_dir
+            .map(|cache_dir| current_dir.join(cache_dir)),
+        arguments.offline,
+        is_cache_required,
+    ) {
+        Some(cache) => Some(cache),
+        None if is_cache_required => {
+            anyhow::bail!("The cache directory is not found. Set it with `--cache-dir`")
         }
-        Arguments::Build {
-            build_type,
+        None => None,
+    };
+    let mirrors = config
+        .as_ref()
+        .map(compiler_llvm_builder::Config::mirrors)
+        .unwrap_or_default();
+    let mut runner = runner(&paths, retry_policy, cache, mirrors);
+
+    match arguments.command {
+        Command::Clone {
+            deep,
             target_env,
-            targets,
-            llvm_projects,
-            enable_rtti,
-            default_target,
-            enable_tests,
-            enable_coverage,
-            extra_args,
-            ccache_variant,
-            enable_assertions,
-            sanitizer,
-            enable_valgrind,
-            valgrind_options,
+            host,
         } => {
-            let mut targets = targets
-                .into_iter()
-                .map(|target| compiler_llvm_builder::Platform::from_str(target.as_str()))
-                .collect::<Result<HashSet<compiler_llvm_builder::Platform>, String>>()
-                .map_err(|platform| anyhow::anyhow!("Unknown platform `{platform}`"))?;
-            targets.insert(compiler_llvm_builder::Platform::EraVM);
-            targets.insert(compiler_llvm_builder::Platform::EVM);
-
-            let extra_args_unescaped: Vec<String> = extra_args
-                .iter()
-                .map(|argument| {
-                    argument
-                        .strip_prefix('\\')
-                        .unwrap_or(argument.as_str())
-                        .to_owned()
-                })
-                .collect();
-            if env::var("VERBOSE").is_ok() {
-                println!("\nextra_args: {extra_args:#?}");
-                println!("\nextra_args_unescaped: {extra_args_unescaped:#?}");
-            }
-
-            if let Some(ccache_variant) = ccache_variant {
+            let lock = compiler_llvm_builder::Lock::try_from(&lock_path)?;
+            let host = match host {
+                Some(host) => host,
+                None => compiler_llvm_builder::Host::detect(target_env)?,
+            };
+            compiler_llvm_builder::clone(lock, deep, host, &paths, runner.as_mut())?;
+        }
+        Command::Build {
+            mut build,
+            from_stage,
+            only_stage,
+        } => {
+            let lock = optional_lock(lock_path.as_path())?;
+            let profile = profile(build.profile.as_deref(), config.as_ref())?;
+            let paths = local_source(
+                paths,
+                lock.as_ref(),
+                build.llvm_source.take(),
+                profile.llvm_source.as_deref(),
+                current_dir.as_path(),
+            )?;
+            let musl = lock.and_then(|lock| lock.musl).unwrap_or_default();
+            let (options, host) = build_options(build, profile)?;
+            if let Some(ccache_variant) = options.ccache_variant {
                 compiler_llvm_builder::utils::exists(ccache_variant.to_string().as_str())?;
             }
-
-            let mut projects = llvm_projects
-                .into_iter()
-                .map(|project| compiler_llvm_builder::llvm_project::LLVMProject::from_str(project.to_string().as_str()))
-                .collect::<Result<HashSet<compiler_llvm_builder::llvm_project::LLVMProject>, String>>()
-                .map_err(|project| anyhow::anyhow!("Unknown LLVM project `{project}`"))?;
-            projects.insert(compiler_llvm_builder::llvm_project::LLVMProject::LLD);
-
+            let selection = compiler_llvm_builder::plan::StageSelection {
+                from_stage,
+                only_stage,
+            };
             compiler_llvm_builder::build(
-                build_type,
-                target_env,
-                targets,
-                projects,
-                enable_rtti,
-                default_target,
-                enable_tests,
-                enable_coverage,
-                extra_args_unescaped,
-                ccache_variant,
-                enable_assertions,
-                sanitizer,
-                enable_valgrind,
-                valgrind_options,
+                options,
+                host,
+                &paths,
+                &musl,
+                &selection,
+                runner.as_mut(),
+            )?;
+        }
+        Command::Plan { mut build, format } => {
+            let lock = optional_lock(lock_path.as_path())?;
+            let profile = profile(build.profile.as_deref(), config.as_ref())?;
+            let paths = local_source(
+                paths,
+                lock.as_ref(),
+                build.llvm_source.take(),
+                profile.llvm_source.as_deref(),
+                current_dir.as_path(),
+            )?;
+            let musl = lock.and_then(|lock| lock.musl).unwrap_or_default();
+            let (options, host) = build_options(build, profile)?;
+            let plan = compiler_llvm_builder::plan(&options, host, &paths, &musl)?;
+            print!("{}", plan.render(format)?);
+        }
+        Command::Checkout { force } => {
+            let lock = compiler_llvm_builder::Lock::try_from(&lock_path)?;
+            compiler_llvm_builder::checkout(lock, force, &paths, runner.as_mut())?;
+        }
+        Command::Lock {
+            command: LockCommand::Update { branch, check },
+        } => {
+            compiler_llvm_builder::update_lock(
+                lock_path.as_path(),
+                branch,
+                check,
+                runner.as_mut(),
+            )?;
+        }
+        Command::Fetch => {
+            let lock = optional_lock(lock_path.as_path())?;
+            let host = lock
+                .as_ref()
+                .and_then(|lock| lock.host.clone())
+                .unwrap_or_default();
+            let musl = lock.and_then(|lock| lock.musl).unwrap_or_default();
+            compiler_llvm_builder::fetch(&host, &musl, runner.as_mut())?;
+        }
+        Command::Cache { command } => {
+            let cache = runner
+                .cache()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("The cache directory is not set"))?;
+            match command {
+                CacheCommand::List => compiler_llvm_builder::list_host_toolchains(&cache)?,
+                CacheCommand::Remove { key } => {
+                    compiler_llvm_builder::remove_host_toolchain(&cache, key.as_str())?
+                }
+                CacheCommand::Prune { unused_for } => {
+                    compiler_llvm_builder::prune_host_toolchains(&cache, unused_for.into())?
+                }
+            }
+        }
+        Command::Host {
+            command: HostCommand::Export { archive, host },
+        } => {
+            let lock = optional_lock(lock_path.as_path())?;
+            let musl = lock.and_then(|lock| lock.musl).unwrap_or_default();
+            let host = match host {
+                Some(host) => host,
+                None => compiler_llvm_builder::Host::detect(
+                    compiler_llvm_builder::target_env::TargetEnv::MUSL,
+                )?,
+            };
+            compiler_llvm_builder::export_host_toolchain(
+                host,
+                &paths,
+                &musl,
+                current_dir.join(archive).as_path(),
+                runner.as_mut(),
             )?;
         }
-        Arguments::Checkout { force } => {
-            let lock = compiler_llvm_builder::Lock::try_from(&PathBuf::from("LLVM.lock"))?;
-            compiler_llvm_builder::checkout(lock, force)?;
+        Command::Host {
+            command: HostCommand::Import { archive },
+        } => {
+            compiler_llvm_builder::import_host_toolchain(
+                &paths,
+                current_dir.join(archive).as_path(),
+                runner.as_mut(),
+            )?;
         }
-        Arguments::Clean => {
-            compiler_llvm_builder::clean()
+        Command::Clean => {
+            compiler_llvm_builder::clean(&paths)
                 .with_context(|| "Unable to remove target LLVM directory")?;
         }
     }
 
     Ok(())
 }
+
+///
+/// Creates the subprocess runner.
+///
+/// The `DRY_RUN` environment variable only prints the commands, and `VERBOSE` prints them
+/// before running. The output of every command is also written to `target-llvm/logs/`.
+///
+fn runner(
+    paths: &compiler_llvm_builder::LLVMPath,
+    retry_policy: compiler_llvm_builder::retry::RetryPolicy,
+    cache: Option<compiler_llvm_builder::cache::Cache>,
+    mirrors: compiler_llvm_builder::mirrors::Mirrors,
+) -> Box<dyn CommandRunner> {
+    if env::var("DRY_RUN").is_ok() {
+        return Box::new(DryRunRunner::default());
+    }
+    let runner = RealRunner::new(env::var("VERBOSE").is_ok())
+        .log_directory(paths.logs().as_path())
+        .retry(retry_policy)
+        .mirrors(mirrors);
+    match cache {
+        Some(cache) => Box::new(runner.cache(cache)),
+        None => Box::new(runner),
+    }
+}
+
+///
+/// Reads the lock file if it exists, as building and planning do not require it.
+///
+fn optional_lock(lock_path: &Path) -> anyhow::Result<Option<compiler_llvm_builder::Lock>> {
+    if !lock_path.exists() {
+        return Ok(None);
+    }
+    compiler_llvm_builder::Lock::try_from(&lock_path.to_path_buf()).map(Some)
+}
+
+///
+/// Reads the profile from the configuration file, or returns the default one if it is not
+/// specified.
+///
+fn profile(
+    name: Option<&str>,
+    config: Option<&compiler_llvm_builder::Config>,
+) -> anyhow::Result<compiler_llvm_builder::config::Profile> {
+    match name {
+        Some(name) => config
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Profile `{name}` requested, but `{}` is not found",
+                    compiler_llvm_builder::Config::FILE_NAME
+                )
+            })?
+            .profile(name),
+        None => Ok(compiler_llvm_builder::config::Profile::default()),
+    }
+}
+
+///
+/// Points the LLVM source directory at the local source tree specified in the arguments,
+/// in the profile, or in the lock file, if any.
+///
+fn local_source(
+    paths: compiler_llvm_builder::LLVMPath,
+    lock: Option<&compiler_llvm_builder::Lock>,
+    llvm_source: Option<PathBuf>,
+    profile_llvm_source: Option<&Path>,
+    current_dir: &Path,
+) -> anyhow::Result<compiler_llvm_builder::LLVMPath> {
+    let source = match (
+        llvm_source,
+        profile_llvm_source,
+        lock.and_then(|lock| lock.source.as_ref()),
+    ) {
+        (Some(llvm_source), _, _) => current_dir.join(llvm_source),
+        (None, Some(llvm_source), _) => paths.root.join(llvm_source),
+        (None, None, Some(source)) => paths.root.join(source.path.as_path()),
+        (None, None, None) => return Ok(paths),
+    };
+    if !source.join("llvm").is_dir() {
+        anyhow::bail!("The LLVM source {source:?} does not contain the `llvm` directory");
+    }
+    Ok(
+        paths.directories(&compiler_llvm_builder::config::Directories {
+            source: Some(source),
+            ..Default::default()
+        }),
+    )
+}
+
+///
+/// Converts the command-line building arguments into the build options and the host.
+///
+/// The profile options are used as the base, and the explicitly passed arguments override
+/// them. The host passed in the arguments overrides both hosts of the profile.
+///
+fn build_options(
+    arguments: BuildArguments,
+    profile: compiler_llvm_builder::config::Profile,
+) -> anyhow::Result<(
+    compiler_llvm_builder::BuildOptions,
+    compiler_llvm_builder::Host,
+)> {
+    let base = profile.options;
+    let (host_triple, host_override) = match (arguments.host_triple, arguments.host) {
+        (None, None) => (profile.host_triple, profile.host),
+        arguments => arguments,
+    };
+
+    let target_env = arguments.target_env.unwrap_or(base.target_env);
+    let (host, build_host) = match (host_triple, host_override) {
+        (Some(host), _) => (
+            host,
+            Some(compiler_llvm_builder::Host::detect(
+                compiler_llvm_builder::target_env::TargetEnv::GNU,
+            )?),
+        ),
+        (None, Some(host)) => (host, None),
+        (None, None) => (compiler_llvm_builder::Host::detect(target_env)?, None),
+    };
+    // The Xcode of the running system is only relevant if the host is not overridden.
+    let xcode_version = match (host_triple, host_override, host.os) {
+        (None, None, compiler_llvm_builder::host::OS::MacOS) => {
+            compiler_llvm_builder::utils::get_xcode_version().ok()
+        }
+        _ => None,
+    };
+
+    let targets = arguments
+        .targets
+        .into_iter()
+        .map(|target| compiler_llvm_builder::Platform::from_str(target.as_str()))
+        .collect::<Result<Vec<compiler_llvm_builder::Platform>, String>>()
+        .map_err(|platform| anyhow::anyhow!("Unknown platform `{platform}`"))?;
+
+    let extra_args_unescaped: Vec<String> = arguments
+        .extra_args
+        .iter()
+        .map(|argument| {
+            argument
+                .strip_prefix('\\')
+                .unwrap_or(argument.as_str())
+                .to_owned()
+        })
+        .collect();
+    if env::var("VERBOSE").is_ok() {
+        println!("\nextra_args: {:#?}", arguments.extra_args);
+        println!("\nextra_args_unescaped: {extra_args_unescaped:#?}");
+    }
+
+    let mut builder = compiler_llvm_builder::build_options::BuildOptionsBuilder::from(base)
+        .target_env(host.env)
+        .targets(targets)
+        .llvm_projects(arguments.llvm_projects)
+        .extra_args(extra_args_unescaped)
+        .valgrind_options(arguments.valgrind_options)
+        .build_host(build_host)
+        .xcode_version(xcode_version);
+    if let Some(build_type) = arguments.build_type {
+        builder = builder.build_type(build_type);
+    }
+    if let Some(enabled) = switch(arguments.enable_rtti, arguments.no_enable_rtti) {
+        builder = builder.enable_rtti(enabled);
+    }
+    if arguments.default_target.is_some() {
+        builder = builder.default_target(arguments.default_target);
+    }
+    if let Some(enabled) = switch(arguments.enable_tests, arguments.no_enable_tests) {
+        builder = builder.enable_tests(enabled);
+    }
+    if let Some(enabled) = switch(arguments.enable_coverage, arguments.no_enable_coverage) {
+        builder = builder.enable_coverage(enabled);
+    }
+    if arguments.ccache_variant.is_some() {
+        builder = builder.ccache_variant(arguments.ccache_variant);
+    }
+    if let Some(enabled) = switch(arguments.enable_assertions, arguments.no_enable_assertions) {
+        builder = builder.enable_assertions(enabled);
+    }
+    if arguments.sanitizer.is_some() {
+        builder = builder.sanitizer(arguments.sanitizer);
+    }
+    if let Some(enabled) = switch(arguments.enable_valgrind, arguments.no_enable_valgrind) {
+        builder = builder.enable_valgrind(enabled);
+    }
+    if arguments.musl_jobs.is_some() {
+        builder = builder.musl_jobs(arguments.musl_jobs);
+    }
+    Ok((builder.build(), host))
+}
+
+///
+/// Returns the value of the option switched by the pair of flags, if any of them is passed.
+///
+fn switch(enable: bool, disable: bool) -> Option<bool> {
+    match (enable, disable) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
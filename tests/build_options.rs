@@ -0,0 +1,98 @@
+use compiler_llvm_builder::llvm_project::LLVMProject;
+use compiler_llvm_builder::sanitizer::Sanitizer;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::BuildOptions;
+use compiler_llvm_builder::BuildType;
+use compiler_llvm_builder::Platform;
+
+/// Tests the default build options.
+///
+/// This test verifies that the default build options match the defaults of the CLI.
+#[test]
+fn defaults() {
+    let options = BuildOptions::builder().build();
+    assert_eq!(options, BuildOptions::default());
+    assert_eq!(options.build_type, BuildType::Release);
+    assert_eq!(options.target_env, TargetEnv::GNU);
+    assert!(options.targets.contains(&Platform::EraVM));
+    assert!(options.targets.contains(&Platform::EVM));
+    assert!(options.llvm_projects.contains(&LLVMProject::LLD));
+    assert!(!options.enable_assertions);
+}
+
+/// Tests the build options serialization round trip.
+///
+/// This test verifies that the build options can be serialized to TOML and parsed back,
+/// and that the fields omitted in TOML fall back to the defaults.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while serializing
+/// the build options.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn serde_round_trip() -> anyhow::Result<()> {
+    let options = BuildOptions::builder()
+        .build_type(BuildType::Debug)
+        .target_env(TargetEnv::MUSL)
+        .llvm_projects([LLVMProject::CLANG])
+        .enable_assertions(true)
+        .sanitizer(Some(Sanitizer::AddressUndefined))
+        .extra_args(["-DLLVM_PARALLEL_LINK_JOBS=1".to_owned()])
+        .build();
+    let serialized = toml::to_string(&options)?;
+    assert_eq!(
+        toml::from_str::<BuildOptions>(serialized.as_str())?,
+        options
+    );
+
+    let partial: BuildOptions = toml::from_str("build-type = \"Debug\"\nenable-tests = true")?;
+    assert_eq!(
+        partial,
+        BuildOptions::builder()
+            .build_type(BuildType::Debug)
+            .enable_tests(true)
+            .build()
+    );
+    Ok(())
+}
+
+/// Tests the mandatory targets and projects of the deserialized build options.
+///
+/// This test verifies that the explicit lists replacing the defaults do not drop the
+/// mandatory targets and projects from the final CMake configuration.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while creating the
+/// CMake configuration.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn mandatory_entries() -> anyhow::Result<()> {
+    let options: BuildOptions = toml::from_str("targets = [\"EVM\"]\nllvm-projects = [\"clang\"]")?;
+    assert!(!options.targets.contains(&Platform::EraVM));
+
+    let config = compiler_llvm_builder::platforms::x86_64_linux_gnu::cmake_config(
+        &options,
+        &compiler_llvm_builder::LLVMPath::new(std::path::Path::new(".")),
+    )?;
+    assert_eq!(
+        config
+            .get("LLVM_TARGETS_TO_BUILD")
+            .map(|entry| entry.value.as_str()),
+        Some("EraVM;EVM")
+    );
+    assert_eq!(
+        config
+            .get("LLVM_ENABLE_PROJECTS")
+            .map(|entry| entry.value.as_str()),
+        Some("clang;lld")
+    );
+    Ok(())
+}
@@ -0,0 +1,251 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use compiler_llvm_builder::cache::Cache;
+use compiler_llvm_builder::plan::Step;
+use compiler_llvm_builder::runner::RealRunner;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// The SHA-256 of `hello\n`.
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+/// Tests downloading in the offline mode.
+///
+/// This test verifies that the cached file is taken from the cache, and that the missing
+/// one is an error instead of a network access.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// download.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn download_offline() -> anyhow::Result<()> {
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), true);
+    let destination = assert_fs::TempDir::new()?;
+    let download = |sha256: &str| Step::Download {
+        url: "https://example.com/hello.txt".to_owned(),
+        destination: destination.path().to_path_buf(),
+        file_name: "hello.txt".to_owned(),
+        sha256: sha256.to_owned(),
+    };
+    let mut runner = RealRunner::new(false).cache(cache.clone());
+
+    let error = download(HELLO_SHA256)
+        .execute(&mut runner)
+        .expect_err("The file is not cached")
+        .to_string();
+    assert!(error.contains("disabled in the offline mode"), "{error}");
+
+    std::fs::create_dir_all(cache_directory.path().join("sha256"))?;
+    std::fs::write(cache.file(HELLO_SHA256), "hello\n")?;
+    download(HELLO_SHA256).execute(&mut runner)?;
+    assert_eq!(
+        std::fs::read_to_string(destination.path().join("hello.txt"))?,
+        "hello\n"
+    );
+    Ok(())
+}
+
+/// Tests building in the offline mode with an empty cache.
+///
+/// This test verifies that the build fails before running anything, listing the missing
+/// sources.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the build command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn build_offline() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    common::create_musl_lockfile(directory.path())?;
+    let cache_directory = assert_fs::TempDir::new()?;
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.arg("--offline")
+        .arg("--cache-dir")
+        .arg(cache_directory.path())
+        .args(["build", "--host", "x86_64-linux-musl"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("missing from the cache"))
+        .stderr(predicate::str::contains(
+            "`musl-1.2.3.tar.gz` from `https://musl.libc.org/releases/musl-1.2.3.tar.gz`",
+        ));
+    assert!(!directory
+        .path()
+        .join(compiler_llvm_builder::LLVMPath::DIRECTORY_LLVM_TARGET)
+        .join("build-crt")
+        .exists());
+    Ok(())
+}
+
+/// Tests fetching the sources and cloning in the offline mode.
+///
+/// This test verifies that the host repository is cached by `fetch`, so it is no longer
+/// reported as missing in the offline mode.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the fetch and clone commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn fetch_and_clone_offline() -> anyhow::Result<()> {
+    let remote = assert_fs::TempDir::new()?;
+    let git = |args: &[&str]| -> anyhow::Result<()> {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(remote.path())
+            .status()?;
+        anyhow::ensure!(status.success(), "git {args:?} failed");
+        Ok(())
+    };
+    git(&["init", "--quiet"])?;
+    git(&["commit", "--quiet", "--allow-empty", "--message", "Initial"])?;
+    git(&["tag", "llvmorg-test"])?;
+    let remote_url = remote.path().to_string_lossy().to_string();
+
+    let directory = assert_fs::TempDir::new()?;
+    directory.child(common::LLVM_LOCK_FILE).write_str(
+        format!(
+            "url = \"https://github.com/matter-labs/era-compiler-llvm\"\nbranch = \"main\"\n\n[host]\nurl = \"{remote_url}\"\ntag = \"llvmorg-test\"\n"
+        )
+        .as_str(),
+    )?;
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), false);
+    std::fs::create_dir_all(cache_directory.path().join("sha256"))?;
+    std::fs::write(
+        cache.file(compiler_llvm_builder::utils::MUSL_SHA256),
+        "cached",
+    )?;
+
+    let clone_offline = || -> anyhow::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+        cmd.current_dir(directory.path());
+        cmd.arg("--offline")
+            .arg("--cache-dir")
+            .arg(cache_directory.path())
+            .args(["clone", "--target-env", "musl"]);
+        Ok(cmd.assert().failure())
+    };
+    clone_offline()?
+        .stderr(predicate::str::contains("the host LLVM repository"))
+        .stderr(predicate::str::contains("the LLVM repository `https://"));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.arg("--cache-dir")
+        .arg(cache_directory.path())
+        .arg("fetch");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("musl-1.2.3: "))
+        .stdout(predicate::str::contains("llvmorg-test: "));
+    assert!(cache.git(remote_url.as_str(), "llvmorg-test").exists());
+
+    clone_offline()?
+        .stderr(predicate::str::contains("the host LLVM repository").not())
+        .stderr(predicate::str::contains("the LLVM repository `https://"));
+    Ok(())
+}
+
+/// Tests resolving the cache directory.
+///
+/// This test verifies that the default cache directory is only used if the cache is required,
+/// and that the host toolchains are only shared through the cache directory set explicitly.
+#[rstest]
+fn resolve() {
+    let root = std::path::PathBuf::from("/cache");
+    assert_eq!(
+        Cache::resolve(Some(root.clone()), true, false),
+        Some(Cache {
+            root,
+            offline: true,
+            shares_host_toolchains: true,
+        })
+    );
+    assert_eq!(Cache::resolve(None, false, false), None);
+    assert_eq!(
+        Cache::resolve(None, false, true),
+        Cache::default_root().map(|root| Cache {
+            root,
+            offline: false,
+            shares_host_toolchains: false,
+        })
+    );
+}
+
+/// Tests cloning in the offline mode with the LLVM repository already cloned.
+///
+/// This test verifies that the repository at the pinned commit is kept and not reported as
+/// missing, so only the host repository is cloned from the cache.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// clone command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn clone_offline_cloned() -> anyhow::Result<()> {
+    const REF: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    let directory = assert_fs::TempDir::new()?;
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), true);
+    let host_source = compiler_llvm_builder::lock::HostSource::default();
+    std::fs::create_dir_all(cache.git(host_source.url.as_str(), host_source.tag.as_str()))?;
+    let paths = compiler_llvm_builder::LLVMPath::new(directory.path());
+    std::fs::create_dir_all(paths.llvm_source())?;
+    let lock = compiler_llvm_builder::Lock {
+        url: "https://github.com/matter-labs/era-compiler-llvm".to_owned(),
+        branch: "main".to_owned(),
+        r#ref: Some(REF.to_owned()),
+        source: None,
+        host: None,
+        musl: None,
+    };
+
+    let mut runner = compiler_llvm_builder::runner::RecordingRunner::default()
+        .cache(cache)
+        .output_on(&["rev-parse", "HEAD"], format!("{REF}\n").as_str());
+    compiler_llvm_builder::clone(
+        lock,
+        false,
+        compiler_llvm_builder::Host::new(
+            compiler_llvm_builder::host::Arch::X86_64,
+            compiler_llvm_builder::host::OS::Linux,
+            compiler_llvm_builder::target_env::TargetEnv::MUSL,
+        ),
+        &paths,
+        &mut runner,
+    )?;
+    assert_eq!(runner.commands.len(), 1);
+    assert_eq!(
+        runner.commands[0].description,
+        "LLVM host repository cloning"
+    );
+    Ok(())
+}
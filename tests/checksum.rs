@@ -0,0 +1,85 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use compiler_llvm_builder::plan::Step;
+use compiler_llvm_builder::runner::RealRunner;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// The SHA-256 of `hello\n`.
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+/// Tests the downloaded file verification.
+///
+/// This test verifies that the file with the expected digest is accepted, and that the file
+/// with another digest is rejected with both hashes in the error and removed.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// download.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn verify_download() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let download = |sha256: &str| Step::Download {
+        url: "https://example.com/hello.txt".to_owned(),
+        destination: directory.path().to_path_buf(),
+        file_name: "hello.txt".to_owned(),
+        sha256: sha256.to_owned(),
+    };
+    let mut runner = RealRunner::new(false);
+
+    directory.child("hello.txt").write_str("hello\n")?;
+    download(HELLO_SHA256).execute(&mut runner)?;
+
+    let expected = "0".repeat(64);
+    let error = download(expected.as_str())
+        .execute(&mut runner)
+        .expect_err("The digest mismatches")
+        .to_string();
+    assert!(
+        error.contains(format!("expected {expected}").as_str()),
+        "{error}"
+    );
+    assert!(
+        error.contains(format!("actual {HELLO_SHA256}").as_str()),
+        "{error}"
+    );
+    assert!(!directory.path().join("hello.txt").exists());
+    Ok(())
+}
+
+/// Tests planning with a musl version without a known digest.
+///
+/// This test verifies that the downloads without an expected digest are rejected.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_without_digest() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory.child(common::LLVM_LOCK_FILE).write_str(
+        "url = \"https://github.com/matter-labs/era-compiler-llvm\"\nbranch = \"main\"\n\n[musl]\nversion = \"1.2.5\"\n",
+    )?;
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--host", "x86_64-linux-musl"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "The SHA-256 of musl 1.2.5 is unknown",
+    ));
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::path::Path;
+
+use compiler_llvm_builder::cmake_config::CMakeEntryType;
+use compiler_llvm_builder::cmake_config::CMakeOrigin;
+use compiler_llvm_builder::platforms;
+use compiler_llvm_builder::sanitizer::Sanitizer;
+use compiler_llvm_builder::BuildOptions;
+use compiler_llvm_builder::CMakeConfig;
+use compiler_llvm_builder::LLVMPath;
+
+/// Tests the CMake configuration overrides and rendering.
+///
+/// This test verifies that setting an existing entry overrides it in place, and that the
+/// extra arguments are rendered after the cache entries set before them and before the ones
+/// set afterwards.
+#[test]
+fn override_and_render() {
+    let mut config = CMakeConfig::new(Path::new("/src"), Path::new("/build"));
+    config
+        .set_bool("LLVM_ENABLE_WERROR", true, CMakeOrigin::Shared)
+        .set_string("CMAKE_BUILD_TYPE", "Release", CMakeOrigin::Options)
+        .set_bool("LLVM_ENABLE_WERROR", false, CMakeOrigin::Platform)
+        .set_bool("LLVM_ENABLE_ZLIB", false, CMakeOrigin::Shared)
+        .extra_args(["-DLLVM_PARALLEL_LINK_JOBS=1".to_owned()])
+        .set_bool("LLVM_ENABLE_ASSERTIONS", true, CMakeOrigin::Options)
+        .unset("LLVM_ENABLE_ZLIB");
+
+    let werror = config.get("LLVM_ENABLE_WERROR").expect("Always exists");
+    assert_eq!(werror.r#type, CMakeEntryType::Bool);
+    assert_eq!(werror.origin, CMakeOrigin::Platform);
+    assert_eq!(
+        config.to_args(),
+        vec![
+            "-S",
+            "/src",
+            "-B",
+            "/build",
+            "-G",
+            "Ninja",
+            "-DLLVM_ENABLE_WERROR='Off'",
+            "-DCMAKE_BUILD_TYPE='Release'",
+            "-DLLVM_PARALLEL_LINK_JOBS=1",
+            "-DLLVM_ENABLE_ASSERTIONS='On'",
+        ]
+    );
+}
+
+/// Tests overriding the CMake entry after the extra arguments.
+///
+/// This test verifies that the entry set before the extra arguments and overridden afterwards
+/// is moved after them, so the override takes precedence over the extra arguments.
+#[test]
+fn override_after_extra_args() {
+    let mut config = CMakeConfig::new(Path::new("/src"), Path::new("/build"));
+    config
+        .set_bool("LLVM_ENABLE_WERROR", true, CMakeOrigin::Shared)
+        .set_string("CMAKE_BUILD_TYPE", "Release", CMakeOrigin::Options)
+        .extra_args(["-DLLVM_ENABLE_WERROR=On".to_owned()])
+        .set_bool("LLVM_ENABLE_WERROR", false, CMakeOrigin::Platform);
+
+    assert_eq!(config.extra_args_position, 1);
+    assert_eq!(
+        config.to_args(),
+        vec![
+            "-S",
+            "/src",
+            "-B",
+            "/build",
+            "-G",
+            "Ninja",
+            "-DCMAKE_BUILD_TYPE='Release'",
+            "-DLLVM_ENABLE_WERROR=On",
+            "-DLLVM_ENABLE_WERROR='Off'",
+        ]
+    );
+}
+
+/// Tests the platform-specific CMake configurations.
+///
+/// This test verifies that the platform overrides are applied on top of the shared base.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while creating the
+/// CMake configuration.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn platform_overrides() -> anyhow::Result<()> {
+    let options = BuildOptions::builder()
+        .sanitizer(Some(Sanitizer::Address))
+        .enable_valgrind(true)
+        .valgrind_options(["--leak-check=no".to_owned()])
+        .build();
+    let paths = LLVMPath::new(Path::new("."));
+
+    let gnu = platforms::x86_64_linux_gnu::cmake_config(&options, &paths)?;
+    assert_eq!(
+        gnu.get("LLVM_USE_LINKER").map(|entry| entry.value.as_str()),
+        Some("lld")
+    );
+    assert_eq!(
+        gnu.get("LLVM_TARGETS_TO_BUILD")
+            .map(|entry| entry.value.as_str()),
+        Some("EraVM;EVM")
+    );
+    assert_eq!(
+        gnu.get("LLVM_LIT_ARGS").map(|entry| entry.value.as_str()),
+        Some("-sv --vg --vg-leak --vg-arg='--leak-check=no'")
+    );
+
+    let windows = platforms::x86_64_windows_gnu::cmake_config(&options, &paths)?;
+    let werror = windows.get("LLVM_ENABLE_WERROR").expect("Always exists");
+    assert_eq!(werror.value, "Off");
+    assert_eq!(werror.origin, CMakeOrigin::Platform);
+    assert!(windows.get("LLVM_LIT_ARGS").is_none());
+
+    let musl = platforms::x86_64_linux_musl::target_cmake_config(
+        &options,
+        Path::new("/llvm"),
+        Path::new("/build-final"),
+        Path::new("/target-final"),
+        Path::new("/target-host"),
+    );
+    assert_eq!(
+        musl.get("CMAKE_C_COMPILER")
+            .map(|entry| entry.value.as_str()),
+        Some("/target-host/bin/clang")
+    );
+    assert_eq!(
+        musl.get("LLVM_USE_SANITIZER")
+            .map(|entry| entry.value.as_str()),
+        Some("Address")
+    );
+    Ok(())
+}
+
+/// Tests the macOS linker flags depending on the Xcode version.
+///
+/// This test verifies that the duplicate libraries warnings are only suppressed for Xcode 15
+/// and newer, and that the Xcode version is not probed if it is not specified.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while creating
+/// the CMake configuration.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn macos_xcode_version() -> anyhow::Result<()> {
+    let paths = LLVMPath::new(Path::new("."));
+
+    let unspecified = platforms::aarch64_macos::cmake_config(&BuildOptions::default(), &paths)?;
+    assert!(unspecified.get("CMAKE_EXE_LINKER_FLAGS").is_none());
+
+    let xcode_15 = platforms::aarch64_macos::cmake_config(
+        &BuildOptions::builder().xcode_version(Some(15)).build(),
+        &paths,
+    )?;
+    assert_eq!(
+        xcode_15
+            .get("CMAKE_EXE_LINKER_FLAGS")
+            .map(|entry| entry.value.as_str()),
+        Some("-Wl,-no_warn_duplicate_libraries")
+    );
+    Ok(())
+}
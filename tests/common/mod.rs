@@ -6,6 +6,32 @@ pub const ERA_LLVM_REPO_URL: &str = "https://github.com/matter-labs/era-compiler
 pub const ERA_LLVM_REPO_TEST_BRANCH: &str = "main";
 pub const ERA_LLVM_REPO_TEST_SHA_INVALID: &str = "12345abcd";
 pub const LLVM_LOCK_FILE: &str = "LLVM.lock";
+pub const KERNEL_SHA256: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Returns the musl source with the SHA-256 of the default kernel headers release.
+pub fn musl_source() -> compiler_llvm_builder::lock::MuslSource {
+    compiler_llvm_builder::lock::MuslSource {
+        kernel_headers: Some(compiler_llvm_builder::lock::KernelHeaders {
+            sha256: Some(KERNEL_SHA256.to_owned()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Creates a lock file with the musl source in the directory for planning the musl hosts.
+pub fn create_musl_lockfile(directory: &std::path::Path) -> anyhow::Result<()> {
+    let lock = compiler_llvm_builder::Lock {
+        url: ERA_LLVM_REPO_URL.to_string(),
+        branch: ERA_LLVM_REPO_TEST_BRANCH.to_string(),
+        r#ref: None,
+        source: None,
+        host: None,
+        musl: Some(musl_source()),
+    };
+    std::fs::write(directory.join(LLVM_LOCK_FILE), toml::to_string(&lock)?)?;
+    Ok(())
+}
 
 /// Creates a temporary lock file for testing.
 pub fn create_test_tmp_lockfile(
@@ -16,6 +42,9 @@ pub fn create_test_tmp_lockfile(
         url: ERA_LLVM_REPO_URL.to_string(),
         branch: ERA_LLVM_REPO_TEST_BRANCH.to_string(),
         r#ref: reference,
+        source: None,
+        host: None,
+        musl: Some(musl_source()),
     };
     file.write_str(toml::to_string(&lock)?.as_str())?;
     Ok(file)
@@ -0,0 +1,266 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// The test configuration file contents.
+const CONFIG: &str = r#"
+[profile.ci-asan]
+build-type = "Debug"
+enable-assertions = true
+sanitizer = "Address"
+extra-args = ["-DLLVM_PARALLEL_LINK_JOBS=2"]
+
+[profile.minimal]
+
+[profile.evm-clang]
+targets = ["EVM"]
+llvm-projects = ["clang"]
+
+[profile.macos]
+host = "aarch64-macos"
+enable-tests = true
+
+[profile.cross]
+host-triple = "aarch64-unknown-linux-gnu"
+"#;
+
+/// Tests the profile parsing.
+///
+/// This test verifies that the profiles are parsed, that the omitted options fall back
+/// to the defaults, that the hosts are parsed from their strings, and that unknown options
+/// are rejected.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while parsing the
+/// configuration file.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn parse() -> anyhow::Result<()> {
+    let config: compiler_llvm_builder::Config = toml::from_str(CONFIG)?;
+    let ci_asan = config.profile("ci-asan")?;
+    assert_eq!(
+        ci_asan.options.build_type,
+        compiler_llvm_builder::BuildType::Debug
+    );
+    assert!(ci_asan.options.enable_assertions);
+    assert_eq!(
+        config.profile("minimal")?,
+        compiler_llvm_builder::config::Profile::default()
+    );
+    assert_eq!(
+        config.profile("macos")?.host.map(|host| host.to_string()),
+        Some("aarch64-macos".to_owned())
+    );
+    let cross = config.profile("cross")?;
+    assert_eq!(
+        cross.host_triple.map(|host| host.to_string()),
+        Some("aarch64-linux-gnu".to_owned())
+    );
+    assert_eq!(
+        toml::from_str::<compiler_llvm_builder::config::Profile>(
+            toml::to_string(&cross)?.as_str()
+        )?,
+        cross
+    );
+    assert!(config
+        .profile("missing")
+        .expect_err("Missing profile")
+        .to_string()
+        .contains("Available profiles: ci-asan, cross, evm-clang, macos, minimal"));
+
+    assert!(toml::from_str::<compiler_llvm_builder::Config>(
+        "[profile.typo]\nenable-asertions = true\n"
+    )
+    .is_err());
+    assert!(toml::from_str::<compiler_llvm_builder::Config>(
+        "[profile.typo]\nhost-triple = \"aarch64-linux-gnu\"\n"
+    )
+    .is_err());
+    Ok(())
+}
+
+/// Tests building with a profile overridden by the command-line flags.
+///
+/// This test verifies that the profile options are used and that the explicitly passed
+/// flags take precedence over them.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn profile_with_overrides() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory
+        .child(compiler_llvm_builder::Config::FILE_NAME)
+        .write_str(CONFIG)?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args([
+        "plan",
+        "--host",
+        "x86_64-linux-gnu",
+        "--profile",
+        "ci-asan",
+        "--build-type",
+        "Release",
+        "--extra-args",
+        "\\-DLLVM_PARALLEL_LINK_JOBS=4",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-DCMAKE_BUILD_TYPE='Release'"))
+        .stdout(predicate::str::contains("-DLLVM_USE_SANITIZER='Address'"))
+        .stdout(predicate::str::contains("-DLLVM_ENABLE_ASSERTIONS='On'"))
+        .stdout(predicate::str::is_match(
+            "(?s)-DLLVM_PARALLEL_LINK_JOBS=2.*-DLLVM_PARALLEL_LINK_JOBS=4",
+        )?);
+    Ok(())
+}
+
+/// Tests building with an unknown profile.
+///
+/// This test verifies that an unknown profile is reported with the available ones.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn unknown_profile() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory
+        .child(compiler_llvm_builder::Config::FILE_NAME)
+        .write_str(CONFIG)?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--profile", "ci-tsan"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Profile `ci-tsan` is not defined"));
+    Ok(())
+}
+
+/// Tests the profile lists against the same command-line arguments.
+///
+/// This test verifies that the profile targets and projects extend the mandatory ones the
+/// same way as the command-line arguments, so both plans are identical.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn profile_matches_arguments() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory
+        .child(compiler_llvm_builder::Config::FILE_NAME)
+        .write_str(CONFIG)?;
+
+    let plan = |args: &[&str]| -> anyhow::Result<String> {
+        let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+        cmd.current_dir(directory.path());
+        cmd.args(["plan", "--host", "x86_64-linux-gnu"]);
+        cmd.args(args);
+        Ok(String::from_utf8(
+            cmd.assert().success().get_output().stdout.clone(),
+        )?)
+    };
+    let profile = plan(&["--profile", "evm-clang"])?;
+    assert!(profile.contains("-DLLVM_TARGETS_TO_BUILD='EraVM;EVM'"));
+    assert!(profile.contains("-DLLVM_ENABLE_PROJECTS='clang;lld'"));
+    assert_eq!(
+        profile,
+        plan(&["--targets", "EVM", "--llvm-projects", "clang"])?
+    );
+    Ok(())
+}
+
+/// Tests switching off the profile options and overriding the profile host.
+///
+/// This test verifies that the `--no-*` flags disable the options enabled by the profile,
+/// that the profile host is used, and that the host passed in the arguments overrides the
+/// profile host triple.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn profile_switches_and_hosts() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory
+        .child(compiler_llvm_builder::Config::FILE_NAME)
+        .write_str(CONFIG)?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--profile", "ci-asan", "--no-enable-assertions"]);
+    cmd.args(["--host", "x86_64-linux-gnu"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-DLLVM_ENABLE_ASSERTIONS='Off'"));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--profile", "macos", "--no-enable-tests"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "-DCMAKE_OSX_DEPLOYMENT_TARGET='11.0'",
+        ))
+        .stdout(predicate::str::contains("-DLLVM_BUILD_TESTS='Off'"));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--profile", "cross", "--host", "x86_64-linux-gnu"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"tblgen\"").not());
+    Ok(())
+}
+
+/// Tests the profile with both hosts.
+///
+/// This test verifies that the profile setting both the host and the host triple is rejected,
+/// the same way as the command-line arguments are.
+#[rstest]
+fn profile_conflicting_hosts() {
+    let error = toml::from_str::<compiler_llvm_builder::Config>(
+        "[profile.both]\nhost = \"x86_64-linux-gnu\"\nhost-triple = \"aarch64-unknown-linux-gnu\"\n",
+    )
+    .expect_err("The hosts conflict")
+    .to_string();
+    assert!(
+        error.contains("`host` cannot be used with `host-triple`"),
+        "{error}"
+    );
+}
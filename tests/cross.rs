@@ -0,0 +1,359 @@
+pub mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use compiler_llvm_builder::host::Arch;
+use compiler_llvm_builder::host::OS;
+use compiler_llvm_builder::plan::Stage;
+use compiler_llvm_builder::plan::Step;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::BuildOptions;
+use compiler_llvm_builder::Host;
+use compiler_llvm_builder::LLVMPath;
+use compiler_llvm_builder::Plan;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// Returns the plan of cross-compiling for the host on an x86_64 Linux builder.
+fn cross_plan(host: Host, options: BuildOptions) -> anyhow::Result<Plan> {
+    let options = compiler_llvm_builder::build_options::BuildOptionsBuilder::from(options)
+        .build_host(Some(Host::new(Arch::X86_64, OS::Linux, TargetEnv::GNU)))
+        .build();
+    compiler_llvm_builder::plan(
+        &options,
+        host,
+        &LLVMPath::new(Path::new("/workspace")),
+        &common::musl_source(),
+    )
+}
+
+/// Returns the arguments of all the plan commands, separated with spaces.
+fn arguments(plan: &Plan) -> String {
+    plan.stages
+        .iter()
+        .flat_map(|stage| stage.steps.iter())
+        .filter_map(|step| match step {
+            Step::Command(command) => Some(command.args.join(" ")),
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns the contents of the toolchain file written by the stage.
+fn toolchain_file(plan: &Plan, stage: &str) -> String {
+    plan.stages
+        .iter()
+        .find(|candidate| candidate.name == stage)
+        .and_then(|stage| {
+            stage.steps.iter().find_map(|step| match step {
+                Step::WriteFile { path, content }
+                    if path
+                        .extension()
+                        .is_some_and(|extension| extension == "cmake") =>
+                {
+                    Some(content.clone())
+                }
+                _ => None,
+            })
+        })
+        .expect("Always exists")
+}
+
+/// Tests cross-compiling for the aarch64 Linux GNU host.
+///
+/// This test verifies that the native table generators are built first, and that the final
+/// build uses them with the toolchain file of the cross-compilation packages.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn aarch64_linux_gnu() -> anyhow::Result<()> {
+    let options = BuildOptions::builder()
+        .llvm_projects([compiler_llvm_builder::llvm_project::LLVMProject::CLANG])
+        .build();
+    let plan = cross_plan(Host::new(Arch::AArch64, OS::Linux, TargetEnv::GNU), options)?;
+    let stages: Vec<&str> = plan
+        .stages
+        .iter()
+        .map(|stage| stage.name.as_str())
+        .collect();
+    assert_eq!(stages, vec![Stage::TBLGEN, Stage::FINAL]);
+
+    let arguments = arguments(&plan);
+    assert!(
+        arguments.contains("llvm-tblgen llvm-min-tblgen clang-tblgen"),
+        "{arguments}"
+    );
+    assert!(
+        arguments.contains("-DLLVM_HOST_TRIPLE='aarch64-unknown-linux-gnu'"),
+        "{arguments}"
+    );
+    assert!(
+        arguments
+            .contains("-DCLANG_TABLEGEN='/workspace/./target-llvm/build-tblgen/bin/clang-tblgen'"),
+        "{arguments}"
+    );
+    assert!(
+        arguments.contains(
+            "-DCMAKE_TOOLCHAIN_FILE='/workspace/./target-llvm/toolchain-aarch64-linux-gnu.cmake'"
+        ),
+        "{arguments}"
+    );
+    assert_eq!(
+        arguments.matches("-DCMAKE_C_COMPILER=").count(),
+        1,
+        "{arguments}"
+    );
+
+    let toolchain = toolchain_file(&plan, Stage::FINAL);
+    assert!(toolchain.contains("set(CMAKE_SYSTEM_PROCESSOR aarch64)"));
+    assert!(toolchain.contains("set(CMAKE_FIND_ROOT_PATH \"/usr/aarch64-linux-gnu\")"));
+    assert!(toolchain.contains("set(CMAKE_CXX_COMPILER_TARGET aarch64-unknown-linux-gnu)"));
+    assert!(!toolchain.contains("CMAKE_SYSROOT"));
+    Ok(())
+}
+
+/// Tests cross-compiling for the aarch64 Linux MUSL host.
+///
+/// This test verifies that musl is cross-compiled, and that the final build uses the stage 1
+/// host clang with the aarch64 musl sysroot.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn aarch64_linux_musl() -> anyhow::Result<()> {
+    let plan = cross_plan(
+        Host::new(Arch::AArch64, OS::Linux, TargetEnv::MUSL),
+        BuildOptions::default(),
+    )?;
+    let stages: Vec<&str> = plan
+        .stages
+        .iter()
+        .map(|stage| stage.name.as_str())
+        .collect();
+    assert_eq!(
+        stages,
+        vec![
+            Stage::MUSL,
+            Stage::CRT,
+            Stage::HOST,
+            Stage::TBLGEN,
+            Stage::FINAL
+        ]
+    );
+
+    let arguments = arguments(&plan);
+    assert!(
+        arguments.contains("--target=aarch64-unknown-linux-musl --disable-shared CC=clang"),
+        "{arguments}"
+    );
+    assert!(
+        arguments.contains("llvm-tblgen llvm-min-tblgen\n"),
+        "{arguments}"
+    );
+    assert!(!arguments.contains("clang-tblgen"), "{arguments}");
+
+    let crt_toolchain = toolchain_file(&plan, Stage::CRT);
+    assert!(crt_toolchain.contains("set(CMAKE_C_COMPILER \"clang\")"));
+    assert!(crt_toolchain.contains("set(CMAKE_TRY_COMPILE_TARGET_TYPE STATIC_LIBRARY)"));
+
+    let final_toolchain = toolchain_file(&plan, Stage::FINAL);
+    assert!(final_toolchain
+        .contains("set(CMAKE_C_COMPILER \"/workspace/./target-llvm/target-host/bin/clang\")"));
+    assert!(final_toolchain.contains("set(CMAKE_SYSROOT \"/workspace/./target-llvm/target-musl\")"));
+    assert!(!final_toolchain.contains("CMAKE_TRY_COMPILE_TARGET_TYPE"));
+    Ok(())
+}
+
+/// Tests the native plan of the builder host.
+///
+/// This test verifies that the plan is not cross-compiled if the builder architecture and
+/// operating system match the host, and that the unsupported combinations are rejected.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn native_and_unsupported() -> anyhow::Result<()> {
+    let plan = cross_plan(
+        Host::new(Arch::X86_64, OS::Linux, TargetEnv::MUSL),
+        BuildOptions::default(),
+    )?;
+    assert!(!plan.stages.iter().any(|stage| stage.name == Stage::TBLGEN));
+
+    let error = cross_plan(
+        Host::new(Arch::AArch64, OS::MacOS, TargetEnv::GNU),
+        BuildOptions::default(),
+    )
+    .expect_err("Cross-compiling for macOS is not supported")
+    .to_string();
+    assert!(
+        error.contains("Cross-compiling for `aarch64-macos` on `x86_64-linux-gnu`"),
+        "{error}"
+    );
+    Ok(())
+}
+
+/// Tests the host triple argument.
+///
+/// This test verifies that the host triple is validated, and that it cannot be combined with
+/// the host argument.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn host_triple_arguments() -> anyhow::Result<()> {
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.args(["plan", "--host-triple", "aarch64-linux-gnu"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid host triple"));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.args([
+        "plan",
+        "--host-triple",
+        "aarch64-unknown-linux-gnu",
+        "--host",
+        "aarch64-linux-gnu",
+    ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+/// Tests locating the static C++ library of the mingw-w64 sysroot.
+///
+/// This test verifies that the sysroot library is preferred, then the newest GCC version with
+/// the POSIX threads, and that a missing library is reported, also if the llvm-mingw sysroot
+/// only has `libc++.a`.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while creating the
+/// sysroot.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn find_mingw_libstdcpp() -> anyhow::Result<()> {
+    const TRIPLE: &str = "x86_64-w64-mingw32";
+
+    let root = assert_fs::TempDir::new()?;
+    let sysroot = root.path().join(TRIPLE);
+    let touch = |path: &Path| -> anyhow::Result<()> {
+        std::fs::create_dir_all(path.parent().expect("Always exists"))?;
+        std::fs::write(path, "")?;
+        Ok(())
+    };
+    std::fs::create_dir_all(&sysroot)?;
+    let error = compiler_llvm_builder::utils::find_mingw_libstdcpp(&sysroot, TRIPLE)
+        .expect_err("The library is missing")
+        .to_string();
+    assert!(error.contains("LIBSTDCPP_SOURCE_PATH"), "{error}");
+
+    touch(&sysroot.join("lib/libc++.a"))?;
+    let error = compiler_llvm_builder::utils::find_mingw_libstdcpp(&sysroot, TRIPLE)
+        .expect_err("The libc++.a is not libstdc++.a")
+        .to_string();
+    assert!(error.contains("only has libc++.a"), "{error}");
+    assert!(error.contains("LIBSTDCPP_SOURCE_PATH"), "{error}");
+
+    let gcc = root.path().join("lib/gcc").join(TRIPLE);
+    for version in ["9-posix", "12-win32", "12-posix", "13-win32"] {
+        touch(&gcc.join(version).join("libstdc++.a"))?;
+    }
+    assert!(
+        compiler_llvm_builder::utils::find_mingw_libstdcpp(&sysroot, TRIPLE)?
+            .ends_with("12-posix/libstdc++.a")
+    );
+
+    let sysroot_library = sysroot.join("lib/libstdc++.a");
+    touch(&sysroot_library)?;
+    assert_eq!(
+        compiler_llvm_builder::utils::find_mingw_libstdcpp(&sysroot, TRIPLE)?,
+        sysroot_library
+    );
+    Ok(())
+}
+
+/// Tests cross-compiling for the x86_64 Windows GNU host.
+///
+/// This test verifies that the final build uses the mingw-w64 sysroot, and that the static
+/// C++ library found there is copied into the final target directory.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+#[rstest]
+fn x86_64_windows_gnu() -> anyhow::Result<()> {
+    let root = assert_fs::TempDir::new()?;
+    let sysroot = root.path().join("x86_64-w64-mingw32");
+    std::fs::create_dir_all(sysroot.join("lib"))?;
+    std::fs::write(sysroot.join("lib/libstdc++.a"), "")?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.env("MINGW_SYSROOT", &sysroot);
+    cmd.env_remove("LIBSTDCPP_SOURCE_PATH");
+    cmd.args([
+        "plan",
+        "--host-triple",
+        "x86_64-w64-mingw32",
+        "--format",
+        "shell",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("# Stage `tblgen`"))
+        .stdout(predicate::str::contains(format!(
+            "set(CMAKE_SYSROOT \"{}\")",
+            sysroot.display()
+        )))
+        .stdout(predicate::str::contains(
+            "set(CMAKE_RC_COMPILER x86_64-w64-mingw32-windres)",
+        ))
+        .stdout(
+            predicate::str::contains("-DLLVM_HOST_TRIPLE=")
+                .and(predicate::str::contains("x86_64-w64-mingw32")),
+        )
+        .stdout(predicate::str::contains(format!(
+            "cp -f {}",
+            sysroot.join("lib/libstdc++.a").display()
+        )))
+        .stdout(predicate::str::contains("target-final/./lib/libstdc++.a"));
+    Ok(())
+}
@@ -0,0 +1,122 @@
+pub mod common;
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use compiler_llvm_builder::config::Directories;
+use compiler_llvm_builder::LLVMPath;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// Tests the workspace directories overriding.
+///
+/// This test verifies that the relative directories are resolved against the workspace root,
+/// that the absolute ones are kept, and that the build directories follow the target one.
+#[rstest]
+fn resolve() {
+    let paths = LLVMPath::new(Path::new("/workspace")).directories(&Directories {
+        source: Some(PathBuf::from("sources/llvm")),
+        host_source: None,
+        target: Some(PathBuf::from("/scratch/target-llvm")),
+    });
+    assert_eq!(
+        paths.llvm_source(),
+        PathBuf::from("/workspace/sources/llvm")
+    );
+    assert_eq!(
+        paths.llvm_host_source(),
+        Path::new("/workspace").join(LLVMPath::DIRECTORY_LLVM_HOST_SOURCE)
+    );
+    assert_eq!(
+        paths.llvm_build_final(),
+        PathBuf::from("/scratch/target-llvm/build-final")
+    );
+    assert_eq!(paths.logs(), PathBuf::from("/scratch/target-llvm/logs"));
+}
+
+/// Tests planning with the workspace directories set from several sources.
+///
+/// This test verifies that the configuration file directories are used, and that the
+/// environment variables take precedence over them.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_with_directories() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory
+        .child(compiler_llvm_builder::Config::FILE_NAME)
+        .write_str("[directories]\nsource = \"sources/llvm\"\ntarget = \"config-target\"\n")?;
+    let root = directory.path().canonicalize()?;
+    let target = assert_fs::TempDir::new()?;
+    let target = target.path().canonicalize()?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(root.as_path());
+    cmd.env("ZKSYNC_LLVM_TARGET_DIR", target.as_os_str());
+    cmd.args(["plan", "--host", "x86_64-linux-gnu"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "{}/sources/llvm/llvm",
+            root.to_string_lossy()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "{}/build-final",
+            target.to_string_lossy()
+        )))
+        .stdout(predicate::str::contains("config-target").not());
+    Ok(())
+}
+
+/// Tests planning with the workspace directories passed as arguments.
+///
+/// This test verifies that the arguments are resolved against the current directory.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_with_arguments() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let root = directory.path().canonicalize()?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(root.as_path());
+    cmd.args([
+        "plan",
+        "--host",
+        "x86_64-linux-gnu",
+        "--llvm-source-dir",
+        "llvm-main",
+        "--llvm-target-dir",
+        "out",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "{}/llvm-main/llvm",
+            root.to_string_lossy()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "{}/out/build-final",
+            root.to_string_lossy()
+        )));
+    assert!(!root.join("out").exists());
+    Ok(())
+}
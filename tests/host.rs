@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use compiler_llvm_builder::host::Arch;
+use compiler_llvm_builder::host::OS;
+use compiler_llvm_builder::lock::MuslSource;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::BuildOptions;
+use compiler_llvm_builder::Host;
+use compiler_llvm_builder::LLVMPath;
+use rstest::rstest;
+
+/// Tests the host parsing and printing.
+///
+/// This test verifies that the host string is parsed into its parts and printed back
+/// in the canonical form, with the environment defaulting to `gnu`.
+#[rstest]
+#[case(
+    "x86_64-linux-gnu",
+    Host::new(Arch::X86_64, OS::Linux, TargetEnv::GNU),
+    "x86_64-linux-gnu"
+)]
+#[case(
+    "aarch64-linux-musl",
+    Host::new(Arch::AArch64, OS::Linux, TargetEnv::MUSL),
+    "aarch64-linux-musl"
+)]
+#[case(
+    "x86_64-windows",
+    Host::new(Arch::X86_64, OS::Windows, TargetEnv::GNU),
+    "x86_64-windows-gnu"
+)]
+#[case(
+    "aarch64-macos",
+    Host::new(Arch::AArch64, OS::MacOS, TargetEnv::GNU),
+    "aarch64-macos"
+)]
+fn parse(#[case] value: &str, #[case] expected: Host, #[case] canonical: &str) {
+    let host = Host::from_str(value).expect("Valid host");
+    assert_eq!(host, expected);
+    assert_eq!(host.to_string(), canonical);
+}
+
+/// Tests the invalid host parsing.
+///
+/// This test verifies that malformed hosts and unknown parts are rejected.
+#[rstest]
+#[case("x86_64")]
+#[case("riscv64-linux-gnu")]
+#[case("x86_64-freebsd")]
+#[case("x86_64-linux-uclibc")]
+#[case("x86_64-linux-gnu-extra")]
+fn parse_invalid(#[case] value: &str) {
+    assert!(Host::from_str(value).is_err());
+}
+
+/// Tests planning for a host other than the running one.
+///
+/// This test verifies that the platform is selected by the host at run time, so the
+/// macOS plan can be generated on any machine.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn plan_other_host() -> anyhow::Result<()> {
+    let options = BuildOptions::default();
+    let paths = LLVMPath::new(Path::new("."));
+    let plan = compiler_llvm_builder::plan(
+        &options,
+        Host::new(Arch::AArch64, OS::MacOS, TargetEnv::GNU),
+        &paths,
+        &MuslSource::default(),
+    )?;
+    let shell = plan.to_shell_script();
+    assert!(shell.contains("-DCMAKE_OSX_DEPLOYMENT_TARGET="));
+
+    assert!(compiler_llvm_builder::plan(
+        &options,
+        Host::new(Arch::AArch64, OS::Windows, TargetEnv::GNU),
+        &paths,
+        &MuslSource::default(),
+    )
+    .is_err());
+    Ok(())
+}
+
+/// Tests cloning for a host other than the running one.
+///
+/// This test verifies that the LLVM host repository is cloned only for the Linux MUSL hosts,
+/// regardless of the running system.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// clone command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+#[case(Host::new(Arch::X86_64, OS::Linux, TargetEnv::MUSL), 2)]
+#[case(Host::new(Arch::AArch64, OS::Linux, TargetEnv::MUSL), 2)]
+#[case(Host::new(Arch::X86_64, OS::Linux, TargetEnv::GNU), 1)]
+#[case(Host::new(Arch::AArch64, OS::MacOS, TargetEnv::GNU), 1)]
+fn clone_other_host(#[case] host: Host, #[case] clones: usize) -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let paths = LLVMPath::new(directory.path());
+    let lock = compiler_llvm_builder::Lock {
+        url: "https://github.com/matter-labs/era-compiler-llvm".to_owned(),
+        branch: "main".to_owned(),
+        r#ref: None,
+        source: None,
+        host: None,
+        musl: None,
+    };
+
+    let mut runner = compiler_llvm_builder::runner::RecordingRunner::default();
+    compiler_llvm_builder::clone(lock, false, host, &paths, &mut runner)?;
+    assert_eq!(
+        runner
+            .commands
+            .iter()
+            .filter(|command| command.args.first().map(String::as_str) == Some("clone"))
+            .count(),
+        clones
+    );
+    Ok(())
+}
+
+/// Tests the host triple parsing and printing.
+///
+/// This test verifies that the LLVM target triples are parsed into the hosts and printed
+/// back, and that the triples of unknown vendors and systems are rejected.
+#[rstest]
+#[case(
+    "aarch64-unknown-linux-gnu",
+    Some(Host::new(Arch::AArch64, OS::Linux, TargetEnv::GNU))
+)]
+#[case(
+    "aarch64-unknown-linux-musl",
+    Some(Host::new(Arch::AArch64, OS::Linux, TargetEnv::MUSL))
+)]
+#[case(
+    "x86_64-w64-mingw32",
+    Some(Host::new(Arch::X86_64, OS::Windows, TargetEnv::GNU))
+)]
+#[case(
+    "aarch64-apple-darwin",
+    Some(Host::new(Arch::AArch64, OS::MacOS, TargetEnv::GNU))
+)]
+#[case("aarch64-linux-gnu", None)]
+#[case("aarch64-unknown-freebsd-gnu", None)]
+fn parse_triple(#[case] value: &str, #[case] expected: Option<Host>) {
+    let host = Host::from_triple(value).ok();
+    assert_eq!(host, expected);
+    if let Some(host) = host {
+        assert_eq!(host.triple(), value);
+    }
+}
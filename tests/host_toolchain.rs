@@ -0,0 +1,545 @@
+pub mod common;
+
+use std::process::Command;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use assert_cmd::prelude::*;
+use compiler_llvm_builder::cache::Cache;
+use compiler_llvm_builder::host_toolchain::HostToolchain;
+use compiler_llvm_builder::host_toolchain::HostToolchainEntry;
+use compiler_llvm_builder::journal::Journal;
+use compiler_llvm_builder::lock::MuslSource;
+use compiler_llvm_builder::LLVMPath;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// The host LLVM test commit.
+const COMMIT: &str = "6009708b4367171ccdbf4b5905cb6a803753fe18";
+
+/// Creates the complete cache entry with the commit, last used the specified time ago.
+fn complete_entry(cache: &Cache, commit: &str, unused_for: Duration) -> anyhow::Result<String> {
+    let mut toolchain = HostToolchain::new(
+        "x86_64-linux-musl".to_owned(),
+        commit.to_owned(),
+        &common::musl_source(),
+        vec!["cmake version 3.28.3".to_owned()],
+    )?;
+    let directory = cache.host_toolchain(toolchain.key.as_str());
+    for name in HostToolchain::DIRECTORIES.into_iter() {
+        std::fs::create_dir_all(directory.join(name).join("bin"))?;
+    }
+    toolchain.save(directory.as_path())?;
+    HostToolchain::touch(directory.as_path())?;
+    std::fs::File::options()
+        .write(true)
+        .open(directory.join(HostToolchain::LAST_USED_FILE_NAME))?
+        .set_modified(SystemTime::now() - unused_for)?;
+    Ok(toolchain.key)
+}
+
+/// Tests the host toolchain cache key.
+///
+/// This test verifies that the key only depends on the toolchain inputs.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while computing the
+/// cache key.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn key() -> anyhow::Result<()> {
+    let toolchain = |commit: &str, musl: MuslSource| {
+        HostToolchain::new(
+            "x86_64-linux-musl".to_owned(),
+            commit.to_owned(),
+            &musl,
+            vec![],
+        )
+        .map(|toolchain| toolchain.key)
+    };
+    let key = toolchain(COMMIT, MuslSource::default())?;
+    assert_eq!(key.len(), 32);
+    assert_eq!(toolchain(COMMIT, MuslSource::default())?, key);
+    assert_ne!(toolchain("0000000", MuslSource::default())?, key);
+    assert_ne!(
+        toolchain(
+            COMMIT,
+            MuslSource {
+                version: "1.2.4".to_owned(),
+                sha256: Some("0".repeat(64)),
+                ..MuslSource::default()
+            }
+        )?,
+        key
+    );
+    Ok(())
+}
+
+/// Tests the stage 1 target directories of the shared host toolchain.
+///
+/// This test verifies that only the MUSL, CRT, and host targets are moved to the cache,
+/// while the build directories stay in the workspace.
+#[rstest]
+fn paths() {
+    let paths = LLVMPath::new(std::path::Path::new("/workspace"))
+        .host_toolchain(std::path::Path::new("/cache/host/key"));
+    assert!(paths.musl_target().starts_with("/cache/host/key"));
+    assert!(paths.llvm_target_crt().starts_with("/cache/host/key"));
+    assert!(paths.llvm_target_host().starts_with("/cache/host/key"));
+    assert!(paths.llvm_build_host().starts_with("/workspace"));
+    assert!(paths.llvm_target_final().starts_with("/workspace"));
+}
+
+/// Tests pruning the host toolchains.
+///
+/// This test verifies that the stale and incomplete entries are removed, while the recently
+/// used ones and the ones being populated by another build are kept.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// cache commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn prune() -> anyhow::Result<()> {
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), false);
+    let fresh = complete_entry(&cache, COMMIT, Duration::from_secs(60))?;
+    let stale = complete_entry(&cache, "0000000", Duration::from_secs(86400 * 60))?;
+    std::fs::create_dir_all(cache.host_toolchain("incomplete"))?;
+    std::fs::create_dir_all(cache.host_toolchain("populated"))?;
+    let _lock = HostToolchainEntry::lock(cache.host_toolchain("populated").as_path())?;
+
+    let removed: Vec<String> = HostToolchainEntry::prune(&cache, Duration::from_secs(86400 * 30))?
+        .into_iter()
+        .map(|entry| {
+            entry
+                .directory
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    let mut expected = vec!["incomplete".to_owned(), stale];
+    expected.sort();
+    assert_eq!(removed, expected);
+    assert!(cache.host_toolchain(fresh.as_str()).exists());
+    assert!(cache.host_toolchain("populated").exists());
+    for key in expected.iter() {
+        assert!(!cache.host_toolchains().join(format!("{key}.lock")).exists());
+    }
+    Ok(())
+}
+
+/// Tests locking the entry while it is pruned.
+///
+/// This test verifies that the build waiting for the lock file removed with the pruned entry
+/// locks the new lock file, so no other build can lock the entry at the same time.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while locking
+/// the entry.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn lock_pruned() -> anyhow::Result<()> {
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), false);
+    let directory = cache.host_toolchain("pruned");
+    let lock_path = cache.host_toolchains().join("pruned.lock");
+
+    let lock = HostToolchainEntry::lock(directory.as_path())?;
+    let waiting_directory = directory.clone();
+    let waiting = std::thread::spawn(move || HostToolchainEntry::lock(waiting_directory.as_path()));
+    std::thread::sleep(Duration::from_millis(200));
+    std::fs::remove_file(lock_path.as_path())?;
+    drop(lock);
+    let _waiting_lock = waiting.join().expect("The thread does not panic")?;
+
+    let other = std::fs::File::open(lock_path.as_path())?;
+    assert!(!fs4::fs_std::FileExt::try_lock_exclusive(&other)?);
+    Ok(())
+}
+
+/// Tests removing the host toolchain with the CLI.
+///
+/// This test verifies that only the entry with the key is removed with its lock file, and
+/// that the unknown key is an error.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the cache commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn remove() -> anyhow::Result<()> {
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), false);
+    let removed = complete_entry(&cache, COMMIT, Duration::ZERO)?;
+    let kept = complete_entry(&cache, "0000000", Duration::ZERO)?;
+
+    let cache_command = |key: &str| -> anyhow::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+        cmd.arg("--cache-dir")
+            .arg(cache_directory.path())
+            .args(["cache", "remove", key]);
+        Ok(cmd.assert())
+    };
+    cache_command(removed.as_str())?
+        .success()
+        .stdout(predicate::str::contains("Removed "));
+    assert!(!cache.host_toolchain(removed.as_str()).exists());
+    assert!(!cache
+        .host_toolchains()
+        .join(format!("{removed}.lock"))
+        .exists());
+    assert!(cache.host_toolchain(kept.as_str()).exists());
+    cache_command(removed.as_str())?
+        .failure()
+        .stderr(predicate::str::contains("is not in the cache"));
+    Ok(())
+}
+
+/// Tests forcing the host stages with the shared host toolchain.
+///
+/// This test verifies that the complete cache entry is not rebuilt in place while the other
+/// builds may be using it.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the build.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn forced_shared_entry() -> anyhow::Result<()> {
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), false);
+    let key = complete_entry(&cache, COMMIT, Duration::ZERO)?;
+    let directory = cache.host_toolchain(key.as_str());
+    let workspace = assert_fs::TempDir::new()?;
+    let paths = LLVMPath::new(workspace.path());
+    HostToolchain::load(directory.as_path())?
+        .expect("The entry is complete")
+        .write(paths.imported_host_toolchain().as_path())?;
+
+    let mut runner = compiler_llvm_builder::runner::RecordingRunner::default()
+        .side_effects(true)
+        .cache(cache.clone());
+    let error = compiler_llvm_builder::build(
+        compiler_llvm_builder::BuildOptions::default(),
+        compiler_llvm_builder::Host::new(
+            compiler_llvm_builder::host::Arch::X86_64,
+            compiler_llvm_builder::host::OS::Linux,
+            compiler_llvm_builder::target_env::TargetEnv::MUSL,
+        ),
+        &paths,
+        &common::musl_source(),
+        &compiler_llvm_builder::plan::StageSelection {
+            only_stage: Some("host".to_owned()),
+            ..Default::default()
+        },
+        &mut runner,
+    )
+    .expect_err("The host stages of the shared entry are forced")
+    .to_string();
+    assert!(error.contains("cannot be forced"), "{error}");
+    assert!(
+        error.contains(format!("`zksync-llvm cache remove {key}`").as_str()),
+        "{error}"
+    );
+    assert!(HostToolchain::load(directory.as_path())?.is_some());
+    assert!(runner
+        .commands
+        .iter()
+        .all(|command| command.program != "cmake"));
+    Ok(())
+}
+
+/// Tests listing and pruning the host toolchains with the CLI.
+///
+/// This test verifies that the complete and incomplete entries are listed, and that all the
+/// entries are removed when pruned with a zero duration.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the cache commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn list_and_prune() -> anyhow::Result<()> {
+    let cache_directory = assert_fs::TempDir::new()?;
+    let cache = Cache::new(cache_directory.path(), false);
+    let key = complete_entry(&cache, COMMIT, Duration::ZERO)?;
+    std::fs::create_dir_all(cache.host_toolchain("incomplete"))?;
+
+    let cache_command = |args: &[&str]| -> anyhow::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+        cmd.arg("--cache-dir")
+            .arg(cache_directory.path())
+            .arg("cache")
+            .args(args);
+        Ok(cmd.assert().success())
+    };
+    cache_command(&["list"])?
+        .stdout(predicate::str::contains(format!(
+            "{key}  x86_64-linux-musl  musl-1.2.3  LLVM {COMMIT}  last used "
+        )))
+        .stdout(predicate::str::contains("incomplete  last modified "));
+    cache_command(&["prune", "--unused-for", "0s"])?
+        .stdout(predicate::str::contains("Removed 2 host toolchain(s)"));
+    cache_command(&["list"])?.stdout(predicate::str::contains("No host toolchains"));
+    Ok(())
+}
+
+/// Tests exporting and importing the host toolchain.
+///
+/// This test verifies that the toolchain is unpacked into the cache entry of its key with
+/// the symbolic links preserved, that the host compiler is pointed at the imported musl, and
+/// that the workspace uses the imported toolchain without the host source.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the import command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn export_and_import() -> anyhow::Result<()> {
+    let exporter = assert_fs::TempDir::new()?;
+    let exporter_cache = Cache::new(exporter.path(), false);
+    let key = complete_entry(&exporter_cache, COMMIT, Duration::ZERO)?;
+    let directory = exporter_cache.host_toolchain(key.as_str());
+    std::fs::write(directory.join("target-host/bin/clang-17"), "clang")?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("clang-17", directory.join("target-host/bin/clang"))?;
+    let archive = exporter.path().join("host-toolchain.tar.zst");
+    HostToolchainEntry::export(
+        &HostToolchain::load(directory.as_path())?.expect("The entry is complete"),
+        directory.as_path(),
+        archive.as_path(),
+    )?;
+
+    let workspace = assert_fs::TempDir::new()?;
+    let importer = assert_fs::TempDir::new()?;
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(workspace.path())
+        .arg("--cache-dir")
+        .arg(importer.path())
+        .args(["host", "import"])
+        .arg(archive.as_path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Imported the host toolchain `{key}`"
+        )));
+
+    let imported = Cache::new(importer.path(), false).host_toolchain(key.as_str());
+    assert!(HostToolchain::load(imported.as_path())?.is_some());
+    #[cfg(unix)]
+    assert_eq!(
+        std::fs::read_link(imported.join("target-host/bin/clang"))?,
+        std::path::Path::new("clang-17")
+    );
+    assert_eq!(
+        std::fs::read_to_string(imported.join("target-host/bin/clang.cfg"))?,
+        format!("--sysroot={}\n", imported.join("target-musl").display())
+    );
+
+    let paths = LLVMPath::new(workspace.path());
+    let toolchain = compiler_llvm_builder::host_toolchain(
+        compiler_llvm_builder::Host::new(
+            compiler_llvm_builder::host::Arch::X86_64,
+            compiler_llvm_builder::host::OS::Linux,
+            compiler_llvm_builder::target_env::TargetEnv::MUSL,
+        ),
+        &paths,
+        &common::musl_source(),
+        &mut compiler_llvm_builder::runner::RecordingRunner::default(),
+    )?;
+    assert_eq!(toolchain.map(|toolchain| toolchain.key), Some(key));
+    Ok(())
+}
+
+/// Tests exporting, importing, and building with the host toolchain of the workspace.
+///
+/// This test verifies that the toolchain is exported from the workspace where it has been
+/// built, imported into a fresh workspace with its stages recorded as imported, and that the
+/// build only runs the stages after it, all without the shared cache.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the host and build commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[cfg(unix)]
+#[rstest]
+fn export_import_build_workspace() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tools = assert_fs::TempDir::new()?;
+    let log = tools.path().join("commands.log");
+    for tool in ["cmake", "ninja", "clang", "clang++", "lld"] {
+        let path = tools.path().join(tool);
+        std::fs::write(
+            path.as_path(),
+            format!(
+                "#!/bin/sh\necho \"{tool} $*\" >> '{}'\necho '{tool} version 1.0'\n",
+                log.display()
+            ),
+        )?;
+        std::fs::set_permissions(path.as_path(), std::fs::Permissions::from_mode(0o755))?;
+    }
+    let path = format!(
+        "{}:{}",
+        tools.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let zksync_llvm = |workspace: &std::path::Path, args: &[&str]| -> anyhow::Result<Command> {
+        let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+        cmd.current_dir(workspace)
+            .env("PATH", path.as_str())
+            .env_remove("ZKSYNC_LLVM_CACHE_DIR")
+            .args(args);
+        Ok(cmd)
+    };
+
+    let exporter = assert_fs::TempDir::new()?;
+    common::create_musl_lockfile(exporter.path())?;
+    let exporter_paths = LLVMPath::new(exporter.path());
+    let host_source = exporter_paths.llvm_host_source();
+    std::fs::create_dir_all(host_source.as_path())?;
+    for args in [
+        vec!["init", "--quiet"],
+        vec!["commit", "--quiet", "--allow-empty", "--message", "Initial"],
+    ] {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(host_source.as_path())
+            .status()?;
+        anyhow::ensure!(status.success());
+    }
+    for name in HostToolchain::DIRECTORIES.into_iter() {
+        for directory in ["bin", "lib"] {
+            std::fs::create_dir_all(exporter_paths.llvm_target().join(name).join(directory))?;
+        }
+    }
+    std::fs::create_dir_all(
+        exporter_paths
+            .llvm_target_host()
+            .join("lib/x86_64-pc-linux-musl"),
+    )?;
+    let mut journal = Journal::default();
+    for stage in HostToolchain::STAGES.into_iter() {
+        journal.complete(stage, "fingerprint".to_owned());
+    }
+    journal.save(exporter_paths.journal().as_path())?;
+
+    let archive = exporter.path().join("host-toolchain.tar.zst");
+    zksync_llvm(
+        exporter.path(),
+        &[
+            "host",
+            "export",
+            "host-toolchain.tar.zst",
+            "--host",
+            "x86_64-linux-musl",
+        ],
+    )?
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("Exported the host toolchain"));
+
+    let importer = assert_fs::TempDir::new()?;
+    common::create_musl_lockfile(importer.path())?;
+    let importer_paths = LLVMPath::new(importer.path());
+    zksync_llvm(importer.path(), &["host", "import"])?
+        .arg(archive.as_path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported the host toolchain"));
+    assert_eq!(
+        std::fs::read_to_string(
+            importer_paths
+                .llvm_target_host()
+                .join("bin")
+                .join("clang.cfg")
+        )?,
+        format!("--sysroot={}\n", importer_paths.musl_target().display())
+    );
+    let journal = Journal::load(importer_paths.journal().as_path())?;
+    for stage in HostToolchain::STAGES.into_iter() {
+        assert!(journal.is_imported(stage), "{stage}");
+    }
+
+    zksync_llvm(importer.path(), &["build", "--host", "x86_64-linux-musl"])?
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Stage `host` is imported. Skipping...",
+        ));
+    let commands = std::fs::read_to_string(log.as_path())?;
+    assert!(commands.contains("cmake "), "{commands}");
+    assert!(!commands.contains("build-host"), "{commands}");
+    assert!(!commands.contains("build-crt"), "{commands}");
+    Ok(())
+}
+
+/// Tests importing a corrupted host toolchain archive.
+///
+/// This test verifies that the archive with a tampered manifest is rejected and leaves
+/// nothing in the cache.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// cache commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn import_corrupted() -> anyhow::Result<()> {
+    let exporter = assert_fs::TempDir::new()?;
+    let exporter_cache = Cache::new(exporter.path(), false);
+    let key = complete_entry(&exporter_cache, COMMIT, Duration::ZERO)?;
+    let directory = exporter_cache.host_toolchain(key.as_str());
+    let mut toolchain = HostToolchain::load(directory.as_path())?.expect("Always exists");
+    toolchain.commit = "0000000".to_owned();
+    let archive = exporter.path().join("host-toolchain.tar.zst");
+    HostToolchainEntry::export(&toolchain, directory.as_path(), archive.as_path())?;
+
+    let importer = assert_fs::TempDir::new()?;
+    let cache = Cache::new(importer.path(), false);
+    let error = HostToolchainEntry::import(&cache, archive.as_path())
+        .expect_err("The manifest is tampered")
+        .to_string();
+    assert!(error.contains("the key does not match"), "{error}");
+    assert!(HostToolchainEntry::list(&cache)?.is_empty());
+    Ok(())
+}
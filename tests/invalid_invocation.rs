@@ -1,3 +1,5 @@
+#![allow(clippy::comparison_to_empty, clippy::uninlined_format_args)]
+
 pub mod common;
 
 use std::process::Command;
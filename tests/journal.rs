@@ -0,0 +1,114 @@
+use compiler_llvm_builder::journal::Journal;
+use compiler_llvm_builder::plan::CommandStep;
+use compiler_llvm_builder::plan::StageSelection;
+use compiler_llvm_builder::plan::Step;
+use compiler_llvm_builder::runner::RecordingRunner;
+use compiler_llvm_builder::Plan;
+
+/// Creates a two-stage plan with the specified argument of the second stage.
+fn plan(argument: &str) -> Plan {
+    Plan::new(&["sh"])
+        .stage(
+            "first",
+            None,
+            vec![Step::Command(CommandStep::new("true", "first"))],
+        )
+        .stage(
+            "second",
+            None,
+            vec![Step::Command(
+                CommandStep::new("true", "second").arg(argument),
+            )],
+        )
+}
+
+/// Runs the plan and returns the descriptions of the commands run.
+fn run(
+    plan: &Plan,
+    selection: &StageSelection,
+    journal: &std::path::Path,
+) -> anyhow::Result<Vec<String>> {
+    let mut runner = RecordingRunner::default()
+        .side_effects(true)
+        .output_on(&[], "1.0.0");
+    plan.execute(&mut runner, selection, journal)?;
+    Ok(runner
+        .commands
+        .into_iter()
+        .map(|command| command.description)
+        .collect())
+}
+
+/// Tests skipping the completed stages.
+///
+/// This test verifies that the stages completed with the same inputs are skipped, that
+/// changing a stage input re-runs it, and that the selection flags force the stages.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// build.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn resume() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let journal = directory.path().join(Journal::FILE_NAME);
+    let all = StageSelection::default();
+
+    assert_eq!(
+        run(&plan("a"), &all, journal.as_path())?,
+        vec!["first", "second"]
+    );
+    assert_eq!(Journal::load(journal.as_path())?.stages.len(), 2);
+    assert!(run(&plan("a"), &all, journal.as_path())?.is_empty());
+    assert_eq!(run(&plan("b"), &all, journal.as_path())?, vec!["second"]);
+
+    let from_first = StageSelection {
+        from_stage: Some("first".to_owned()),
+        only_stage: None,
+    };
+    assert_eq!(
+        run(&plan("b"), &from_first, journal.as_path())?,
+        vec!["first", "second"]
+    );
+
+    let only_second = StageSelection {
+        from_stage: None,
+        only_stage: Some("second".to_owned()),
+    };
+    assert_eq!(
+        run(&plan("b"), &only_second, journal.as_path())?,
+        vec!["second"]
+    );
+    Ok(())
+}
+
+/// Tests selecting an unknown stage.
+///
+/// This test verifies that the unknown stage name is rejected with the list of stages.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// build.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn unknown_stage() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let journal = directory.path().join(Journal::FILE_NAME);
+    let selection = StageSelection {
+        from_stage: Some("host".to_owned()),
+        only_stage: None,
+    };
+    let error = run(&plan("a"), &selection, journal.as_path()).expect_err("Unknown stage");
+    assert!(error
+        .to_string()
+        .contains("Unknown stage `host`. Available stages: first, second"));
+    Ok(())
+}
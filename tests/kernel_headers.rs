@@ -0,0 +1,279 @@
+use compiler_llvm_builder::host::Arch;
+use compiler_llvm_builder::host::OS;
+use compiler_llvm_builder::lock::KernelHeaders;
+use compiler_llvm_builder::lock::MuslSource;
+use compiler_llvm_builder::plan::Stage;
+use compiler_llvm_builder::plan::Step;
+use compiler_llvm_builder::runner::RealRunner;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::BuildOptions;
+use compiler_llvm_builder::Host;
+use compiler_llvm_builder::LLVMPath;
+use compiler_llvm_builder::Lock;
+use rstest::rstest;
+
+/// The placeholder SHA-256 of the kernel release tarball.
+const KERNEL_SHA256: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Returns the MUSL stage steps planned for the host with the musl source.
+fn musl_steps(arch: Arch, musl: &MuslSource) -> anyhow::Result<Vec<Step>> {
+    let plan = compiler_llvm_builder::plan(
+        &BuildOptions::default(),
+        Host::new(arch, OS::Linux, TargetEnv::MUSL),
+        &LLVMPath::new(std::path::Path::new("/workspace")),
+        musl,
+    )?;
+    Ok(plan
+        .stages
+        .into_iter()
+        .find(|stage| stage.name == Stage::MUSL)
+        .expect("Always exists")
+        .steps)
+}
+
+/// Tests the kernel headers table of the lock file.
+///
+/// This test verifies that the pinned release and the directory are parsed, and that the
+/// built-in release is used if the table is omitted.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while parsing the
+/// lock file.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn parse() -> anyhow::Result<()> {
+    let lock: Lock = toml::from_str(
+        r#"
+url = "https://github.com/matter-labs/era-compiler-llvm"
+branch = "main"
+
+[musl.kernel-headers]
+version = "6.6.58"
+sha256 = "0000000000000000000000000000000000000000000000000000000000000000"
+"#,
+    )?;
+    let musl = lock.musl.expect("Always exists");
+    let kernel_headers = musl.kernel_headers.clone().expect("Always exists");
+    assert_eq!(
+        kernel_headers.tarball_url("6.6.58"),
+        "https://cdn.kernel.org/pub/linux/kernel/v6.x/linux-6.6.58.tar.gz"
+    );
+    assert_eq!(
+        musl.kernel_headers_description(),
+        format!("linux-6.6.58 {KERNEL_SHA256}")
+    );
+
+    let lock: Lock = toml::from_str(
+        "url = \"\"\nbranch = \"main\"\n\n[musl.kernel-headers]\npath = \"sysroot/include\"\n",
+    )?;
+    assert_eq!(
+        lock.musl
+            .expect("Always exists")
+            .kernel_headers
+            .expect("Always exists")
+            .path,
+        Some(std::path::PathBuf::from("sysroot/include"))
+    );
+    assert_eq!(
+        MuslSource::default().kernel_headers_description(),
+        MuslSource {
+            kernel_headers: Some(KernelHeaders {
+                version: Some(compiler_llvm_builder::utils::LINUX_VERSION.to_owned()),
+                ..KernelHeaders::default()
+            }),
+            ..MuslSource::default()
+        }
+        .kernel_headers_description()
+    );
+    Ok(())
+}
+
+/// Tests planning the kernel headers installation.
+///
+/// This test verifies that the pinned release, which is also the default one, is downloaded,
+/// installed for the kernel architecture of the host, and copied into the musl sysroot
+/// instead of the build system headers.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+#[case(Arch::X86_64, "ARCH=x86", "x86_64-linux-gnu", Some("6.6.58"))]
+#[case(Arch::AArch64, "ARCH=arm64", "aarch64-linux-gnu", Some("6.6.58"))]
+#[case(Arch::X86_64, "ARCH=x86", "x86_64-linux-gnu", None)]
+fn plan_pinned(
+    #[case] arch: Arch,
+    #[case] kernel_arch: &str,
+    #[case] multiarch: &str,
+    #[case] version: Option<&str>,
+) -> anyhow::Result<()> {
+    let musl = MuslSource {
+        kernel_headers: Some(KernelHeaders {
+            version: version.map(str::to_owned),
+            sha256: Some(KERNEL_SHA256.to_owned()),
+            ..KernelHeaders::default()
+        }),
+        ..MuslSource::default()
+    };
+    let steps = musl_steps(arch, &musl)?;
+    assert!(steps.iter().any(|step| matches!(
+        step,
+        Step::Download { file_name, sha256, .. }
+            if file_name == "linux-6.6.58.tar.gz" && sha256 == KERNEL_SHA256
+    )));
+    assert!(steps.iter().any(|step| matches!(
+        step,
+        Step::Command(command)
+            if command.args.iter().any(|arg| arg == kernel_arch)
+                && command.args.iter().any(|arg| arg == "headers_install")
+    )));
+    let Some(Step::CopyKernelHeaders {
+        source,
+        multiarch: copied_multiarch,
+        destination,
+    }) = steps.last()
+    else {
+        panic!("The kernel headers are not copied last: {steps:?}");
+    };
+    assert!(source.ends_with("linux-6.6.58/install/include"));
+    assert_eq!(copied_multiarch, multiarch);
+    assert!(destination.ends_with("target-musl/include"));
+    assert!(!steps.iter().any(|step| matches!(
+        step,
+        Step::CopyKernelHeaders { source, .. } if source.starts_with("/usr/include")
+    )));
+    Ok(())
+}
+
+/// Tests planning the kernel headers copied from a directory.
+///
+/// This test verifies that the build system headers are copied only if their directory is
+/// specified explicitly, and that the relative directories are resolved against the workspace.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+#[case("/usr/include", "/usr/include")]
+#[case("sysroot/include", "/workspace/sysroot/include")]
+fn plan_directory(#[case] path: &str, #[case] expected: &str) -> anyhow::Result<()> {
+    let musl = MuslSource {
+        kernel_headers: Some(KernelHeaders {
+            path: Some(std::path::PathBuf::from(path)),
+            ..KernelHeaders::default()
+        }),
+        ..MuslSource::default()
+    };
+    let steps = musl_steps(Arch::X86_64, &musl)?;
+    assert!(!steps.iter().any(|step| matches!(
+        step,
+        Step::Download { file_name, .. } if file_name.starts_with("linux-")
+    )));
+    let Some(Step::CopyKernelHeaders { source, .. }) = steps.last() else {
+        panic!("The kernel headers are not copied last: {steps:?}");
+    };
+    assert_eq!(source, std::path::Path::new(expected));
+    Ok(())
+}
+
+/// Tests planning the kernel headers with an incomplete table.
+///
+/// This test verifies that the pinned release, including the default one, requires its
+/// SHA-256, and that the release and the directory cannot be specified together.
+#[rstest]
+#[case(Some("6.6.58"), None, None, "sha256")]
+#[case(
+    Some("6.6.58"),
+    Some(KERNEL_SHA256),
+    Some("include"),
+    "either `version` or `path`"
+)]
+#[case(None, None, None, "Linux 6.6.58")]
+fn plan_invalid(
+    #[case] version: Option<&str>,
+    #[case] sha256: Option<&str>,
+    #[case] path: Option<&str>,
+    #[case] expected: &str,
+) {
+    let musl = MuslSource {
+        kernel_headers: Some(KernelHeaders {
+            version: version.map(str::to_owned),
+            sha256: sha256.map(str::to_owned),
+            path: path.map(std::path::PathBuf::from),
+            ..KernelHeaders::default()
+        }),
+        ..MuslSource::default()
+    };
+    let error = musl_steps(Arch::X86_64, &musl)
+        .expect_err("The kernel headers table is invalid")
+        .to_string();
+    assert!(error.contains(expected), "{error}");
+}
+
+/// Tests copying the kernel headers.
+///
+/// This test verifies that the architecture specific `asm` headers are taken from the
+/// multiarch directory of the system headers, or from the top level of the installed ones.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while copying the
+/// headers.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+#[case::multiarch(true)]
+#[case::flat(false)]
+fn copy(#[case] multiarch: bool) -> anyhow::Result<()> {
+    let source = assert_fs::TempDir::new()?;
+    let write = |path: &str, content: &str| -> anyhow::Result<()> {
+        let path = source.path().join(path);
+        std::fs::create_dir_all(path.parent().expect("Always exists"))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    };
+    write("linux/types.h", "linux")?;
+    write("asm-generic/types.h", "asm-generic")?;
+    write("asm/types.h", "flat")?;
+    if multiarch {
+        write("x86_64-linux-gnu/asm/types.h", "multiarch")?;
+    }
+
+    let destination = assert_fs::TempDir::new()?;
+    let include = destination.path().join("include");
+    Step::CopyKernelHeaders {
+        source: source.path().to_path_buf(),
+        multiarch: "x86_64-linux-gnu".to_owned(),
+        destination: include.clone(),
+    }
+    .execute(&mut RealRunner::new(false))?;
+    assert_eq!(
+        std::fs::read_to_string(include.join("linux/types.h"))?,
+        "linux"
+    );
+    assert_eq!(
+        std::fs::read_to_string(include.join("asm-generic/types.h"))?,
+        "asm-generic"
+    );
+    assert_eq!(
+        std::fs::read_to_string(include.join("asm/types.h"))?,
+        if multiarch { "multiarch" } else { "flat" }
+    );
+    Ok(())
+}
@@ -0,0 +1,156 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use compiler_llvm_builder::runner::RealRunner;
+use compiler_llvm_builder::BuildReport;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// The test lock file pointing to a local source tree.
+const LOCK: &str = r#"
+url = "https://github.com/matter-labs/era-compiler-llvm"
+branch = "main"
+source = { path = "../era-compiler-llvm" }
+"#;
+
+/// Tests planning from the local source tree specified in the lock file.
+///
+/// This test verifies that the source path is resolved against the lock file directory
+/// and that the `--llvm-source` argument takes precedence over it.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_from_local_source() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory
+        .child("workspace")
+        .child(common::LLVM_LOCK_FILE)
+        .write_str(LOCK)?;
+    std::fs::create_dir_all(directory.path().join("era-compiler-llvm").join("llvm"))?;
+    std::fs::create_dir_all(directory.path().join("other").join("llvm"))?;
+    let root = directory.path().canonicalize()?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(root.join("workspace"));
+    cmd.args(["plan", "--host", "x86_64-linux-gnu"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "{}/workspace/../era-compiler-llvm/llvm",
+            root.to_string_lossy()
+        )));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(root.join("workspace"));
+    cmd.args([
+        "plan",
+        "--host",
+        "x86_64-linux-gnu",
+        "--llvm-source",
+        "../other",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("/../other/llvm"))
+        .stdout(predicate::str::contains("era-compiler-llvm/llvm").not());
+    Ok(())
+}
+
+/// Tests planning from a directory that is not an LLVM source tree.
+///
+/// This test verifies that the local source tree is rejected without the `llvm` directory.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_from_invalid_source() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--llvm-source", "."]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "does not contain the `llvm` directory",
+    ));
+    Ok(())
+}
+
+/// Tests cloning with the lock file pointing to a local source tree.
+///
+/// This test verifies that the local source tree is never cloned over.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the clone command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn clone_local_source() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory.child(common::LLVM_LOCK_FILE).write_str(LOCK)?;
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.arg("clone");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("which is built as is"));
+    Ok(())
+}
+
+/// Tests the build report of a local source tree.
+///
+/// This test verifies that the report records the `HEAD` commit and the uncommitted changes.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn report() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let git = |args: &[&str]| -> anyhow::Result<()> {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(directory.path())
+            .status()?;
+        anyhow::ensure!(status.success(), "git {args:?} failed");
+        Ok(())
+    };
+    git(&["init", "--quiet"])?;
+    git(&["commit", "--quiet", "--allow-empty", "--message", "Initial"])?;
+
+    let mut runner = RealRunner::new(false);
+    let report = BuildReport::new(directory.path(), "x86_64-linux-gnu".to_owned(), &mut runner);
+    assert_eq!(report.head.as_deref().map(str::len), Some(40));
+    assert!(!report.dirty);
+
+    directory.child("patch.diff").write_str("local change")?;
+    let report = BuildReport::new(directory.path(), "x86_64-linux-gnu".to_owned(), &mut runner);
+    assert!(report.dirty);
+    assert!(report.to_string().ends_with("with uncommitted changes"));
+    Ok(())
+}
@@ -0,0 +1,110 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// Tests the lock file discovery.
+///
+/// This test verifies that the lock file is found in the parent directories, and that
+/// nothing is found outside of the workspace.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn discover() -> anyhow::Result<()> {
+    let lockfile = common::create_test_tmp_lockfile(None)?;
+    let root = lockfile
+        .parent()
+        .expect("Lockfile parent dir does not exist");
+    let nested = root.join("nested").join("deeper");
+    std::fs::create_dir_all(nested.as_path())?;
+
+    assert_eq!(
+        compiler_llvm_builder::Lock::discover(nested.as_path()),
+        Some(lockfile.path().to_path_buf())
+    );
+
+    let directory = assert_fs::TempDir::new()?;
+    assert_eq!(
+        compiler_llvm_builder::Lock::discover(directory.path()),
+        None
+    );
+    Ok(())
+}
+
+/// Tests running from a subdirectory of the workspace.
+///
+/// This test verifies that the directory containing the discovered lock file becomes the
+/// workspace root, so all the paths are resolved against it.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_from_subdirectory() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory.child(common::LLVM_LOCK_FILE).write_str(
+        "url = \"https://github.com/matter-labs/era-compiler-llvm\"\nbranch = \"main\"\n",
+    )?;
+    let root = directory.path().canonicalize()?;
+    let nested = root.join("nested");
+    std::fs::create_dir_all(nested.as_path())?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(nested.as_path());
+    cmd.arg("plan");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "{}/./llvm/",
+            root.to_string_lossy()
+        )))
+        .stdout(predicate::str::contains(nested.to_string_lossy().as_ref()).not());
+    Ok(())
+}
+
+/// Tests the explicit lock file path.
+///
+/// This test verifies that the `--lock` option is accepted before and after the subcommand,
+/// and that the specified file is used instead of the discovered one.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the checkout command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+#[case(&["--lock", "missing/LLVM.lock", "checkout"])]
+#[case(&["checkout", "--lock", "missing/LLVM.lock"])]
+fn explicit_lock(#[case] args: &[&str]) -> anyhow::Result<()> {
+    let lockfile = common::create_test_tmp_lockfile(None)?;
+    let test_dir = lockfile
+        .parent()
+        .expect("Lockfile parent dir does not exist");
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(test_dir);
+    cmd.args(args);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Error opening \"missing/LLVM.lock\" file",
+    ));
+    Ok(())
+}
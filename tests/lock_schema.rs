@@ -0,0 +1,138 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use compiler_llvm_builder::lock::HostSource;
+use compiler_llvm_builder::Lock;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// Tests the lock file without the source tables.
+///
+/// This test verifies that the lock files written before the source tables were added
+/// are still parsed and fall back to the built-in sources.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while parsing the
+/// lock file.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn parse_without_sources() -> anyhow::Result<()> {
+    let lock: Lock = toml::from_str(
+        "url = \"https://github.com/matter-labs/era-compiler-llvm\"\nbranch = \"main\"\n",
+    )?;
+    assert_eq!(lock.host, None);
+    assert_eq!(lock.musl, None);
+    assert_eq!(
+        lock.musl.unwrap_or_default().name(),
+        format!("musl-{}", compiler_llvm_builder::utils::MUSL_VERSION)
+    );
+    Ok(())
+}
+
+/// Tests the lock file with the source tables.
+///
+/// This test verifies that the omitted table fields fall back to the built-in sources.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while parsing the
+/// lock file.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn parse_with_sources() -> anyhow::Result<()> {
+    let lock: Lock = toml::from_str(
+        r#"
+url = "https://github.com/matter-labs/era-compiler-llvm"
+branch = "main"
+
+[host]
+tag = "llvmorg-18.1.8"
+commit = "3b5b5c1ec4a3095ab096dd780e84d7ab81f3d7ff"
+
+[musl]
+url = "https://musl.libc.org/releases"
+version = "1.2.5"
+sha256 = "a9a118bbe84d8764da0ea0d28b3ab3fae8477fc7e4085d90102b8596fc7c75e4"
+"#,
+    )?;
+    assert_eq!(
+        lock.host,
+        Some(HostSource {
+            url: compiler_llvm_builder::utils::LLVM_HOST_SOURCE_URL.to_owned(),
+            tag: "llvmorg-18.1.8".to_owned(),
+            commit: Some("3b5b5c1ec4a3095ab096dd780e84d7ab81f3d7ff".to_owned()),
+        })
+    );
+    let musl = lock.musl.expect("Always exists");
+    assert_eq!(musl.name(), "musl-1.2.5");
+    assert_eq!(musl.url, "https://musl.libc.org/releases");
+    Ok(())
+}
+
+/// Tests the lock file with unknown fields.
+///
+/// This test verifies that the misspelled fields are rejected and named in the error.
+#[rstest]
+#[case("tag = \"x\"\n", "unknown field `tag`")]
+#[case("[musl]\nverison = \"1.2.5\"\n", "unknown field `verison`")]
+#[case("[host]\nsha = \"x\"\n", "unknown field `sha`")]
+fn parse_unknown_field(#[case] extra: &str, #[case] expected: &str) {
+    let error = toml::from_str::<Lock>(
+        format!("url = \"https://github.com/matter-labs/era-compiler-llvm\"\nbranch = \"main\"\n{extra}")
+            .as_str(),
+    )
+    .expect_err("Unknown field");
+    assert!(error.to_string().contains(expected), "{error}");
+}
+
+/// Tests planning with the musl source from the lock file.
+///
+/// This test verifies that the pinned musl release is downloaded and built.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_with_musl_source() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    directory.child(common::LLVM_LOCK_FILE).write_str(
+        r#"
+url = "https://github.com/matter-labs/era-compiler-llvm"
+branch = "main"
+
+[musl]
+url = "https://mirror.example.com/musl/"
+version = "1.2.5"
+sha256 = "a9a118bbe84d8764da0ea0d28b3ab3fae8477fc7e4085d90102b8596fc7c75e4"
+
+[musl.kernel-headers]
+sha256 = "0000000000000000000000000000000000000000000000000000000000000000"
+"#,
+    )?;
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--host", "x86_64-linux-musl"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "https://mirror.example.com/musl/musl-1.2.5.tar.gz",
+        ))
+        .stdout(predicate::str::contains("musl-1.2.5/build"));
+    Ok(())
+}
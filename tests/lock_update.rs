@@ -0,0 +1,149 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::FileWriteStr;
+use assert_fs::fixture::PathChild;
+use compiler_llvm_builder::runner::RecordingRunner;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// The remote head of the `main` branch.
+const MAIN_HEAD: &str = "1111111111111111111111111111111111111111";
+
+/// The remote head of the `release` branch.
+const RELEASE_HEAD: &str = "2222222222222222222222222222222222222222";
+
+/// The test lock file with comments and custom formatting.
+const LOCK: &str = r#"# The LLVM fork pin.
+url    = "https://github.com/matter-labs/era-compiler-llvm"
+branch = "main"   # bumped weekly
+ref    = "0000000000000000000000000000000000000000"
+"#;
+
+/// Returns the runner simulating `git ls-remote` of the LLVM repository.
+fn ls_remote_runner() -> RecordingRunner {
+    RecordingRunner::default()
+        .output_on(
+            &["refs/heads/main"],
+            format!("{MAIN_HEAD}\trefs/heads/main\n").as_str(),
+        )
+        .output_on(
+            &["refs/heads/release"],
+            format!("{RELEASE_HEAD}\trefs/heads/release\n").as_str(),
+        )
+}
+
+/// Tests pinning the branch head.
+///
+/// This test verifies that the commit is pinned with the comments and formatting intact,
+/// and that the check mode fails only for the stale pin.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// lock update command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn update() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let lock_file = directory.child(common::LLVM_LOCK_FILE);
+    lock_file.write_str(LOCK)?;
+    let mut runner = ls_remote_runner();
+
+    let error = compiler_llvm_builder::update_lock(lock_file.path(), None, true, &mut runner)
+        .expect_err("The pin is stale");
+    assert!(error.to_string().contains("is stale"));
+    assert_eq!(std::fs::read_to_string(lock_file.path())?, LOCK);
+
+    compiler_llvm_builder::update_lock(lock_file.path(), None, false, &mut runner)?;
+    assert_eq!(
+        std::fs::read_to_string(lock_file.path())?,
+        LOCK.replace("0000000000000000000000000000000000000000", MAIN_HEAD)
+    );
+    compiler_llvm_builder::update_lock(lock_file.path(), None, true, &mut runner)?;
+
+    compiler_llvm_builder::update_lock(
+        lock_file.path(),
+        Some("release".to_owned()),
+        false,
+        &mut runner,
+    )?;
+    let lock = compiler_llvm_builder::Lock::try_from(&lock_file.path().to_path_buf())?;
+    assert_eq!(lock.branch, "release");
+    assert_eq!(lock.r#ref.as_deref(), Some(RELEASE_HEAD));
+
+    assert!(compiler_llvm_builder::update_lock(
+        lock_file.path(),
+        Some("missing".to_owned()),
+        false,
+        &mut runner,
+    )
+    .is_err());
+    assert!(runner.commands.is_empty());
+    Ok(())
+}
+
+/// Tests pinning the branch head of a local repository.
+///
+/// This test verifies that the `lock update` command resolves the head with `git ls-remote`
+/// and prints the updated commit range.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the lock update command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn update_command() -> anyhow::Result<()> {
+    let remote = assert_fs::TempDir::new()?;
+    let git = |args: &[&str]| -> anyhow::Result<()> {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(remote.path())
+            .status()?;
+        anyhow::ensure!(status.success(), "git {args:?} failed");
+        Ok(())
+    };
+    git(&["init", "--quiet", "--initial-branch", "main"])?;
+    git(&["commit", "--quiet", "--allow-empty", "--message", "Initial"])?;
+
+    let directory = assert_fs::TempDir::new()?;
+    directory.child(common::LLVM_LOCK_FILE).write_str(
+        format!(
+            "url = \"{}\"\nbranch = \"main\"\n",
+            remote.path().to_string_lossy()
+        )
+        .as_str(),
+    )?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["lock", "update", "--check"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("without a commit"));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["lock", "update"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-> `main` at "));
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(directory.path());
+    cmd.args(["lock", "update", "--check"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("is up to date"));
+    Ok(())
+}
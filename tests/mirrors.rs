@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use compiler_llvm_builder::host::Arch;
+use compiler_llvm_builder::host::OS;
+use compiler_llvm_builder::mirrors::Mirror;
+use compiler_llvm_builder::mirrors::Mirrors;
+use compiler_llvm_builder::mirrors::Rewrite;
+use compiler_llvm_builder::retry::RetryPolicy;
+use compiler_llvm_builder::runner::RecordingRunner;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::Host;
+use compiler_llvm_builder::LLVMPath;
+use compiler_llvm_builder::Lock;
+use rstest::rstest;
+
+/// The pinned test commit.
+const REF: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// The LLVM repository upstream URL.
+const UPSTREAM: &str = "https://github.com/matter-labs/era-compiler-llvm";
+
+/// Returns the mirrors of the LLVM repository.
+fn mirrors() -> Mirrors {
+    Mirrors::new(
+        vec![Mirror {
+            upstream: UPSTREAM.to_owned(),
+            urls: vec![
+                "https://mirror-1.example.com/era-compiler-llvm".to_owned(),
+                "https://mirror-2.example.com/era-compiler-llvm".to_owned(),
+            ],
+        }],
+        vec![Rewrite {
+            prefix: "https://github.com/".to_owned(),
+            replacement: "https://proxy.example.com/github/".to_owned(),
+        }],
+    )
+}
+
+/// Returns the runner with the mirrors, simulating the servers that are down.
+fn mirror_runner(unavailable: &[&str]) -> RecordingRunner {
+    unavailable
+        .iter()
+        .fold(RecordingRunner::default(), |runner, url| {
+            runner.fail_on(&[url], "Could not resolve host")
+        })
+        .output_on(&[], "true\n")
+        .mirrors(mirrors())
+}
+
+/// Returns the lock pinned to the test commit.
+fn lock() -> Lock {
+    Lock {
+        url: UPSTREAM.to_owned(),
+        branch: "main".to_owned(),
+        r#ref: Some(REF.to_owned()),
+        source: None,
+        host: None,
+        musl: None,
+    }
+}
+
+/// Tests the mirror URL resolution.
+///
+/// This test verifies that the mirrors are tried before the upstream, that the upstream is
+/// rewritten by the first matching rule, and that the prefixes only match whole path segments.
+#[rstest]
+#[case(
+    "https://musl.libc.org/releases/musl-1.2.3.tar.gz",
+    vec![
+        "https://mirror.example.com/musl/musl-1.2.3.tar.gz",
+        "https://musl.libc.org/releases/musl-1.2.3.tar.gz",
+    ]
+)]
+#[case(
+    "https://musl.libc.org/releases-old/musl-1.2.3.tar.gz",
+    vec!["https://musl.libc.org/releases-old/musl-1.2.3.tar.gz"]
+)]
+#[case(
+    UPSTREAM,
+    vec![
+        "https://mirror-1.example.com/era-compiler-llvm",
+        "https://mirror-2.example.com/era-compiler-llvm",
+        "https://proxy.example.com/github/matter-labs/era-compiler-llvm",
+    ]
+)]
+#[case(
+    "https://github.com/llvm/llvm-project",
+    vec!["https://proxy.example.com/github/llvm/llvm-project"]
+)]
+fn candidates(#[case] url: &str, #[case] expected: Vec<&str>) {
+    let mut mirrors = mirrors();
+    mirrors.mirrors.push(Mirror {
+        upstream: "https://musl.libc.org/releases/".to_owned(),
+        urls: vec!["https://mirror.example.com/musl".to_owned()],
+    });
+    mirrors.rewrites.push(Rewrite {
+        prefix: "https://github.com/llvm/".to_owned(),
+        replacement: "https://unused.example.com/".to_owned(),
+    });
+    assert_eq!(mirrors.candidates(url), expected);
+}
+
+/// Tests cloning from the mirrors.
+///
+/// This test verifies that the next mirror is tried if the first one is down, and that the
+/// pinned commit is fetched from the mirrors as well.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// clone commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn clone_from_mirrors() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let paths = LLVMPath::new(directory.path());
+
+    let mut runner = mirror_runner(&["https://mirror-1.example.com/era-compiler-llvm"]);
+    compiler_llvm_builder::clone(
+        lock(),
+        false,
+        Host::new(Arch::X86_64, OS::Linux, TargetEnv::GNU),
+        &paths,
+        &mut runner,
+    )?;
+    assert_eq!(runner.commands.len(), 5);
+    assert_eq!(
+        runner.commands[1].args[5],
+        "https://mirror-2.example.com/era-compiler-llvm"
+    );
+    assert_eq!(
+        runner.commands[3].args,
+        vec![
+            "fetch",
+            "--depth",
+            "1",
+            "https://mirror-2.example.com/era-compiler-llvm",
+            REF
+        ]
+    );
+    assert_eq!(runner.commands[4].args, vec!["checkout", REF]);
+    Ok(())
+}
+
+/// Tests the checkout with all the mirrors down.
+///
+/// This test verifies that the rewritten upstream URL is tried last, and that the error
+/// lists the failures of all the mirrors.
+#[rstest]
+fn checkout_mirrors_down() {
+    let paths = LLVMPath::new(std::path::Path::new("."));
+    let mut lock = lock();
+    lock.r#ref = None;
+
+    let mut runner = mirror_runner(&[
+        "https://mirror-1.example.com/era-compiler-llvm",
+        "https://mirror-2.example.com/era-compiler-llvm",
+        "https://proxy.example.com/github/matter-labs/era-compiler-llvm",
+    ]);
+    let error = compiler_llvm_builder::checkout(lock, false, &paths, &mut runner)
+        .expect_err("All the mirrors are down")
+        .to_string();
+    assert_eq!(runner.commands.len(), 3);
+    assert!(
+        error.starts_with("LLVM repository data fetching failed from all the mirrors"),
+        "{error}"
+    );
+    assert!(
+        error.contains("https://proxy.example.com/github/matter-labs/era-compiler-llvm: "),
+        "{error}"
+    );
+}
+
+/// Tests downloading from the unreachable mirrors.
+///
+/// This test verifies that every attempt tries all the mirrors.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// download.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn download_mirrors_down() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let mirrors = Mirrors::new(
+        vec![Mirror {
+            upstream: "http://127.0.0.1:9/releases".to_owned(),
+            urls: vec!["http://127.0.0.1:9/mirror".to_owned()],
+        }],
+        vec![],
+    );
+    let policy = RetryPolicy {
+        max_attempts: 2,
+        initial_backoff: Duration::from_millis(2),
+        max_backoff: Duration::from_millis(4),
+        deadline: Duration::from_secs(60),
+    };
+    let error = compiler_llvm_builder::utils::download(
+        "http://127.0.0.1:9/releases/musl-1.2.3.tar.gz",
+        directory.path(),
+        "musl-1.2.3.tar.gz",
+        compiler_llvm_builder::utils::MUSL_SHA256,
+        &policy,
+        None,
+        Some(&mirrors),
+    )
+    .expect_err("The servers are unreachable")
+    .to_string();
+    assert!(error.contains("failed after 2 attempt(s)"), "{error}");
+    assert_eq!(
+        error
+            .matches("http://127.0.0.1:9/mirror/musl-1.2.3.tar.gz: ")
+            .count(),
+        2,
+        "{error}"
+    );
+    assert!(!directory.path().join("musl-1.2.3.tar.gz").exists());
+    Ok(())
+}
+
+/// Tests the mirrors configuration.
+///
+/// This test verifies that the mirror lists and rewrite rules are read from the
+/// configuration file.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while parsing the
+/// configuration file.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn parse() -> anyhow::Result<()> {
+    let config: compiler_llvm_builder::Config = toml::from_str(
+        r#"
+[[mirror]]
+upstream = "https://github.com/matter-labs/era-compiler-llvm"
+urls = ["https://mirror-1.example.com/era-compiler-llvm", "https://mirror-2.example.com/era-compiler-llvm"]
+
+[[rewrite]]
+prefix = "https://github.com/"
+replacement = "https://proxy.example.com/github/"
+"#,
+    )?;
+    assert_eq!(config.mirrors(), mirrors());
+    assert!(toml::from_str::<compiler_llvm_builder::Config>(
+        "[[rewrite]]\nfrom = \"https://github.com/\"\nreplacement = \"\"\n"
+    )
+    .is_err());
+    Ok(())
+}
@@ -0,0 +1,88 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use rstest::rstest;
+
+/// Tests the building plan printing in the JSON format.
+///
+/// This test verifies that the plan is printed as JSON with the final stage and that no
+/// target directory is created, as planning must not have any side effects.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_json() -> anyhow::Result<()> {
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    let directory = assert_fs::TempDir::new()?;
+    cmd.current_dir(directory.path());
+    cmd.arg("plan");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let plan: compiler_llvm_builder::Plan = serde_json::from_slice(output.as_slice())?;
+    assert!(plan
+        .stages
+        .iter()
+        .any(|stage| stage.name == compiler_llvm_builder::plan::Stage::FINAL));
+    assert!(!directory
+        .path()
+        .join(compiler_llvm_builder::LLVMPath::DIRECTORY_LLVM_TARGET)
+        .exists());
+    Ok(())
+}
+
+/// Tests the building plan printing in the shell script format.
+///
+/// This test verifies that the plan is printed as a shell script running CMake and ninja.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_shell() -> anyhow::Result<()> {
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    let directory = assert_fs::TempDir::new()?;
+    cmd.current_dir(directory.path());
+    cmd.args(["plan", "--format", "shell", "--enable-assertions"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("#!/bin/sh"))
+        .stdout(predicate::str::contains("cmake -S"))
+        .stdout(predicate::str::contains("-DLLVM_ENABLE_ASSERTIONS="))
+        .stdout(predicate::str::contains("ninja -C"));
+    Ok(())
+}
+
+/// Tests the building plan printing with an unknown format.
+///
+/// This test verifies that an unsupported plan format is rejected.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing
+/// the plan command.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn plan_unknown_format() -> anyhow::Result<()> {
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.args(["plan", "--format", "yaml"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unsupported plan format"));
+    Ok(())
+}
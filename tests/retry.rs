@@ -0,0 +1,203 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use compiler_llvm_builder::retry::RetryPolicy;
+use rstest::rstest;
+
+/// Returns the policy with short delays.
+fn policy(max_attempts: u32, deadline: Duration) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        initial_backoff: Duration::from_millis(2),
+        max_backoff: Duration::from_millis(4),
+        deadline,
+    }
+}
+
+/// Tests retrying until the operation succeeds.
+///
+/// This test verifies that the failed attempts are retried and the result is returned.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// operation.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn success_after_failures() -> anyhow::Result<()> {
+    let mut attempts = 0;
+    let value = policy(5, Duration::from_secs(60)).run("Flaky operation", |_| {
+        attempts += 1;
+        if attempts < 3 {
+            anyhow::bail!("failure {attempts}");
+        }
+        Ok(attempts)
+    })?;
+    assert_eq!(value, 3);
+    Ok(())
+}
+
+/// Tests exhausting the attempts.
+///
+/// This test verifies that the operation is attempted the maximum number of times and that
+/// the error lists the failure of every attempt.
+#[rstest]
+fn attempts_exhausted() {
+    let mut attempts = 0;
+    let error = policy(3, Duration::from_secs(60))
+        .run("Broken operation", |_| -> anyhow::Result<()> {
+            attempts += 1;
+            anyhow::bail!("failure {attempts}")
+        })
+        .expect_err("Always fails")
+        .to_string();
+    assert_eq!(attempts, 3);
+    assert!(
+        error.starts_with("Broken operation failed after 3 attempt(s)"),
+        "{error}"
+    );
+    for attempt in 1..=3 {
+        assert!(
+            error.contains(format!("attempt {attempt}: failure {attempt}").as_str()),
+            "{error}"
+        );
+    }
+}
+
+/// Tests reaching the deadline.
+///
+/// This test verifies that no attempt is started after the deadline.
+#[rstest]
+fn deadline_reached() {
+    let mut attempts = 0;
+    let error = policy(10, Duration::ZERO)
+        .run("Slow operation", |_| -> anyhow::Result<()> {
+            attempts += 1;
+            anyhow::bail!("failure {attempts}")
+        })
+        .expect_err("Always fails")
+        .to_string();
+    assert_eq!(attempts, 1);
+    assert!(
+        error.contains("the deadline of 0s would be exceeded"),
+        "{error}"
+    );
+}
+
+/// Tests downloading from an unreachable server.
+///
+/// This test verifies that the download gives up after the configured attempts and leaves
+/// no partial file behind.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// download.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn download_unreachable() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let error = compiler_llvm_builder::utils::download(
+        "http://127.0.0.1:9/musl-1.2.3.tar.gz",
+        directory.path(),
+        "musl-1.2.3.tar.gz",
+        compiler_llvm_builder::utils::MUSL_SHA256,
+        &policy(2, Duration::from_secs(60)),
+        None,
+        None,
+    )
+    .expect_err("The server is unreachable")
+    .to_string();
+    assert!(error.contains("failed after 2 attempt(s)"), "{error}");
+    assert!(!directory.path().join("musl-1.2.3.tar.gz").exists());
+    Ok(())
+}
+
+/// Tests downloading from the mirrors accepting connections but never responding.
+///
+/// This test verifies that the request timeout is limited by the remaining deadline, and that
+/// the mirrors after the deadline are not tried.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// download.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn download_slow_mirror() -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    std::thread::spawn(move || {
+        let mut connections = Vec::new();
+        for connection in listener.incoming() {
+            connections.push(connection);
+        }
+    });
+    let upstream = format!("http://{address}/releases");
+    let mirrors = compiler_llvm_builder::mirrors::Mirrors::new(
+        vec![compiler_llvm_builder::mirrors::Mirror {
+            upstream: upstream.clone(),
+            urls: vec![format!("http://{address}/mirror")],
+        }],
+        vec![],
+    );
+    let url = format!("{upstream}/musl-1.2.3.tar.gz");
+
+    let directory = assert_fs::TempDir::new()?;
+    let started = Instant::now();
+    let error = compiler_llvm_builder::utils::download(
+        url.as_str(),
+        directory.path(),
+        "musl-1.2.3.tar.gz",
+        compiler_llvm_builder::utils::MUSL_SHA256,
+        &policy(5, Duration::from_secs(2)),
+        None,
+        Some(&mirrors),
+    )
+    .expect_err("The mirrors never respond")
+    .to_string();
+    assert!(started.elapsed() < Duration::from_secs(30), "{error}");
+    assert!(
+        error.contains("the deadline is exceeded before trying"),
+        "{error}"
+    );
+    assert!(!directory.path().join("musl-1.2.3.tar.gz").exists());
+    Ok(())
+}
+
+/// Tests the retry policy configuration.
+///
+/// This test verifies that the omitted fields fall back to the defaults.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while parsing the
+/// configuration file.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+fn parse() -> anyhow::Result<()> {
+    let config: compiler_llvm_builder::Config =
+        toml::from_str("[download]\nmax-attempts = 2\ndeadline = \"30s\"\n")?;
+    assert_eq!(
+        config.download,
+        RetryPolicy {
+            max_attempts: 2,
+            deadline: Duration::from_secs(30),
+            ..RetryPolicy::default()
+        }
+    );
+    assert!(toml::from_str::<compiler_llvm_builder::Config>("[download]\nretries = 2\n").is_err());
+    Ok(())
+}
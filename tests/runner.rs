@@ -0,0 +1,223 @@
+pub mod common;
+
+use compiler_llvm_builder::host::Arch;
+use compiler_llvm_builder::host::OS;
+use compiler_llvm_builder::plan::CommandStep;
+use compiler_llvm_builder::plan::StageSelection;
+use compiler_llvm_builder::runner::CommandError;
+use compiler_llvm_builder::runner::CommandRunner;
+use compiler_llvm_builder::runner::RealRunner;
+use compiler_llvm_builder::runner::RecordingRunner;
+use compiler_llvm_builder::runner::ReplayRunner;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::BuildOptions;
+use compiler_llvm_builder::Host;
+use compiler_llvm_builder::LLVMPath;
+use compiler_llvm_builder::Lock;
+
+/// Tests recording the build commands.
+///
+/// This test verifies that the build runs its commands through the injected runner
+/// and that the recording runner does not touch the file system.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn record_build() -> anyhow::Result<()> {
+    let mut runner = RecordingRunner::default();
+    compiler_llvm_builder::build(
+        BuildOptions::default(),
+        Host::new(Arch::X86_64, OS::Linux, TargetEnv::MUSL),
+        &LLVMPath::new(std::path::Path::new(".")),
+        &common::musl_source(),
+        &StageSelection::default(),
+        &mut runner,
+    )?;
+    let programs: Vec<&str> = runner
+        .commands
+        .iter()
+        .map(|command| command.program.as_str())
+        .collect();
+    assert_eq!(
+        programs,
+        vec![
+            "../configure",
+            "make",
+            "make",
+            "make",
+            "cmake",
+            "ninja",
+            "cmake",
+            "ninja",
+            "cmake",
+            "ninja"
+        ]
+    );
+    assert!(!std::path::Path::new(compiler_llvm_builder::LLVMPath::DIRECTORY_LLVM_TARGET).exists());
+    Ok(())
+}
+
+/// Tests replaying the recorded checkout commands.
+///
+/// This test verifies that the recording can be saved, loaded, and replayed, and that
+/// a diverging command sequence is rejected.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn replay_checkout() -> anyhow::Result<()> {
+    let lock = Lock {
+        url: "https://github.com/matter-labs/era-compiler-llvm".to_owned(),
+        branch: "main".to_owned(),
+        r#ref: Some("12345abcd".to_owned()),
+        source: None,
+        host: None,
+        musl: None,
+    };
+
+    let paths = LLVMPath::new(std::path::Path::new("."));
+
+    let mut recording = RecordingRunner::default();
+    compiler_llvm_builder::checkout(lock.clone(), true, &paths, &mut recording)?;
+    assert_eq!(recording.commands.len(), 4);
+    assert_eq!(
+        recording.commands[3].args,
+        vec!["checkout", "--force", "12345abcd"]
+    );
+
+    let file = assert_fs::NamedTempFile::new("recording.json")?;
+    recording.save(file.path())?;
+
+    let mut replay = ReplayRunner::load(file.path())?;
+    compiler_llvm_builder::checkout(lock.clone(), true, &paths, &mut replay)?;
+    replay.finish()?;
+
+    let mut replay = ReplayRunner::load(file.path())?;
+    assert!(compiler_llvm_builder::checkout(lock, false, &paths, &mut replay).is_err());
+    Ok(())
+}
+
+/// Tests the failed subprocess error.
+///
+/// This test verifies that the error carries the description, the command line, the exit
+/// code, and only the last lines of the output streams.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn command_error() -> anyhow::Result<()> {
+    let command = CommandStep::new("sh", "Failing script")
+        .arg("-c")
+        .arg("echo one; echo two; echo three; echo oops >&2; exit 3");
+    let mut runner = RealRunner::new(false);
+    runner.tail_lines = 2;
+
+    let error = runner
+        .run(&command)
+        .expect_err("The script must fail")
+        .downcast::<CommandError>()?;
+    assert_eq!(error.description, "Failing script");
+    assert_eq!(error.command, command.command_line());
+    assert_eq!(error.exit_code, Some(3));
+    assert_eq!(error.signal, None);
+    assert_eq!(error.stdout_tail, vec!["two", "three"]);
+    assert_eq!(error.stderr_tail, vec!["oops"]);
+
+    let rendered = error.to_string();
+    assert!(rendered.starts_with("Failing script failed with exit code 3"));
+    assert!(rendered.contains("    | oops"));
+    Ok(())
+}
+
+/// Tests reading the tool versions.
+///
+/// This test verifies that the tool exiting with an error on `--version`, like the `lld`
+/// generic driver, gets an empty version instead of failing the build.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[cfg(unix)]
+#[test]
+fn tool_versions() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = assert_fs::TempDir::new()?;
+    let lld = directory.path().join("lld");
+    std::fs::write(
+        lld.as_path(),
+        "#!/bin/sh\necho 'lld is a generic driver.' >&2\nexit 1\n",
+    )?;
+    std::fs::set_permissions(lld.as_path(), std::fs::Permissions::from_mode(0o755))?;
+
+    let plan = compiler_llvm_builder::Plan::new(&[lld.to_string_lossy().as_ref(), "git"]);
+    let versions = plan.tool_versions(&mut RealRunner::new(false))?;
+    assert_eq!(versions[0], "");
+    assert!(versions[1].starts_with("git version "), "{versions:?}");
+    Ok(())
+}
+
+/// Tests the per-command log files.
+///
+/// This test verifies that every command writes a numbered log file with the output and
+/// the timestamps, and that the failed command error points to its log file.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn command_logs() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let mut runner = RealRunner::new(false).log_directory(directory.path());
+
+    runner
+        .run(&CommandStep::new("sh", "LLVM host building cmake").args(["-c", "echo configured"]))?;
+    let error = runner
+        .run(
+            &CommandStep::new("sh", "Running ninja install")
+                .args(["-c", "echo broken >&2; exit 1"]),
+        )
+        .expect_err("The script must fail")
+        .downcast::<CommandError>()?;
+
+    let first = std::fs::read_to_string(directory.path().join("01-llvm-host-building-cmake.log"))?;
+    assert!(first.starts_with("# description: LLVM host building cmake\n"));
+    assert!(first.contains("\nconfigured\n"));
+    assert!(first.contains("# started: "));
+    assert!(first.contains("# finished: "));
+    assert!(first.contains("# duration: "));
+
+    let second_path = directory.path().join("02-running-ninja-install.log");
+    assert_eq!(error.log_path.as_deref(), Some(second_path.as_path()));
+    assert!(std::fs::read_to_string(second_path)?.contains("\nbroken\n"));
+    assert!(error.to_string().contains("  log: "));
+    Ok(())
+}
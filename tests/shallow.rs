@@ -0,0 +1,149 @@
+use compiler_llvm_builder::host::Arch;
+use compiler_llvm_builder::host::OS;
+use compiler_llvm_builder::runner::RecordingRunner;
+use compiler_llvm_builder::target_env::TargetEnv;
+use compiler_llvm_builder::Host;
+use compiler_llvm_builder::LLVMPath;
+use compiler_llvm_builder::Lock;
+
+/// The pinned test commit.
+const REF: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// Returns the runner simulating a shallow repository and a server that may reject
+/// fetching by hash.
+fn shallow_runner(fetch_by_hash: bool) -> RecordingRunner {
+    let runner = RecordingRunner::default();
+    let runner = if fetch_by_hash {
+        runner
+    } else {
+        runner.fail_on(
+            &["fetch", REF],
+            "Server does not allow request for unadvertised object",
+        )
+    };
+    runner.output_on(&[], "true\n")
+}
+
+/// Returns the fetch command arguments of the lock branch history with the depth.
+fn branch_fetch(depth: &str) -> Vec<&str> {
+    vec![
+        "fetch",
+        "--depth",
+        depth,
+        "origin",
+        "+refs/heads/main:refs/remotes/origin/main",
+    ]
+}
+
+/// Returns the lock pinned to the test commit.
+fn lock() -> Lock {
+    Lock {
+        url: "https://github.com/matter-labs/era-compiler-llvm".to_owned(),
+        branch: "main".to_owned(),
+        r#ref: Some(REF.to_owned()),
+        source: None,
+        host: None,
+        musl: None,
+    }
+}
+
+/// Tests the shallow clone of a pinned commit.
+///
+/// This test verifies that only the pinned commit is fetched on top of the shallow clone.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// clone and checkout commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn clone_shallow() -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let paths = LLVMPath::new(directory.path());
+
+    let mut runner = shallow_runner(true);
+    compiler_llvm_builder::clone(
+        lock(),
+        false,
+        Host::new(Arch::X86_64, OS::Linux, TargetEnv::GNU),
+        &paths,
+        &mut runner,
+    )?;
+    assert_eq!(runner.commands.len(), 3);
+    assert_eq!(
+        runner.commands[1].args,
+        vec!["fetch", "--depth", "1", "origin", REF]
+    );
+    assert_eq!(runner.commands[2].args, vec!["checkout", REF]);
+    Ok(())
+}
+
+/// Tests the checkout of a pinned commit in a shallow repository.
+///
+/// This test verifies that the repository stays shallow, that the branch history is deepened
+/// in steps if the server does not allow fetching by hash, and that the whole history is
+/// only fetched if the commit is not found in the branch history.
+///
+/// # Errors
+///
+/// Returns an error if any of the test assertions fail or if there is an error while executing the
+/// clone and checkout commands.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[test]
+fn checkout_shallow() -> anyhow::Result<()> {
+    let paths = LLVMPath::new(std::path::Path::new("."));
+
+    let mut runner = shallow_runner(true);
+    compiler_llvm_builder::checkout(lock(), false, &paths, &mut runner)?;
+    assert_eq!(
+        runner.commands[0].args,
+        vec!["fetch", "--depth", "1", "origin", REF]
+    );
+    assert_eq!(
+        runner.commands.last().map(|command| command.args.clone()),
+        Some(vec!["checkout".to_owned(), REF.to_owned()])
+    );
+
+    let mut runner = shallow_runner(false);
+    compiler_llvm_builder::checkout(lock(), false, &paths, &mut runner)?;
+    assert_eq!(runner.commands[1].args, branch_fetch("100"));
+    assert_eq!(
+        runner.commands.last().map(|command| command.args.clone()),
+        Some(vec!["checkout".to_owned(), REF.to_owned()])
+    );
+
+    let mut runner = RecordingRunner::default()
+        .fail_on(
+            &["fetch", REF],
+            "Server does not allow request for unadvertised object",
+        )
+        .fail_on(&["cat-file"], "Not a valid object name")
+        .output_on(&[], "true\n");
+    compiler_llvm_builder::checkout(lock(), false, &paths, &mut runner)?;
+    let fetches: Vec<Vec<String>> = runner.commands[1..5]
+        .iter()
+        .map(|command| command.args.clone())
+        .collect();
+    assert_eq!(
+        fetches,
+        vec![
+            branch_fetch("100"),
+            branch_fetch("1000"),
+            branch_fetch("10000"),
+            vec![
+                "fetch",
+                "--unshallow",
+                "--tags",
+                "origin",
+                "+refs/heads/*:refs/remotes/origin/*"
+            ],
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,111 @@
+pub mod common;
+
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use rstest::rstest;
+
+/// The placeholder of the working directory in the snapshots.
+const WORKING_DIRECTORY_PLACEHOLDER: &str = "<WORKDIR>";
+
+/// The fake `libstdc++.a` path required by the Windows plan.
+const LIBSTDCPP_SOURCE_PATH: &str = "/mingw64/lib/libstdc++.a";
+
+/// The number of the musl build jobs fixed to make the snapshots independent of the CPUs.
+const MUSL_JOBS: &str = "4";
+
+/// Tests the generated build commands against the golden snapshots.
+///
+/// This test verifies that the CMake, ninja, and auxiliary commands generated for every
+/// platform and option combination do not change unexpectedly. The plan is printed without
+/// running anything, so the test runs offline in seconds.
+///
+/// The working directory is replaced with a placeholder, and the number of the musl build
+/// jobs is fixed. The Xcode version is not probed, as the host is always specified. To accept
+/// intended changes, run the tests with `INSTA_UPDATE=always` and review the snapshot diff.
+///
+/// # Errors
+///
+/// Returns an error if the plan command fails or the snapshot does not match.
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the test passes.
+#[rstest]
+#[case::x86_64_linux_gnu("x86_64_linux_gnu", "x86_64-linux-gnu", &[])]
+#[case::x86_64_linux_gnu_sanitizer(
+    "x86_64_linux_gnu_sanitizer",
+    "x86_64-linux-gnu",
+    &["--sanitizer", "Address", "--enable-assertions"]
+)]
+#[case::x86_64_linux_gnu_valgrind(
+    "x86_64_linux_gnu_valgrind",
+    "x86_64-linux-gnu",
+    &["--enable-tests", "--enable-valgrind", "--valgrind-options=--leak-check=full"]
+)]
+#[case::x86_64_linux_gnu_coverage(
+    "x86_64_linux_gnu_coverage",
+    "x86_64-linux-gnu",
+    &["--build-type", "Debug", "--enable-coverage"]
+)]
+#[case::x86_64_linux_gnu_ccache(
+    "x86_64_linux_gnu_ccache",
+    "x86_64-linux-gnu",
+    &["--ccache-variant", "sccache"]
+)]
+#[case::x86_64_linux_gnu_extra(
+    "x86_64_linux_gnu_extra",
+    "x86_64-linux-gnu",
+    &[
+        "--llvm-projects", "clang",
+        "--enable-rtti",
+        "--default-target", "evm",
+        "--targets", "EVM",
+        "--extra-args", "\\-DLLVM_PARALLEL_LINK_JOBS=1",
+    ]
+)]
+#[case::x86_64_linux_musl("x86_64_linux_musl", "x86_64-linux-musl", &[])]
+#[case::x86_64_linux_musl_ccache(
+    "x86_64_linux_musl_ccache",
+    "x86_64-linux-musl",
+    &["--ccache-variant", "ccache", "--enable-assertions"]
+)]
+#[case::aarch64_linux_gnu("aarch64_linux_gnu", "aarch64-linux-gnu", &[])]
+#[case::aarch64_linux_musl("aarch64_linux_musl", "aarch64-linux-musl", &[])]
+#[case::x86_64_macos("x86_64_macos", "x86_64-macos", &[])]
+#[case::aarch64_macos("aarch64_macos", "aarch64-macos", &[])]
+#[case::aarch64_macos_sanitizer(
+    "aarch64_macos_sanitizer",
+    "aarch64-macos",
+    &["--sanitizer", "Address;Undefined"]
+)]
+#[case::x86_64_windows_gnu("x86_64_windows_gnu", "x86_64-windows-gnu", &[])]
+fn plan(#[case] name: &str, #[case] host: &str, #[case] args: &[&str]) -> anyhow::Result<()> {
+    let directory = assert_fs::TempDir::new()?;
+    let working_directory = directory.path().canonicalize()?;
+    common::create_musl_lockfile(working_directory.as_path())?;
+
+    let mut cmd = Command::cargo_bin(common::ZKSYNC_LLVM)?;
+    cmd.current_dir(working_directory.as_path());
+    cmd.env("LIBSTDCPP_SOURCE_PATH", LIBSTDCPP_SOURCE_PATH);
+    cmd.env_remove("DRY_RUN");
+    cmd.env_remove("VERBOSE");
+    cmd.args([
+        "plan",
+        "--format",
+        "json",
+        "--host",
+        host,
+        "--musl-jobs",
+        MUSL_JOBS,
+    ]);
+    cmd.args(args);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let plan = String::from_utf8(output)?.replace(
+        working_directory.to_string_lossy().as_ref(),
+        WORKING_DIRECTORY_PLACEHOLDER,
+    );
+    insta::assert_snapshot!(name, plan);
+    Ok(())
+}